@@ -5,6 +5,7 @@ use bytes::{Bytes, BytesMut};
 use futures::{Async, Poll, Stream};
 
 use crate::error::Error;
+use crate::header::HeaderMap;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 /// Body size hint
@@ -33,6 +34,14 @@ pub trait MessageBody {
     fn size(&self) -> BodySize;
 
     fn poll_next(&mut self) -> Poll<Option<Bytes>, Error>;
+
+    /// Trailing headers of the body.
+    ///
+    /// Called once after `poll_next()` returns `None`. Trailers are sent
+    /// only with chunked http/1.1 responses and with http/2 responses.
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        None
+    }
 }
 
 impl MessageBody for () {
@@ -53,6 +62,10 @@ impl<T: MessageBody> MessageBody for Box<T> {
     fn poll_next(&mut self) -> Poll<Option<Bytes>, Error> {
         self.as_mut().poll_next()
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        self.as_mut().trailers()
+    }
 }
 
 pub enum ResponseBody<B> {
@@ -99,6 +112,13 @@ impl<B: MessageBody> MessageBody for ResponseBody<B> {
             ResponseBody::Other(ref mut body) => body.poll_next(),
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        match self {
+            ResponseBody::Body(ref mut body) => body.trailers(),
+            ResponseBody::Other(ref mut body) => body.trailers(),
+        }
+    }
 }
 
 impl<B: MessageBody> Stream for ResponseBody<B> {
@@ -159,6 +179,13 @@ impl MessageBody for Body {
             Body::Message(ref mut body) => body.poll_next(),
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        match self {
+            Body::Message(ref mut body) => body.trailers(),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for Body {
@@ -1,17 +1,19 @@
 use std::fmt;
 use std::marker::PhantomData;
 use std::rc::Rc;
+use std::time::Duration;
 
 use actix_codec::Framed;
 use actix_server_config::ServerConfig as SrvConfig;
 use actix_service::{IntoNewService, NewService, Service};
 
 use crate::body::MessageBody;
-use crate::config::{KeepAlive, ServiceConfig};
+use crate::config::{ConnectionConfig, H1Config, H2Config, KeepAlive, ServiceConfig};
 use crate::error::Error;
 use crate::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::h2::H2Service;
 use crate::helpers::{Data, DataFactory};
+use crate::rate::DataRate;
 use crate::request::Request;
 use crate::response::Response;
 use crate::service::HttpService;
@@ -24,6 +26,9 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler<T>> {
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_disconnect: u64,
+    h1: H1Config,
+    h2: H2Config,
+    conn: ConnectionConfig,
     expect: X,
     upgrade: Option<U>,
     on_connect: Option<Rc<dyn Fn(&T) -> Box<dyn DataFactory>>>,
@@ -42,6 +47,9 @@ where
             keep_alive: KeepAlive::Timeout(5),
             client_timeout: 5000,
             client_disconnect: 0,
+            h1: H1Config::default(),
+            h2: H2Config::default(),
+            conn: ConnectionConfig::default(),
             expect: ExpectHandler,
             upgrade: None,
             on_connect: None,
@@ -101,6 +109,199 @@ where
         self
     }
 
+    /// Set maximum number of requests served by single connection.
+    ///
+    /// Http/1 response to the last request gets `Connection: close` header,
+    /// http/2 connection is closed gracefully with GOAWAY frame,
+    /// active streams get completed.
+    ///
+    /// To disable limit set value to 0. By default there is no limit.
+    pub fn max_requests_per_connection(mut self, val: usize) -> Self {
+        self.conn.max_requests = if val == 0 { None } else { Some(val) };
+        self
+    }
+
+    /// Set maximum connection lifetime in milliseconds.
+    ///
+    /// Http/1 response sent after connection reaches this age gets
+    /// `Connection: close` header, idle http/1 connection is closed.
+    /// Http/2 connection is closed gracefully with GOAWAY frame,
+    /// active streams get completed.
+    ///
+    /// To disable limit set value to 0. By default there is no limit.
+    pub fn max_connection_age(mut self, val: u64) -> Self {
+        self.conn.max_age = if val == 0 {
+            None
+        } else {
+            Some(Duration::from_millis(val))
+        };
+        self
+    }
+
+    /// Set minimum request body data rate.
+    ///
+    /// Connection is dropped if client sends request body slower than
+    /// `bytes_per_sec` bytes per second. Rate is not checked during
+    /// `grace_period` (in milliseconds) and while application does not
+    /// read request payload. For http/2 connections request payload stream
+    /// returns error.
+    ///
+    /// To disable check set `bytes_per_sec` to 0. By default minimum
+    /// rate is not enforced.
+    pub fn min_request_data_rate(
+        mut self,
+        bytes_per_sec: u64,
+        grace_period: u64,
+    ) -> Self {
+        self.conn.min_request_rate = DataRate::new(bytes_per_sec, grace_period);
+        self
+    }
+
+    /// Set minimum response data rate.
+    ///
+    /// Connection is dropped if client reads response slower than
+    /// `bytes_per_sec` bytes per second. Rate is not checked during
+    /// `grace_period` (in milliseconds) and while response body is not
+    /// ready. For http/2 connections response stream gets reset.
+    ///
+    /// To disable check set `bytes_per_sec` to 0. By default minimum
+    /// rate is not enforced.
+    pub fn min_response_data_rate(
+        mut self,
+        bytes_per_sec: u64,
+        grace_period: u64,
+    ) -> Self {
+        self.conn.min_response_rate = DataRate::new(bytes_per_sec, grace_period);
+        self
+    }
+
+    /// Set maximum number of headers in http/1 request.
+    ///
+    /// Request with more headers is rejected with
+    /// `431 Request Header Fields Too Large` response.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn h1_max_headers(mut self, val: usize) -> Self {
+        self.h1.max_headers = val;
+        self
+    }
+
+    /// Set maximum size of http/1 request head, request line and headers.
+    ///
+    /// Request with larger head is rejected with
+    /// `431 Request Header Fields Too Large` response.
+    ///
+    /// By default max size is set to 131,072 bytes.
+    pub fn h1_max_header_block_size(mut self, val: usize) -> Self {
+        self.h1.max_header_block_size = val;
+        self
+    }
+
+    /// Set maximum length of http/1 request uri.
+    ///
+    /// Request with longer uri is rejected with `414 URI Too Long` response.
+    ///
+    /// By default max length is set to 65,536 bytes.
+    pub fn h1_max_uri_length(mut self, val: usize) -> Self {
+        self.h1.max_uri_length = val;
+        self
+    }
+
+    /// Set maximum size of single http/1 request header value.
+    ///
+    /// Request with larger header value is rejected with
+    /// `431 Request Header Fields Too Large` response.
+    ///
+    /// By default max size is set to 65,536 bytes.
+    pub fn h1_max_header_value_size(mut self, val: usize) -> Self {
+        self.h1.max_header_value_size = val;
+        self
+    }
+
+    /// Set maximum number of concurrent streams of http/2 connection.
+    ///
+    /// By default there is no limit.
+    pub fn h2_max_concurrent_streams(mut self, val: u32) -> Self {
+        self.h2.max_concurrent_streams = Some(val);
+        self
+    }
+
+    /// Set initial http/2 stream-level flow control window size.
+    ///
+    /// By default window size is set to 65,535 bytes.
+    pub fn h2_initial_window_size(mut self, val: u32) -> Self {
+        self.h2.initial_window_size = Some(val);
+        self
+    }
+
+    /// Set initial http/2 connection-level flow control window size.
+    ///
+    /// By default window size is set to 65,535 bytes.
+    pub fn h2_initial_connection_window_size(mut self, val: u32) -> Self {
+        self.h2.initial_connection_window_size = Some(val);
+        self
+    }
+
+    /// Set maximum http/2 frame size the server is willing to receive.
+    ///
+    /// Value must be between 16,384 and 16,777,215 bytes.
+    /// By default max frame size is set to 16,384 bytes.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if value is out of range.
+    pub fn h2_max_frame_size(mut self, val: u32) -> Self {
+        assert!(
+            val >= 16_384 && val <= 16_777_215,
+            "http/2 max frame size must be between 16384 and 16777215, got {}",
+            val
+        );
+        self.h2.max_frame_size = Some(val);
+        self
+    }
+
+    /// Set maximum size of header list the server is willing to accept
+    /// on http/2 connection.
+    ///
+    /// By default there is no limit.
+    pub fn h2_max_header_list_size(mut self, val: u32) -> Self {
+        self.h2.max_header_list_size = Some(val);
+        self
+    }
+
+    /// Set http/2 connection keep-alive setting.
+    ///
+    /// Http/2 connection without active streams is closed gracefully
+    /// after keep-alive timeout. `KeepAlive::Disabled` closes connection
+    /// as soon as all streams are complete, `KeepAlive::Os` never closes
+    /// idle connections.
+    ///
+    /// By default server keep-alive setting is used.
+    pub fn h2_keep_alive<W: Into<KeepAlive>>(mut self, val: W) -> Self {
+        self.h2.keep_alive = Some(val.into());
+        self
+    }
+
+    /// Set http/2 keep-alive PING interval in milliseconds.
+    ///
+    /// Server sends PING frame with this interval and closes connection
+    /// if client does not acknowledge it within PING timeout.
+    ///
+    /// To disable PING frames set value to 0. By default PING frames
+    /// are disabled.
+    pub fn h2_ping_interval(mut self, val: u64) -> Self {
+        self.h2.ping_interval = val;
+        self
+    }
+
+    /// Set http/2 keep-alive PING timeout in milliseconds.
+    ///
+    /// By default PING timeout is set to 20000 milliseconds.
+    pub fn h2_ping_timeout(mut self, val: u64) -> Self {
+        self.h2.ping_timeout = val;
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -117,6 +318,9 @@ where
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
+            h1: self.h1,
+            h2: self.h2,
+            conn: self.conn,
             expect: expect.into_new_service(),
             upgrade: self.upgrade,
             on_connect: self.on_connect,
@@ -143,6 +347,9 @@ where
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
             client_disconnect: self.client_disconnect,
+            h1: self.h1,
+            h2: self.h2,
+            conn: self.conn,
             expect: self.expect,
             upgrade: Some(upgrade.into_new_service()),
             on_connect: self.on_connect,
@@ -153,13 +360,23 @@ where
     /// Set on-connect callback.
     ///
     /// It get called once per connection and result of the call
-    /// get stored to the request's extensions.
+    /// get stored to the request's extensions. Multiple callbacks
+    /// could be registered, results of all callbacks get stored.
     pub fn on_connect<F, I>(mut self, f: F) -> Self
     where
         F: Fn(&T) -> I + 'static,
         I: Clone + 'static,
+        T: 'static,
     {
-        self.on_connect = Some(Rc::new(move |io| Box::new(Data(f(io)))));
+        let on_connect: Rc<dyn Fn(&T) -> Box<dyn DataFactory>> =
+            if let Some(prev) = self.on_connect.take() {
+                Rc::new(move |io: &T| -> Box<dyn DataFactory> {
+                    Box::new((prev(io), Data(f(io))))
+                })
+            } else {
+                Rc::new(move |io: &T| -> Box<dyn DataFactory> { Box::new(Data(f(io))) })
+            };
+        self.on_connect = Some(on_connect);
         self
     }
 
@@ -172,10 +389,13 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_settings(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
+            self.h1,
+            self.h2,
+            self.conn,
         );
         H1Service::with_config(cfg, service.into_new_service())
             .expect(self.expect)
@@ -193,12 +413,16 @@ where
         S::Response: Into<Response<B>>,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_settings(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
+            self.h1,
+            self.h2,
+            self.conn,
         );
         H2Service::with_config(cfg, service.into_new_service())
+            .on_connect(self.on_connect)
     }
 
     /// Finish service configuration and create `HttpService` instance.
@@ -211,10 +435,13 @@ where
         S::Response: Into<Response<B>>,
         <S::Service as Service>::Future: 'static,
     {
-        let cfg = ServiceConfig::new(
+        let cfg = ServiceConfig::with_settings(
             self.keep_alive,
             self.client_timeout,
             self.client_disconnect,
+            self.h1,
+            self.h2,
+            self.conn,
         );
         HttpService::with_config(cfg, service.into_new_service())
             .expect(self.expect)
@@ -9,6 +9,8 @@ use futures::{future, Future};
 use time;
 use tokio_timer::{sleep, Delay};
 
+use crate::rate::DataRate;
+
 // "Sun, 06 Nov 1994 08:49:37 GMT".len()
 const DATE_VALUE_LENGTH: usize = 29;
 
@@ -39,6 +41,63 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+/// HTTP/1 request parser limits
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct H1Config {
+    pub(crate) max_headers: usize,
+    pub(crate) max_header_block_size: usize,
+    pub(crate) max_uri_length: usize,
+    pub(crate) max_header_value_size: usize,
+}
+
+impl Default for H1Config {
+    fn default() -> Self {
+        H1Config {
+            max_headers: 96,
+            max_header_block_size: 131_072,
+            max_uri_length: 65_536,
+            max_header_value_size: 65_536,
+        }
+    }
+}
+
+/// HTTP/2 connection settings
+#[derive(Debug, Clone)]
+pub(crate) struct H2Config {
+    pub(crate) max_concurrent_streams: Option<u32>,
+    pub(crate) initial_window_size: Option<u32>,
+    pub(crate) initial_connection_window_size: Option<u32>,
+    pub(crate) max_frame_size: Option<u32>,
+    pub(crate) max_header_list_size: Option<u32>,
+    pub(crate) keep_alive: Option<KeepAlive>,
+    pub(crate) ping_interval: u64,
+    pub(crate) ping_timeout: u64,
+}
+
+impl Default for H2Config {
+    fn default() -> Self {
+        H2Config {
+            max_concurrent_streams: None,
+            initial_window_size: None,
+            initial_connection_window_size: None,
+            max_frame_size: None,
+            max_header_list_size: None,
+            keep_alive: None,
+            ping_interval: 0,
+            ping_timeout: 20_000,
+        }
+    }
+}
+
+/// Connection level settings shared by http/1 and http/2 dispatchers
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectionConfig {
+    pub(crate) min_request_rate: Option<DataRate>,
+    pub(crate) min_response_rate: Option<DataRate>,
+    pub(crate) max_requests: Option<usize>,
+    pub(crate) max_age: Option<Duration>,
+}
+
 /// Http service configuration
 pub struct ServiceConfig(Rc<Inner>);
 
@@ -47,6 +106,9 @@ struct Inner {
     client_timeout: u64,
     client_disconnect: u64,
     ka_enabled: bool,
+    h1: H1Config,
+    h2: H2Config,
+    conn: ConnectionConfig,
     timer: DateService,
 }
 
@@ -68,6 +130,26 @@ impl ServiceConfig {
         keep_alive: KeepAlive,
         client_timeout: u64,
         client_disconnect: u64,
+    ) -> ServiceConfig {
+        ServiceConfig::with_settings(
+            keep_alive,
+            client_timeout,
+            client_disconnect,
+            H1Config::default(),
+            H2Config::default(),
+            ConnectionConfig::default(),
+        )
+    }
+
+    /// Create instance of `ServiceConfig` with custom http/1 limits,
+    /// http/2 and connection settings
+    pub(crate) fn with_settings(
+        keep_alive: KeepAlive,
+        client_timeout: u64,
+        client_disconnect: u64,
+        h1: H1Config,
+        h2: H2Config,
+        conn: ConnectionConfig,
     ) -> ServiceConfig {
         let (keep_alive, ka_enabled) = match keep_alive {
             KeepAlive::Timeout(val) => (val as u64, true),
@@ -85,6 +167,9 @@ impl ServiceConfig {
             ka_enabled,
             client_timeout,
             client_disconnect,
+            h1,
+            h2,
+            conn,
             timer: DateService::new(),
         }))
     }
@@ -153,6 +238,84 @@ impl ServiceConfig {
         }
     }
 
+    /// Http/1 request parser limits.
+    pub(crate) fn h1_limits(&self) -> H1Config {
+        self.0.h1
+    }
+
+    /// Max number of requests served by single connection, if configured.
+    pub fn max_requests_per_connection(&self) -> Option<usize> {
+        self.0.conn.max_requests
+    }
+
+    /// Max connection lifetime, if configured.
+    pub fn max_connection_age(&self) -> Option<Duration> {
+        self.0.conn.max_age
+    }
+
+    /// Minimum request body data rate.
+    pub(crate) fn min_request_rate(&self) -> Option<DataRate> {
+        self.0.conn.min_request_rate
+    }
+
+    /// Minimum response data rate.
+    pub(crate) fn min_response_rate(&self) -> Option<DataRate> {
+        self.0.conn.min_response_rate
+    }
+
+    /// Http/2 connection builder with configured settings.
+    pub(crate) fn h2_builder(&self) -> h2::server::Builder {
+        let h2 = &self.0.h2;
+        let mut builder = h2::server::Builder::new();
+        if let Some(val) = h2.max_concurrent_streams {
+            builder.max_concurrent_streams(val);
+        }
+        if let Some(val) = h2.initial_window_size {
+            builder.initial_window_size(val);
+        }
+        if let Some(val) = h2.initial_connection_window_size {
+            builder.initial_connection_window_size(val);
+        }
+        if let Some(val) = h2.max_frame_size {
+            builder.max_frame_size(val);
+        }
+        if let Some(val) = h2.max_header_list_size {
+            builder.max_header_list_size(val);
+        }
+        builder
+    }
+
+    /// Http/2 connection idle timeout.
+    ///
+    /// Returns `None` if idle connections should not be closed and
+    /// `Some(Duration::from_secs(0))` if connection should be closed
+    /// as soon as all streams are complete.
+    pub(crate) fn h2_idle_timeout(&self) -> Option<Duration> {
+        if let Some(ka) = self.0.h2.keep_alive {
+            match ka {
+                KeepAlive::Timeout(0) | KeepAlive::Os => None,
+                KeepAlive::Timeout(val) => Some(Duration::from_secs(val as u64)),
+                KeepAlive::Disabled => Some(Duration::from_secs(0)),
+            }
+        } else if !self.0.ka_enabled {
+            Some(Duration::from_secs(0))
+        } else {
+            self.0.keep_alive
+        }
+    }
+
+    /// Http/2 PING interval and timeout, if keep-alive pings are enabled.
+    pub(crate) fn h2_ping(&self) -> Option<(Duration, Duration)> {
+        if self.0.h2.ping_interval != 0 {
+            Some((
+                Duration::from_millis(self.0.h2.ping_interval),
+                Duration::from_millis(self.0.h2.ping_timeout),
+            ))
+        } else {
+            None
+        }
+    }
+
     #[inline]
     pub(crate) fn now(&self) -> Instant {
         self.0.timer.now()
@@ -287,4 +450,30 @@ mod tests {
             future::ok::<_, ()>(())
         }));
     }
+    #[test]
+    fn test_h2_idle_timeout() {
+        let cfg = ServiceConfig::new(KeepAlive::Timeout(5), 0, 0);
+        assert_eq!(cfg.h2_idle_timeout(), Some(Duration::from_secs(5)));
+        assert!(cfg.h2_ping().is_none());
+
+        let cfg = ServiceConfig::new(KeepAlive::Disabled, 0, 0);
+        assert_eq!(cfg.h2_idle_timeout(), Some(Duration::from_secs(0)));
+
+        let mut h2 = H2Config::default();
+        h2.keep_alive = Some(KeepAlive::Os);
+        h2.ping_interval = 1000;
+        let cfg = ServiceConfig::with_settings(
+            KeepAlive::Timeout(5),
+            0,
+            0,
+            H1Config::default(),
+            h2,
+            ConnectionConfig::default(),
+        );
+        assert_eq!(cfg.h2_idle_timeout(), None);
+        assert_eq!(
+            cfg.h2_ping(),
+            Some((Duration::from_millis(1000), Duration::from_millis(20_000)))
+        );
+    }
 }
@@ -720,7 +720,7 @@ impl<'c> Cookie<'c> {
     pub fn make_permanent(&mut self) {
         let twenty_years = Duration::days(365 * 20);
         self.set_max_age(twenty_years);
-        self.set_expires(time::now() + twenty_years);
+        self.set_expires(time::now() + time::Duration::days(365 * 20));
     }
 
     fn fmt_parameters(&self, f: &mut fmt::Formatter) -> fmt::Result {
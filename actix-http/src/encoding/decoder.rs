@@ -1,24 +1,35 @@
 use std::io::{self, Write};
 
-use actix_threadpool::{run, CpuFuture};
+use actix_threadpool::{run, BlockingError, CpuFuture};
 #[cfg(feature = "brotli")]
 use brotli2::write::BrotliDecoder;
 use bytes::Bytes;
 #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
 use flate2::write::{GzDecoder, ZlibDecoder};
 use futures::{try_ready, Async, Future, Poll, Stream};
+#[cfg(feature = "zstd")]
+use zstd::stream::write::Decoder as ZstdDecoder;
 
-use super::Writer;
+use super::{LimitExceeded, Writer};
 use crate::error::PayloadError;
 use crate::http::header::{ContentEncoding, HeaderMap, CONTENT_ENCODING};
 
 const INPLACE: usize = 2049;
 
+/// Compression ratio is not checked until decompressed payload reaches
+/// this size, small payloads could have high ratio.
+const RATIO_THRESHOLD: usize = 65_536;
+
 pub struct Decoder<S> {
     decoder: Option<ContentDecoder>,
     stream: S,
     eof: bool,
     fut: Option<CpuFuture<(Option<Bytes>, ContentDecoder), io::Error>>,
+    error: Option<io::Error>,
+    max_size: Option<usize>,
+    max_ratio: Option<usize>,
+    compressed: usize,
+    decompressed: usize,
 }
 
 impl<S> Decoder<S>
@@ -28,6 +39,7 @@ where
     /// Construct a decoder.
     #[inline]
     pub fn new(stream: S, encoding: ContentEncoding) -> Decoder<S> {
+        let mut error = None;
         let decoder = match encoding {
             #[cfg(feature = "brotli")]
             ContentEncoding::Br => Some(ContentDecoder::Br(Box::new(
@@ -41,13 +53,27 @@ where
             ContentEncoding::Gzip => Some(ContentDecoder::Gzip(Box::new(
                 GzDecoder::new(Writer::new()),
             ))),
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => match ZstdDecoder::new(Writer::new()) {
+                Ok(decoder) => Some(ContentDecoder::Zstd(Box::new(decoder))),
+                Err(err) => {
+                    // do not pass compressed payload through as is
+                    error = Some(err);
+                    None
+                }
+            },
             _ => None,
         };
         Decoder {
             decoder,
             stream,
             fut: None,
+            error,
             eof: false,
+            max_size: None,
+            max_ratio: None,
+            compressed: 0,
+            decompressed: 0,
         }
     }
 
@@ -67,6 +93,57 @@ where
 
         Self::new(stream, encoding)
     }
+
+    /// Set max size of decompressed payload.
+    ///
+    /// If decompressed payload exceeds this size,
+    /// `PayloadError::DecompressionLimit` get returned. By default size is
+    /// not limited.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Set max ratio between decompressed and compressed payload sizes.
+    ///
+    /// Ratio is enforced only after decompressed payload reaches 64Kb.
+    /// If ratio is exceeded, `PayloadError::DecompressionLimit` get returned.
+    /// By default ratio is not limited.
+    pub fn max_ratio(mut self, ratio: usize) -> Self {
+        self.max_ratio = Some(ratio);
+        self
+    }
+
+    /// Number of bytes decoder is allowed to produce
+    fn allowance(&self) -> Option<usize> {
+        let ratio = self.max_ratio.map(|ratio| {
+            std::cmp::max(self.compressed.saturating_mul(ratio), RATIO_THRESHOLD)
+        });
+        let limit = match (self.max_size, ratio) {
+            (Some(size), Some(ratio)) => Some(std::cmp::min(size, ratio)),
+            (size, ratio) => size.or(ratio),
+        };
+        limit.map(|limit| limit.saturating_sub(self.decompressed))
+    }
+
+    fn decoded(&mut self, chunk: Option<Bytes>) -> Option<Bytes> {
+        if let Some(ref chunk) = chunk {
+            self.decompressed += chunk.len();
+        }
+        chunk
+    }
+}
+
+fn payload_error(err: io::Error) -> PayloadError {
+    let limit = err
+        .get_ref()
+        .map(|e| e.is::<LimitExceeded>())
+        .unwrap_or(false);
+    if limit {
+        PayloadError::DecompressionLimit
+    } else {
+        err.into()
+    }
 }
 
 impl<S> Stream for Decoder<S>
@@ -77,12 +154,20 @@ where
     type Error = PayloadError;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(err) = self.error.take() {
+            self.eof = true;
+            return Err(err.into());
+        }
+
         loop {
             if let Some(ref mut fut) = self.fut {
-                let (chunk, decoder) = try_ready!(fut.poll());
+                let (chunk, decoder) = try_ready!(fut.poll().map_err(|e| match e {
+                    BlockingError::Error(e) => payload_error(e),
+                    e => e.into(),
+                }));
                 self.decoder = Some(decoder);
                 self.fut.take();
-                if let Some(chunk) = chunk {
+                if let Some(chunk) = self.decoded(chunk) {
                     return Ok(Async::Ready(Some(chunk)));
                 }
             }
@@ -94,10 +179,14 @@ where
             match self.stream.poll()? {
                 Async::Ready(Some(chunk)) => {
                     if let Some(mut decoder) = self.decoder.take() {
+                        self.compressed += chunk.len();
+                        decoder.set_limit(self.allowance());
+
                         if chunk.len() < INPLACE {
-                            let chunk = decoder.feed_data(chunk)?;
+                            let chunk =
+                                decoder.feed_data(chunk).map_err(payload_error)?;
                             self.decoder = Some(decoder);
-                            if let Some(chunk) = chunk {
+                            if let Some(chunk) = self.decoded(chunk) {
                                 return Ok(Async::Ready(Some(chunk)));
                             }
                         } else {
@@ -114,7 +203,9 @@ where
                 Async::Ready(None) => {
                     self.eof = true;
                     return if let Some(mut decoder) = self.decoder.take() {
-                        Ok(Async::Ready(decoder.feed_eof()?))
+                        decoder.set_limit(self.allowance());
+                        let chunk = decoder.feed_eof().map_err(payload_error)?;
+                        Ok(Async::Ready(self.decoded(chunk)))
                     } else {
                         Ok(Async::Ready(None))
                     };
@@ -133,9 +224,28 @@ enum ContentDecoder {
     Gzip(Box<GzDecoder<Writer>>),
     #[cfg(feature = "brotli")]
     Br(Box<BrotliDecoder<Writer>>),
+    #[cfg(feature = "zstd")]
+    Zstd(Box<ZstdDecoder<Writer>>),
 }
 
 impl ContentDecoder {
+    #[allow(unreachable_patterns)]
+    fn set_limit(&mut self, limit: Option<usize>) {
+        match self {
+            #[cfg(feature = "brotli")]
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut().set_limit(limit),
+            #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut().set_limit(limit),
+            #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+            ContentDecoder::Deflate(ref mut decoder) => {
+                decoder.get_mut().set_limit(limit)
+            }
+            #[cfg(feature = "zstd")]
+            ContentDecoder::Zstd(ref mut decoder) => decoder.get_mut().set_limit(limit),
+            _ => (),
+        }
+    }
+
     #[allow(unreachable_patterns)]
     fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
         match self {
@@ -175,6 +285,18 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            #[cfg(feature = "zstd")]
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.flush() {
+                Ok(_) => {
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
             _ => Ok(None),
         }
     }
@@ -221,6 +343,19 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            #[cfg(feature = "zstd")]
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.write_all(&data) {
+                Ok(_) => {
+                    decoder.flush()?;
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
             _ => Ok(Some(data)),
         }
     }
@@ -8,8 +8,11 @@ use bytes::Bytes;
 #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
 use flate2::write::{GzEncoder, ZlibEncoder};
 use futures::{Async, Future, Poll};
+#[cfg(feature = "zstd")]
+use zstd::stream::write::Encoder as ZstdEncoder;
 
 use crate::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::header::HeaderMap;
 use crate::http::header::{ContentEncoding, CONTENT_ENCODING};
 use crate::http::{HeaderValue, HttpTryFrom, StatusCode};
 use crate::{Error, ResponseHead};
@@ -30,6 +33,19 @@ impl<B: MessageBody> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<Encoder<B>> {
+        Encoder::response_with_level(encoding, None, head, body)
+    }
+
+    /// Encode response body with specific compression level.
+    ///
+    /// Level is clamped to the range supported by encoding algorithm,
+    /// default level is used if `level` is `None`.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<Encoder<B>> {
         let can_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
             || head.status == StatusCode::SWITCHING_PROTOCOLS
@@ -55,7 +71,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if can_encode {
             // Modify response body only if encoder is not None
-            if let Some(enc) = ContentEncoder::encoder(encoding) {
+            if let Some(enc) = ContentEncoder::encoder(encoding, level) {
                 update_head(encoding, head);
                 head.no_chunking(false);
                 return ResponseBody::Body(Encoder {
@@ -158,6 +174,22 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
             }
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        match self.body {
+            EncoderBody::Bytes(_) => None,
+            EncoderBody::Stream(ref mut b) => b.trailers(),
+            EncoderBody::BoxedStream(ref mut b) => b.trailers(),
+        }
+    }
+}
+
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+fn flate2_level(level: Option<u32>) -> flate2::Compression {
+    match level {
+        Some(level) => flate2::Compression::new(level.min(9)),
+        None => flate2::Compression::fast(),
+    }
 }
 
 fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
@@ -174,24 +206,35 @@ enum ContentEncoder {
     Gzip(GzEncoder<Writer>),
     #[cfg(feature = "brotli")]
     Br(BrotliEncoder<Writer>),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdEncoder<Writer>),
 }
 
 impl ContentEncoder {
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    #[allow(unused_variables)]
+    fn encoder(encoding: ContentEncoding, level: Option<u32>) -> Option<Self> {
         match encoding {
             #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2_level(level),
             ))),
             #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2_level(level),
             ))),
             #[cfg(feature = "brotli")]
             ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
+                let level = level.map(|l| l.min(11)).unwrap_or(3);
+                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), level)))
+            }
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => {
+                let level = level.map(|l| l.max(1).min(21)).unwrap_or(3);
+                ZstdEncoder::new(Writer::new(), level as i32)
+                    .map(ContentEncoder::Zstd)
+                    .ok()
             }
             _ => None,
         }
@@ -206,6 +249,8 @@ impl ContentEncoder {
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(ref mut encoder) => encoder.get_mut().take(),
         }
     }
 
@@ -226,6 +271,11 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(encoder) => match encoder.finish() {
+                Ok(writer) => Ok(writer.buf.freeze()),
+                Err(err) => Err(err),
+            },
         }
     }
 
@@ -255,6 +305,14 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(ref mut encoder) => match encoder.write_all(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    trace!("Error decoding zstd encoding: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
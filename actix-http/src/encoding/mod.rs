@@ -1,5 +1,5 @@
 //! Content-Encoding support
-use std::io;
+use std::{fmt, io};
 
 use bytes::{Bytes, BytesMut};
 
@@ -11,21 +11,32 @@ pub use self::encoder::Encoder;
 
 pub(self) struct Writer {
     buf: BytesMut,
+    limit: Option<usize>,
 }
 
 impl Writer {
     fn new() -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit: None,
         }
     }
     fn take(&mut self) -> Bytes {
         self.buf.take().freeze()
     }
+    /// Set max number of bytes that could be buffered
+    fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
 }
 
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(limit) = self.limit {
+            if self.buf.len() + buf.len() > limit {
+                return Err(io::Error::new(io::ErrorKind::Other, LimitExceeded));
+            }
+        }
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
@@ -33,3 +44,15 @@ impl io::Write for Writer {
         Ok(())
     }
 }
+
+/// Writer limit is reached
+#[derive(Debug)]
+pub(self) struct LimitExceeded;
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Decompressed payload limit is reached")
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
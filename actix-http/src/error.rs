@@ -249,6 +249,12 @@ pub enum ParseError {
     /// A message head is too large to be reasonable.
     #[display(fmt = "Message head is too large")]
     TooLarge,
+    /// A request uri is longer than configured limit.
+    #[display(fmt = "Uri is too long")]
+    UriTooLong,
+    /// Too much unprocessed data follows a message without payload.
+    #[display(fmt = "Too much unprocessed data")]
+    BufferOverflow,
     /// A message reached EOF, but is not complete.
     #[display(fmt = "Message is incomplete")]
     Incomplete,
@@ -268,10 +274,17 @@ pub enum ParseError {
     Utf8(Utf8Error),
 }
 
-/// Return `BadRequest` for `ParseError`
+/// Return `BadRequest` for `ParseError`, `RequestHeaderFieldsTooLarge`
+/// for too large message head and `UriTooLong` for too long uri
 impl ResponseError for ParseError {
     fn error_response(&self) -> Response {
-        Response::new(StatusCode::BAD_REQUEST)
+        match *self {
+            ParseError::TooLarge => {
+                Response::new(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+            }
+            ParseError::UriTooLong => Response::new(StatusCode::URI_TOO_LONG),
+            _ => Response::new(StatusCode::BAD_REQUEST),
+        }
     }
 }
 
@@ -328,6 +341,9 @@ pub enum PayloadError {
     /// A payload reached size limit.
     #[display(fmt = "A payload reached size limit.")]
     Overflow,
+    /// Decompressed payload reached size limit or compression ratio limit.
+    #[display(fmt = "A decompressed payload reached size limit.")]
+    DecompressionLimit,
     /// A payload length is unknown.
     #[display(fmt = "A payload length is unknown.")]
     UnknownLength,
@@ -371,12 +387,14 @@ impl From<BlockingError<io::Error>> for PayloadError {
 
 /// `PayloadError` returns two possible results:
 ///
-/// - `Overflow` returns `PayloadTooLarge`
+/// - `Overflow` and `DecompressionLimit` return `PayloadTooLarge`
 /// - Other errors returns `BadRequest`
 impl ResponseError for PayloadError {
     fn error_response(&self) -> Response {
         match *self {
-            PayloadError::Overflow => Response::new(StatusCode::PAYLOAD_TOO_LARGE),
+            PayloadError::Overflow | PayloadError::DecompressionLimit => {
+                Response::new(StatusCode::PAYLOAD_TOO_LARGE)
+            }
             _ => Response::new(StatusCode::BAD_REQUEST),
         }
     }
@@ -419,6 +437,14 @@ pub enum DispatchError {
     #[display(fmt = "Connection shutdown timeout")]
     DisconnectTimeout,
 
+    /// Http/2 keep-alive PING was not acknowledged within the specified timeout.
+    #[display(fmt = "Keep-alive ping timeout")]
+    PingTimeout,
+
+    /// Client sends request body or reads response slower than minimum data rate.
+    #[display(fmt = "Client data rate is below minimum")]
+    SlowClient,
+
     /// Payload is not consumed
     #[display(fmt = "Task is completed but request's payload is not consumed")]
     PayloadIsNotConsumed,
@@ -969,6 +995,12 @@ mod tests {
     fn test_into_response() {
         let resp: Response = ParseError::Incomplete.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp: Response = ParseError::TooLarge.error_response();
+        assert_eq!(resp.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+        let resp: Response = ParseError::UriTooLong.error_response();
+        assert_eq!(resp.status(), StatusCode::URI_TOO_LONG);
+        let resp: Response = ParseError::BufferOverflow.error_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
         let err: HttpError = StatusCode::from_u16(10000).err().unwrap().into();
         let resp: Response = err.error_response();
@@ -1023,6 +1055,9 @@ mod tests {
             format!("{}", err),
             "A payload reached EOF, but is not complete. With error: None"
         );
+
+        let resp = PayloadError::DecompressionLimit.error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 
     macro_rules! from {
@@ -14,6 +14,7 @@ use super::{Message, MessageType};
 use crate::body::BodySize;
 use crate::config::ServiceConfig;
 use crate::error::ParseError;
+use crate::header::HeaderMap;
 use crate::helpers;
 use crate::message::{ConnectionType, Head, ResponseHead};
 use crate::request::Request;
@@ -34,6 +35,7 @@ pub struct Codec {
     config: ServiceConfig,
     decoder: decoder::MessageDecoder<Request>,
     payload: Option<PayloadDecoder>,
+    trailers: Option<HeaderMap>,
     version: Version,
     ctype: ConnectionType,
 
@@ -64,11 +66,13 @@ impl Codec {
         } else {
             Flags::empty()
         };
+        let decoder = decoder::MessageDecoder::new(config.h1_limits());
         Codec {
             config,
             flags,
-            decoder: decoder::MessageDecoder::default(),
+            decoder,
             payload: None,
+            trailers: None,
             version: Version::HTTP_11,
             ctype: ConnectionType::Close,
             encoder: encoder::MessageEncoder::default(),
@@ -109,6 +113,20 @@ impl Codec {
     pub fn config(&self) -> &ServiceConfig {
         &self.config
     }
+
+    /// Trailers of the last request's chunked payload
+    pub(crate) fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
+    }
+
+    /// Encode end of response payload with trailing headers
+    pub(crate) fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        dst: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.encoder.encode_trailers(trailers, dst)
+    }
 }
 
 impl Decoder for Codec {
@@ -120,7 +138,9 @@ impl Decoder for Codec {
             Ok(match self.payload.as_mut().unwrap().decode(src)? {
                 Some(PayloadItem::Chunk(chunk)) => Some(Message::Chunk(Some(chunk))),
                 Some(PayloadItem::Eof) => {
-                    self.payload.take();
+                    if let Some(mut payload) = self.payload.take() {
+                        self.trailers = payload.take_trailers();
+                    }
                     Some(Message::Chunk(None))
                 }
                 None => None,
@@ -9,6 +9,7 @@ use http::{header, HttpTryFrom, Method, StatusCode, Uri, Version};
 use httparse;
 use log::{debug, error, trace};
 
+use crate::config::H1Config;
 use crate::error::ParseError;
 use crate::header::HeaderMap;
 use crate::message::{ConnectionType, ResponseHead};
@@ -18,7 +19,10 @@ const MAX_BUFFER_SIZE: usize = 131_072;
 const MAX_HEADERS: usize = 96;
 
 /// Incoming messagd decoder
-pub(crate) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(crate) struct MessageDecoder<T: MessageType> {
+    limits: H1Config,
+    _t: PhantomData<T>,
+}
 
 #[derive(Debug)]
 /// Incoming request type
@@ -30,7 +34,17 @@ pub(crate) enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(H1Config::default())
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    /// Create decoder with parser limits
+    pub(crate) fn new(limits: H1Config) -> Self {
+        MessageDecoder {
+            limits,
+            _t: PhantomData,
+        }
     }
 }
 
@@ -39,7 +53,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, &self.limits)
     }
 }
 
@@ -56,12 +70,16 @@ pub(crate) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &H1Config,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
         slice: &Bytes,
         raw_headers: &[HeaderIndex],
+        limits: &H1Config,
     ) -> Result<PayloadLength, ParseError> {
         let mut ka = None;
         let mut has_upgrade = false;
@@ -73,6 +91,11 @@ pub(crate) trait MessageType: Sized {
             let headers = self.headers_mut();
 
             for idx in raw_headers.iter() {
+                if idx.value.1 - idx.value.0 > limits.max_header_value_size {
+                    debug!("Header value is larger than configured limit");
+                    return Err(ParseError::TooLarge);
+                }
+
                 let name =
                     HeaderName::from_bytes(&slice[idx.name.0..idx.name.1]).unwrap();
 
@@ -183,38 +206,77 @@ impl MessageType for Request {
         &mut self.head_mut().headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        limits: &H1Config,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read only this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
-        let mut headers: [HeaderIndex; MAX_HEADERS] = unsafe { mem::uninitialized() };
+        let mut headers_buf: [HeaderIndex; MAX_HEADERS] =
+            unsafe { mem::uninitialized() };
+        let mut headers_vec;
+        let headers: &mut [HeaderIndex] = if limits.max_headers <= MAX_HEADERS {
+            &mut headers_buf[..limits.max_headers]
+        } else {
+            headers_vec = vec![HeaderIndex::default(); limits.max_headers];
+            &mut headers_vec
+        };
 
         let (len, method, uri, ver, h_len) = {
-            let mut parsed: [httparse::Header; MAX_HEADERS] =
+            let mut parsed_buf: [httparse::Header; MAX_HEADERS] =
                 unsafe { mem::uninitialized() };
+            let mut parsed_vec;
+            let parsed: &mut [httparse::Header] = if limits.max_headers <= MAX_HEADERS {
+                &mut parsed_buf[..limits.max_headers]
+            } else {
+                parsed_vec = vec![httparse::EMPTY_HEADER; limits.max_headers];
+                &mut parsed_vec
+            };
 
-            let mut req = httparse::Request::new(&mut parsed);
+            let mut req = httparse::Request::new(parsed);
             match req.parse(src)? {
                 httparse::Status::Complete(len) => {
+                    if len > limits.max_header_block_size {
+                        debug!("Request head is larger than configured limit");
+                        return Err(ParseError::TooLarge);
+                    }
+                    let path = req.path.unwrap();
+                    if path.len() > limits.max_uri_length {
+                        debug!("Request uri is longer than configured limit");
+                        return Err(ParseError::UriTooLong);
+                    }
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
-                    let uri = Uri::try_from(req.path.unwrap())?;
+                    let uri = Uri::try_from(path)?;
                     let version = if req.version.unwrap() == 1 {
                         Version::HTTP_11
                     } else {
                         Version::HTTP_10
                     };
-                    HeaderIndex::record(src, req.headers, &mut headers);
+                    HeaderIndex::record(src, req.headers, headers);
 
                     (len, method, uri, version, req.headers.len())
                 }
-                httparse::Status::Partial => return Ok(None),
+                httparse::Status::Partial => {
+                    // request line is not complete yet
+                    if req.path.is_none() && src.len() > limits.max_uri_length {
+                        debug!("Request uri is longer than configured limit");
+                        return Err(ParseError::UriTooLong);
+                    }
+                    if src.len() > limits.max_header_block_size {
+                        debug!("Request head is larger than configured limit");
+                        return Err(ParseError::TooLarge);
+                    }
+                    return Ok(None);
+                }
             }
         };
 
         let mut msg = Request::new();
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length =
+            msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len], limits)?;
 
         // payload decoder
         let decoder = match length {
@@ -228,7 +290,7 @@ impl MessageType for Request {
                     PayloadType::Stream(PayloadDecoder::eof())
                 } else if src.len() >= MAX_BUFFER_SIZE {
                     trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
-                    return Err(ParseError::TooLarge);
+                    return Err(ParseError::BufferOverflow);
                 } else {
                     PayloadType::None
                 }
@@ -257,16 +319,34 @@ impl MessageType for ResponseHead {
         &mut self.headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
+    fn decode(
+        src: &mut BytesMut,
+        limits: &H1Config,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
         // Unsafe: we read only this data only after httparse parses headers into.
         // performance bump for pipeline benchmarks.
-        let mut headers: [HeaderIndex; MAX_HEADERS] = unsafe { mem::uninitialized() };
+        let mut headers_buf: [HeaderIndex; MAX_HEADERS] =
+            unsafe { mem::uninitialized() };
+        let mut headers_vec;
+        let headers: &mut [HeaderIndex] = if limits.max_headers <= MAX_HEADERS {
+            &mut headers_buf[..limits.max_headers]
+        } else {
+            headers_vec = vec![HeaderIndex::default(); limits.max_headers];
+            &mut headers_vec
+        };
 
         let (len, ver, status, h_len) = {
-            let mut parsed: [httparse::Header; MAX_HEADERS] =
+            let mut parsed_buf: [httparse::Header; MAX_HEADERS] =
                 unsafe { mem::uninitialized() };
+            let mut parsed_vec;
+            let parsed: &mut [httparse::Header] = if limits.max_headers <= MAX_HEADERS {
+                &mut parsed_buf[..limits.max_headers]
+            } else {
+                parsed_vec = vec![httparse::EMPTY_HEADER; limits.max_headers];
+                &mut parsed_vec
+            };
 
-            let mut res = httparse::Response::new(&mut parsed);
+            let mut res = httparse::Response::new(parsed);
             match res.parse(src)? {
                 httparse::Status::Complete(len) => {
                     let version = if res.version.unwrap() == 1 {
@@ -276,11 +356,17 @@ impl MessageType for ResponseHead {
                     };
                     let status = StatusCode::from_u16(res.code.unwrap())
                         .map_err(|_| ParseError::Status)?;
-                    HeaderIndex::record(src, res.headers, &mut headers);
+                    HeaderIndex::record(src, res.headers, headers);
 
                     (len, version, status, res.headers.len())
                 }
-                httparse::Status::Partial => return Ok(None),
+                httparse::Status::Partial => {
+                    if src.len() > limits.max_header_block_size {
+                        error!("Response head is larger than configured limit");
+                        return Err(ParseError::TooLarge);
+                    }
+                    return Ok(None);
+                }
             }
         };
 
@@ -288,7 +374,8 @@ impl MessageType for ResponseHead {
         msg.version = ver;
 
         // convert headers
-        let length = msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len])?;
+        let length =
+            msg.set_headers(&src.split_to(len).freeze(), &headers[..h_len], limits)?;
 
         // message payload
         let decoder = if let PayloadLength::Payload(pl) = length {
@@ -298,7 +385,7 @@ impl MessageType for ResponseHead {
             PayloadType::Stream(PayloadDecoder::eof())
         } else if src.len() >= MAX_BUFFER_SIZE {
             error!("MAX_BUFFER_SIZE unprocessed data reached, closing");
-            return Err(ParseError::TooLarge);
+            return Err(ParseError::BufferOverflow);
         } else {
             // for HTTP/1.0 read to eof and close connection
             if msg.version == Version::HTTP_10 {
@@ -313,7 +400,7 @@ impl MessageType for ResponseHead {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub(crate) struct HeaderIndex {
     pub(crate) name: (usize, usize),
     pub(crate) value: (usize, usize),
@@ -348,26 +435,37 @@ pub enum PayloadItem {
 ///
 /// If a message body does not include a Transfer-Encoding, it *should*
 /// include a Content-Length header.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct PayloadDecoder {
     kind: Kind,
+    trailers: Option<HeaderMap>,
 }
 
 impl PayloadDecoder {
     pub fn length(x: u64) -> PayloadDecoder {
         PayloadDecoder {
             kind: Kind::Length(x),
+            trailers: None,
         }
     }
 
     pub fn chunked() -> PayloadDecoder {
         PayloadDecoder {
             kind: Kind::Chunked(ChunkedState::Size, 0),
+            trailers: None,
         }
     }
 
     pub fn eof() -> PayloadDecoder {
-        PayloadDecoder { kind: Kind::Eof }
+        PayloadDecoder {
+            kind: Kind::Eof,
+            trailers: None,
+        }
+    }
+
+    /// Trailers of chunked payload, available after eof
+    pub fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
     }
 }
 
@@ -404,8 +502,7 @@ enum ChunkedState {
     Body,
     BodyCr,
     BodyLf,
-    EndCr,
-    EndLf,
+    Trailers,
     End,
 }
 
@@ -437,6 +534,16 @@ impl Decoder for PayloadDecoder {
             }
             Kind::Chunked(ref mut state, ref mut size) => {
                 loop {
+                    if *state == ChunkedState::Trailers {
+                        match read_trailers(src)? {
+                            Async::NotReady => return Ok(None),
+                            Async::Ready(trailers) => {
+                                self.trailers = trailers;
+                                *state = ChunkedState::End;
+                            }
+                        }
+                    }
+
                     let mut buf = None;
                     // advances the chunked state
                     *state = match state.step(src, size, &mut buf)? {
@@ -494,9 +601,7 @@ impl ChunkedState {
             Body => ChunkedState::read_body(body, size, buf),
             BodyCr => ChunkedState::read_body_cr(body),
             BodyLf => ChunkedState::read_body_lf(body),
-            EndCr => ChunkedState::read_end_cr(body),
-            EndLf => ChunkedState::read_end_lf(body),
-            End => Ok(Async::Ready(ChunkedState::End)),
+            Trailers | End => Ok(Async::Ready(self.clone())),
         }
     }
     fn read_size(rdr: &mut BytesMut, size: &mut u64) -> Poll<ChunkedState, io::Error> {
@@ -551,7 +656,7 @@ impl ChunkedState {
     ) -> Poll<ChunkedState, io::Error> {
         match byte!(rdr) {
             b'\n' if *size > 0 => Ok(Async::Ready(ChunkedState::Body)),
-            b'\n' if *size == 0 => Ok(Async::Ready(ChunkedState::EndCr)),
+            b'\n' if *size == 0 => Ok(Async::Ready(ChunkedState::Trailers)),
             _ => Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Invalid chunk size LF",
@@ -605,24 +710,43 @@ impl ChunkedState {
             )),
         }
     }
-    fn read_end_cr(rdr: &mut BytesMut) -> Poll<ChunkedState, io::Error> {
-        match byte!(rdr) {
-            b'\r' => Ok(Async::Ready(ChunkedState::EndLf)),
-            _ => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid chunk end CR",
-            )),
-        }
-    }
-    fn read_end_lf(rdr: &mut BytesMut) -> Poll<ChunkedState, io::Error> {
-        match byte!(rdr) {
-            b'\n' => Ok(Async::Ready(ChunkedState::End)),
-            _ => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid chunk end LF",
-            )),
+}
+
+/// Parse trailer section of chunked payload
+fn read_trailers(src: &mut BytesMut) -> Poll<Option<HeaderMap>, io::Error> {
+    let invalid =
+        || io::Error::new(io::ErrorKind::InvalidInput, "Invalid chunk trailer");
+
+    let (len, trailers) = {
+        let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        match httparse::parse_headers(src, &mut parsed) {
+            Ok(httparse::Status::Complete((len, headers))) => {
+                if headers.is_empty() {
+                    (len, None)
+                } else {
+                    let mut trailers = HeaderMap::with_capacity(headers.len());
+                    for h in headers {
+                        let name = HeaderName::from_bytes(h.name.as_bytes())
+                            .map_err(|_| invalid())?;
+                        let value =
+                            HeaderValue::from_bytes(h.value).map_err(|_| invalid())?;
+                        trailers.append(name, value);
+                    }
+                    (len, Some(trailers))
+                }
+            }
+            Ok(httparse::Status::Partial) => {
+                if src.len() >= MAX_BUFFER_SIZE {
+                    error!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    return Err(invalid());
+                }
+                return Ok(Async::NotReady);
+            }
+            Err(_) => return Err(invalid()),
         }
-    }
+    };
+    src.split_to(len);
+    Ok(Async::Ready(trailers))
 }
 
 #[cfg(test)]
@@ -1079,6 +1203,76 @@ mod tests {
         expect_parse_err!(&mut buf);
     }
 
+    #[test]
+    fn test_http_request_parser_limits() {
+        let mut reader = MessageDecoder::<Request>::new(H1Config {
+            max_headers: 2,
+            max_header_block_size: 128,
+            max_uri_length: 16,
+            max_header_value_size: 8,
+        });
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        // too many headers
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+
+        // too large header value
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\na: 123456789\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+
+        // too large header block
+        let mut buf = BytesMut::from(format!(
+            "GET /test HTTP/1.1\r\n{}: 1\r\n\r\n",
+            "a".repeat(120)
+        ));
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+        let mut buf =
+            BytesMut::from(format!("GET /test HTTP/1.1\r\n{}", "a".repeat(120)));
+        match reader.decode(&mut buf) {
+            Err(ParseError::TooLarge) => (),
+            _ => unreachable!("TooLarge error expected"),
+        }
+
+        // too long uri
+        let mut buf = BytesMut::from("GET /0123456789abcdef HTTP/1.1\r\n\r\n");
+        match reader.decode(&mut buf) {
+            Err(ParseError::UriTooLong) => (),
+            _ => unreachable!("UriTooLong error expected"),
+        }
+        let mut buf = BytesMut::from("GET /0123456789abcdef");
+        match reader.decode(&mut buf) {
+            Err(ParseError::UriTooLong) => (),
+            _ => unreachable!("UriTooLong error expected"),
+        }
+    }
+
+    #[test]
+    fn test_http_request_parser_buffer_overflow() {
+        // unprocessed data after request without payload is not a request head
+        let mut buf = BytesMut::from(format!(
+            "GET /test HTTP/1.1\r\n\r\n{}",
+            "a".repeat(MAX_BUFFER_SIZE)
+        ));
+        let mut reader = MessageDecoder::<Request>::default();
+        match reader.decode(&mut buf) {
+            Err(ParseError::BufferOverflow) => (),
+            _ => unreachable!("BufferOverflow error expected"),
+        }
+    }
+
     #[test]
     fn test_http_request_chunked_payload() {
         let mut buf = BytesMut::from(
@@ -1164,10 +1358,6 @@ mod tests {
         let msg = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(msg.chunk().as_ref(), b"li");
 
-        //trailers
-        //buf.feed_data("test: test\r\n");
-        //not_ready!(reader.parse(&mut buf, &mut readbuf));
-
         buf.extend(b"ne\r\n0\r\n");
         let msg = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(msg.chunk().as_ref(), b"ne");
@@ -1177,6 +1367,46 @@ mod tests {
         assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_trailers() {
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"4\r\ndata\r\n0\r\nx-checksum: abc\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+
+        buf.extend(b"x-status: 0\r\n\r\n");
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+        assert!(buf.is_empty());
+
+        let trailers = pl.take_trailers().unwrap();
+        assert_eq!(trailers.get("x-checksum").unwrap(), "abc");
+        assert_eq!(trailers.get("x-status").unwrap(), "0");
+    }
+
+    #[test]
+    fn test_parse_chunked_payload_invalid_trailers() {
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap();
+
+        buf.extend(b"0\r\ninvalid trailer\r\n\r\n");
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
     #[test]
     fn test_parse_chunked_payload_chunk_extension() {
         let mut buf = BytesMut::from(
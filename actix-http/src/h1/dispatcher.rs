@@ -6,18 +6,21 @@ use actix_codec::{Decoder, Encoder, Framed, FramedParts};
 use actix_server_config::IoStream;
 use actix_service::Service;
 use bitflags::bitflags;
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use futures::{Async, Future, Poll};
-use log::{error, trace};
+use log::{error, trace, warn};
 use tokio_timer::Delay;
 
 use crate::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::cloneable::CloneableService;
 use crate::config::ServiceConfig;
 use crate::error::{DispatchError, Error};
-use crate::error::{ParseError, PayloadError};
+use crate::error::{ParseError, PayloadError, ResponseError};
+use crate::h2::upgrade as h2c;
 use crate::helpers::DataFactory;
 use crate::httpmessage::HttpMessage;
+use crate::message::ConnectionType;
+use crate::rate::RateTracker;
 use crate::request::Request;
 use crate::response::Response;
 
@@ -30,14 +33,15 @@ const HW_BUFFER_SIZE: usize = 32_768;
 const MAX_PIPELINED_MESSAGES: usize = 16;
 
 bitflags! {
-    pub struct Flags: u8 {
-        const STARTED            = 0b0000_0001;
-        const KEEPALIVE          = 0b0000_0010;
-        const POLLED             = 0b0000_0100;
-        const SHUTDOWN           = 0b0000_1000;
-        const READ_DISCONNECT    = 0b0001_0000;
-        const WRITE_DISCONNECT   = 0b0010_0000;
-        const UPGRADE            = 0b0100_0000;
+    pub struct Flags: u16 {
+        const STARTED            = 0b0000_0000_0001;
+        const KEEPALIVE          = 0b0000_0000_0010;
+        const POLLED             = 0b0000_0000_0100;
+        const SHUTDOWN           = 0b0000_0000_1000;
+        const READ_DISCONNECT    = 0b0000_0001_0000;
+        const WRITE_DISCONNECT   = 0b0000_0010_0000;
+        const UPGRADE            = 0b0000_0100_0000;
+        const H2C                = 0b0000_1000_0000;
     }
 }
 
@@ -67,9 +71,21 @@ where
 {
     Normal(InnerDispatcher<T, S, B, X, U>),
     Upgrade(U::Future),
+    H2c(InnerDispatcher<T, S, B, X, U>, Request, Bytes),
     None,
 }
 
+/// Connection parts after `Upgrade: h2c` handshake
+pub(crate) struct H2cUpgrade<T, S> {
+    pub(crate) io: T,
+    pub(crate) read_buf: BytesMut,
+    pub(crate) request: Request,
+    pub(crate) settings: Bytes,
+    pub(crate) config: ServiceConfig,
+    pub(crate) service: CloneableService<S>,
+    pub(crate) on_connect: Option<Box<dyn DataFactory>>,
+}
+
 struct InnerDispatcher<T, S, B, X, U>
 where
     S: Service<Request = Request>,
@@ -95,6 +111,13 @@ where
     ka_expire: Instant,
     ka_timer: Option<Delay>,
 
+    req_rate: Option<RateTracker>,
+    resp_rate: Option<RateTracker>,
+
+    responses: usize,
+    created: Instant,
+    age_timer: Option<Delay>,
+
     io: T,
     read_buf: BytesMut,
     write_buf: BytesMut,
@@ -104,6 +127,7 @@ where
 enum DispatcherMessage {
     Item(Request),
     Upgrade(Request),
+    H2c(Request, Bytes),
     Error(Response<()>),
 }
 
@@ -144,6 +168,7 @@ where
 
 enum PollResponse {
     Upgrade(Request),
+    H2c(Request, Bytes),
     DoNothing,
     DrainWriteBuf,
 }
@@ -244,9 +269,47 @@ where
                 flags,
                 ka_expire,
                 ka_timer,
+                req_rate: config.min_request_rate().map(RateTracker::new),
+                resp_rate: config.min_response_rate().map(RateTracker::new),
+                responses: 0,
+                created: config.now(),
+                age_timer: config
+                    .max_connection_age()
+                    .map(|age| Delay::new(config.now() + age)),
             }),
         }
     }
+
+    /// Allow connection upgrade to http/2 with `Upgrade: h2c` request.
+    ///
+    /// Dispatcher completes after sending `101 Switching Protocols` response,
+    /// connection parts are available via `take_h2c()` method.
+    pub(crate) fn h2c(mut self) -> Self {
+        if let DispatcherState::Normal(ref mut inner) = self.inner {
+            inner.flags.insert(Flags::H2C);
+        }
+        self
+    }
+
+    /// Take connection parts if connection got upgraded to http/2.
+    pub(crate) fn take_h2c(&mut self) -> Option<H2cUpgrade<T, S>> {
+        if let DispatcherState::H2c(..) = self.inner {
+            if let DispatcherState::H2c(inner, request, settings) =
+                std::mem::replace(&mut self.inner, DispatcherState::None)
+            {
+                return Some(H2cUpgrade {
+                    request,
+                    settings,
+                    io: inner.io,
+                    read_buf: inner.read_buf,
+                    config: inner.codec.config().clone(),
+                    service: inner.service,
+                    on_connect: inner.on_connect,
+                });
+            }
+        }
+        None
+    }
 }
 
 impl<T, S, B, X, U> InnerDispatcher<T, S, B, X, U>
@@ -304,6 +367,9 @@ where
                 }
                 Ok(n) => {
                     written += n;
+                    if let Some(ref mut rate) = self.resp_rate {
+                        rate.feed(n);
+                    }
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
                     if written > 0 {
@@ -324,11 +390,30 @@ where
         Ok(false)
     }
 
+    /// Check if connection served max number of requests or reached max age
+    fn is_exhausted(&self) -> bool {
+        let config = self.codec.config();
+        config
+            .max_requests_per_connection()
+            .map_or(false, |max| self.responses >= max)
+            || config
+                .max_connection_age()
+                .map_or(false, |age| config.now() >= self.created + age)
+    }
+
     fn send_response(
         &mut self,
-        message: Response<()>,
+        mut message: Response<()>,
         body: ResponseBody<B>,
     ) -> Result<State<S, B, X>, DispatchError> {
+        self.responses += 1;
+        if self.is_exhausted() && !self.codec.upgrade() {
+            trace!("Connection reached max requests or max age, close connection");
+            message
+                .head_mut()
+                .set_connection_type(ConnectionType::Close);
+        }
+
         self.codec
             .encode(Message::Item((message, body.size())), &mut self.write_buf)
             .map_err(|err| {
@@ -363,6 +448,9 @@ where
                     Some(DispatcherMessage::Upgrade(req)) => {
                         return Ok(PollResponse::Upgrade(req));
                     }
+                    Some(DispatcherMessage::H2c(req, settings)) => {
+                        return Ok(PollResponse::H2c(req, settings));
+                    }
                     None => None,
                 },
                 State::ExpectCall(ref mut fut) => match fut.poll() {
@@ -406,10 +494,17 @@ where
                                     continue;
                                 }
                                 Async::Ready(None) => {
-                                    self.codec.encode(
-                                        Message::Chunk(None),
-                                        &mut self.write_buf,
-                                    )?;
+                                    if let Some(trailers) = stream.trailers() {
+                                        self.codec.encode_trailers(
+                                            &trailers,
+                                            &mut self.write_buf,
+                                        )?;
+                                    } else {
+                                        self.codec.encode(
+                                            Message::Chunk(None),
+                                            &mut self.write_buf,
+                                        )?;
+                                    }
                                     self.state = State::None;
                                 }
                                 Async::NotReady => return Ok(PollResponse::DoNothing),
@@ -507,6 +602,17 @@ where
                                 on_connect.set(&mut req.extensions_mut());
                             }
 
+                            if pl == MessageType::Stream
+                                && self.flags.contains(Flags::H2C)
+                            {
+                                if let Some(settings) = h2c::settings(req.head()) {
+                                    self.flags.insert(Flags::UPGRADE);
+                                    self.messages.push_back(DispatcherMessage::H2c(
+                                        req, settings,
+                                    ));
+                                    break;
+                                }
+                            }
                             if pl == MessageType::Stream && self.upgrade.is_some() {
                                 self.messages.push_back(DispatcherMessage::Upgrade(req));
                                 break;
@@ -528,6 +634,9 @@ where
                         }
                         Message::Chunk(Some(chunk)) => {
                             if let Some(ref mut payload) = self.payload {
+                                if let Some(ref mut rate) = self.req_rate {
+                                    rate.feed(chunk.len());
+                                }
                                 payload.feed_data(chunk);
                             } else {
                                 error!(
@@ -543,6 +652,9 @@ where
                         }
                         Message::Chunk(None) => {
                             if let Some(mut payload) = self.payload.take() {
+                                if let Some(trailers) = self.codec.take_trailers() {
+                                    payload.set_trailers(trailers);
+                                }
                                 payload.feed_eof();
                             } else {
                                 error!("Internal server error: unexpected eof");
@@ -567,9 +679,10 @@ where
                         payload.set_error(PayloadError::EncodingCorrupted);
                     }
 
-                    // Malformed requests should be responded with 400
+                    // Malformed requests should be responded with 400,
+                    // requests exceeding parser limits with 431 or 414
                     self.messages.push_back(DispatcherMessage::Error(
-                        Response::BadRequest().finish().drop_body(),
+                        e.error_response().drop_body(),
                     ));
                     self.flags.insert(Flags::READ_DISCONNECT);
                     self.error = Some(e.into());
@@ -586,8 +699,83 @@ where
         Ok(updated)
     }
 
+    /// Check minimum request body and response data rates
+    fn poll_data_rates(&mut self) -> Result<(), DispatchError> {
+        if let Some(ref mut rate) = self.req_rate {
+            // time is counted only while payload waits for data from client
+            match self.payload {
+                Some(ref payload) if payload.need_read() == PayloadStatus::Read => {
+                    rate.resume()
+                }
+                Some(_) => rate.pause(),
+                None => rate.reset(),
+            }
+            if rate.poll_check() {
+                warn!(
+                    "Request body data rate is below minimum, drop connection: {:?}",
+                    self.peer_addr
+                );
+                if let Some(mut payload) = self.payload.take() {
+                    payload.set_error(PayloadError::Incomplete(None));
+                }
+                return Err(DispatchError::SlowClient);
+            }
+        }
+
+        if let Some(ref mut rate) = self.resp_rate {
+            // time is counted only while response data waits for client
+            if !self.write_buf.is_empty() {
+                rate.resume();
+            } else if self.state.is_empty() {
+                rate.reset();
+            } else {
+                rate.pause();
+            }
+            if rate.poll_check() {
+                warn!(
+                    "Response data rate is below minimum, drop connection: {:?}",
+                    self.peer_addr
+                );
+                return Err(DispatchError::SlowClient);
+            }
+        }
+        Ok(())
+    }
+
     /// keep-alive timer
     fn poll_keepalive(&mut self) -> Result<(), DispatchError> {
+        // close idle connection once it reaches max age
+        if !self.flags.contains(Flags::SHUTDOWN) {
+            let expired = if let Some(ref mut timer) = self.age_timer {
+                timer
+                    .poll()
+                    .map_err(|e| {
+                        error!("Timer error {:?}", e);
+                        DispatchError::Unknown
+                    })?
+                    .is_ready()
+            } else {
+                false
+            };
+
+            if expired && self.state.is_empty() && self.write_buf.is_empty() {
+                trace!("Connection reached max age, close connection");
+                self.flags.insert(Flags::STARTED | Flags::SHUTDOWN);
+
+                // start shutdown timer
+                if let Some(deadline) = self.codec.config().client_disconnect_timer() {
+                    let timer =
+                        self.ka_timer.get_or_insert_with(|| Delay::new(deadline));
+                    timer.reset(deadline);
+                    let _ = timer.poll();
+                } else {
+                    // no shutdown timeout, drop socket
+                    self.flags.insert(Flags::WRITE_DISCONNECT);
+                }
+                return Ok(());
+            }
+        }
+
         if self.ka_timer.is_none() {
             // shutdown timeout
             if self.flags.contains(Flags::SHUTDOWN) {
@@ -748,6 +936,22 @@ where
                             }
                         }
 
+                        // switch to http/2
+                        if let PollResponse::H2c(req, settings) = result {
+                            if let DispatcherState::Normal(mut inner) =
+                                std::mem::replace(&mut self.inner, DispatcherState::None)
+                            {
+                                trace!("Upgrade connection to http/2");
+                                inner
+                                    .write_buf
+                                    .extend_from_slice(h2c::SWITCHING_PROTOCOLS);
+                                self.inner = DispatcherState::H2c(inner, req, settings);
+                                return self.poll();
+                            } else {
+                                panic!()
+                            }
+                        }
+
                         // we didnt get WouldBlock from write operation,
                         // so data get written to kernel completely (OSX)
                         // and we have to write again otherwise response can get stuck
@@ -761,6 +965,9 @@ where
                         return Ok(Async::Ready(()));
                     }
 
+                    // slow clients
+                    inner.poll_data_rates()?;
+
                     let is_empty = inner.state.is_empty();
 
                     // read half is closed and we do not processing any responses
@@ -795,6 +1002,15 @@ where
                 error!("Upgrade handler error: {}", e);
                 DispatchError::Upgrade
             }),
+            DispatcherState::H2c(ref mut inner, ..) => {
+                // 101 response must be sent before http/2 connection starts
+                inner.poll_flush()?;
+                if inner.write_buf.is_empty() {
+                    Ok(Async::Ready(()))
+                } else {
+                    Ok(Async::NotReady)
+                }
+            }
             DispatcherState::None => panic!(),
         }
     }
@@ -845,8 +1061,10 @@ mod tests {
     use futures::future::{lazy, ok};
 
     use super::*;
+    use crate::config::{ConnectionConfig, H1Config, H2Config, KeepAlive};
     use crate::error::Error;
     use crate::h1::{ExpectHandler, UpgradeHandler};
+    use crate::rate::DataRate;
     use crate::test::TestBuffer;
 
     #[test]
@@ -874,4 +1092,122 @@ mod tests {
             ok::<_, ()>(())
         }));
     }
+
+    #[test]
+    fn test_req_uri_too_long() {
+        let mut sys = actix_rt::System::new("test");
+        let _ = sys.block_on(lazy(|| {
+            let buf = TestBuffer::new("GET /0123456789abcdef HTTP/1.1\r\n\r\n");
+
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                ServiceConfig::with_settings(
+                    KeepAlive::Os,
+                    0,
+                    0,
+                    H1Config {
+                        max_uri_length: 16,
+                        ..H1Config::default()
+                    },
+                    H2Config::default(),
+                    ConnectionConfig::default(),
+                ),
+                CloneableService::new(
+                    (|_| ok::<_, Error>(Response::Ok().finish())).into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+            );
+            assert!(h1.poll().is_err());
+
+            if let DispatcherState::Normal(ref inner) = h1.inner {
+                assert!(inner.flags.contains(Flags::READ_DISCONNECT));
+                assert_eq!(&inner.io.write_buf[..27], b"HTTP/1.1 414 URI Too Long\r\n");
+            }
+            ok::<_, ()>(())
+        }));
+    }
+
+    #[test]
+    fn test_slow_request_body() {
+        let mut sys = actix_rt::System::new("test");
+        let _ = sys.block_on(lazy(|| {
+            let buf =
+                TestBuffer::new("POST /test HTTP/1.1\r\ncontent-length: 100\r\n\r\nab");
+
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                ServiceConfig::with_settings(
+                    KeepAlive::Os,
+                    0,
+                    0,
+                    H1Config::default(),
+                    H2Config::default(),
+                    ConnectionConfig {
+                        min_request_rate: DataRate::new(1_000_000, 0),
+                        ..ConnectionConfig::default()
+                    },
+                ),
+                CloneableService::new(
+                    (|req: Request| {
+                        // keep request payload alive, but never read it
+                        futures::future::poll_fn(move || {
+                            let _ = &req;
+                            Ok::<_, Error>(Async::<Response>::NotReady)
+                        })
+                    })
+                    .into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+            );
+            assert!(h1.poll().unwrap().is_not_ready());
+
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            match h1.poll() {
+                Err(DispatchError::SlowClient) => (),
+                _ => panic!("data rate is not enforced"),
+            }
+            ok::<_, ()>(())
+        }));
+    }
+
+    #[test]
+    fn test_max_requests_per_connection() {
+        let mut sys = actix_rt::System::new("test");
+        let _ = sys.block_on(lazy(|| {
+            let buf = TestBuffer::new("GET /test HTTP/1.1\r\n\r\n");
+
+            let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<TestBuffer>>::new(
+                buf,
+                ServiceConfig::with_settings(
+                    KeepAlive::Os,
+                    0,
+                    0,
+                    H1Config::default(),
+                    H2Config::default(),
+                    ConnectionConfig {
+                        max_requests: Some(1),
+                        ..ConnectionConfig::default()
+                    },
+                ),
+                CloneableService::new(
+                    (|_| ok::<_, Error>(Response::Ok().finish())).into_service(),
+                ),
+                CloneableService::new(ExpectHandler),
+                None,
+                None,
+            );
+            assert!(h1.poll().is_ok());
+
+            if let DispatcherState::Normal(ref inner) = h1.inner {
+                assert!(!inner.flags.contains(Flags::KEEPALIVE));
+                let data = String::from_utf8_lossy(&inner.io.write_buf);
+                assert!(data.contains("connection: close\r\n"));
+            }
+            ok::<_, ()>(())
+        }));
+    }
 }
@@ -293,6 +293,15 @@ impl<T: MessageType> MessageEncoder<T> {
         self.te.encode_eof(buf)
     }
 
+    /// Encode eof with trailing headers
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.te.encode_trailers(trailers, buf)
+    }
+
     pub fn encode(
         &mut self,
         dst: &mut BytesMut,
@@ -443,6 +452,37 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode eof with trailing headers.
+    ///
+    /// Trailers could be sent only with chunked transfer encoding,
+    /// for other encodings they are ignored.
+    pub fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(ref mut eof) => {
+                if !*eof {
+                    *eof = true;
+                    buf.extend_from_slice(b"0\r\n");
+                    for (key, value) in trailers.iter() {
+                        let v = value.as_ref();
+                        let k = key.as_str().as_bytes();
+                        buf.reserve(k.len() + v.len() + 4);
+                        buf.put_slice(k);
+                        buf.put_slice(b": ");
+                        buf.put_slice(v);
+                        buf.put_slice(b"\r\n");
+                    }
+                    buf.extend_from_slice(b"\r\n");
+                }
+                Ok(())
+            }
+            _ => self.encode_eof(buf),
+        }
+    }
 }
 
 struct Writer<'a>(pub &'a mut BytesMut);
@@ -490,7 +530,7 @@ mod tests {
     use bytes::Bytes;
 
     use super::*;
-    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
 
     #[test]
     fn test_chunked_te() {
@@ -506,6 +546,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("x-checksum"),
+            HeaderValue::from_static("abc"),
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        assert!(!enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(
+            bytes.take().freeze(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\nx-checksum: abc\r\n\r\n")
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::length(4);
+        enc.encode(b"test", &mut bytes).unwrap();
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(bytes.take().freeze(), Bytes::from_static(b"test"));
+    }
+
     #[test]
     fn test_camel_case() {
         let mut bytes = BytesMut::with_capacity(2048);
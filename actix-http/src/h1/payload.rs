@@ -9,6 +9,7 @@ use futures::task::Task;
 use futures::{Async, Poll, Stream};
 
 use crate::error::PayloadError;
+use crate::header::HeaderMap;
 
 /// max buffer size 32k
 pub(crate) const MAX_BUFFER_SIZE: usize = 32_768;
@@ -77,6 +78,13 @@ impl Payload {
     pub fn unread_data(&mut self, data: Bytes) {
         self.inner.borrow_mut().unread_data(data);
     }
+
+    /// Trailing headers of chunked payload
+    ///
+    /// Trailers are available after payload stream is complete.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.inner.borrow().trailers.clone()
+    }
 }
 
 impl Stream for Payload {
@@ -109,6 +117,13 @@ impl PayloadSender {
         }
     }
 
+    #[inline]
+    pub fn set_trailers(&mut self, trailers: HeaderMap) {
+        if let Some(shared) = self.inner.upgrade() {
+            shared.borrow_mut().trailers = Some(trailers);
+        }
+    }
+
     #[inline]
     pub fn feed_data(&mut self, data: Bytes) {
         if let Some(shared) = self.inner.upgrade() {
@@ -145,6 +160,7 @@ struct Inner {
     err: Option<PayloadError>,
     need_read: bool,
     items: VecDeque<Bytes>,
+    trailers: Option<HeaderMap>,
     task: Option<Task>,
     io_task: Option<Task>,
 }
@@ -156,6 +172,7 @@ impl Inner {
             len: 0,
             err: None,
             items: VecDeque::new(),
+            trailers: None,
             need_read: true,
             task: None,
             io_task: None,
@@ -251,4 +268,29 @@ mod tests {
             }))
             .unwrap();
     }
+
+    #[test]
+    fn test_trailers() {
+        let (mut sender, mut payload) = Payload::create(false);
+        sender.feed_data(Bytes::from("data"));
+        assert!(payload.trailers().is_none());
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            http::header::HeaderName::from_static("x-checksum"),
+            http::header::HeaderValue::from_static("abc"),
+        );
+        sender.set_trailers(trailers);
+        sender.feed_eof();
+
+        assert_eq!(
+            Async::Ready(Some(Bytes::from("data"))),
+            payload.poll().ok().unwrap()
+        );
+        assert_eq!(Async::Ready(None), payload.poll().ok().unwrap());
+        assert_eq!(
+            payload.trailers().unwrap().get("x-checksum").unwrap(),
+            "abc"
+        );
+    }
 }
@@ -1,6 +1,8 @@
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::marker::PhantomData;
-use std::time::Instant;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use std::{fmt, mem, net};
 
 use actix_codec::{AsyncRead, AsyncWrite};
@@ -8,9 +10,10 @@ use actix_server_config::IoStream;
 use actix_service::Service;
 use bitflags::bitflags;
 use bytes::{Bytes, BytesMut};
+use futures::task::{self, Task};
 use futures::{try_ready, Async, Future, Poll, Sink, Stream};
 use h2::server::{Connection, SendResponse};
-use h2::{RecvStream, SendStream};
+use h2::{Ping, PingPong, RecvStream, SendStream};
 use http::header::{
     HeaderValue, ACCEPT_ENCODING, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING,
 };
@@ -22,26 +25,86 @@ use crate::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::cloneable::CloneableService;
 use crate::config::ServiceConfig;
 use crate::error::{DispatchError, Error, ParseError, PayloadError, ResponseError};
+use crate::h2::StreamReset;
 use crate::helpers::DataFactory;
+use crate::httpmessage::HttpMessage;
 use crate::message::ResponseHead;
 use crate::payload::Payload;
+use crate::rate::RateTracker;
 use crate::request::Request;
 use crate::response::Response;
 
 const CHUNK_SIZE: usize = 16_384;
 
+bitflags! {
+    struct Flags: u8 {
+        const STARTED  = 0b0000_0001;
+        const SHUTDOWN = 0b0000_0010;
+    }
+}
+
 /// Dispatcher for HTTP/2 protocol
+///
+/// Extended CONNECT streams (RFC 8441) are not supported, `h2` does not
+/// negotiate `SETTINGS_ENABLE_CONNECT_PROTOCOL` and rejects requests with
+/// `:protocol` pseudo-header.
 pub struct Dispatcher<T: IoStream, S: Service<Request = Request>, B: MessageBody> {
     service: CloneableService<S>,
     connection: Connection<T, Bytes>,
     on_connect: Option<Box<dyn DataFactory>>,
     config: ServiceConfig,
     peer_addr: Option<net::SocketAddr>,
-    ka_expire: Instant,
+    flags: Flags,
+    streams: Rc<Streams>,
+    idle_timeout: Option<Duration>,
     ka_timer: Option<Delay>,
+    ping: Option<KeepAlivePing>,
+    requests: usize,
+    age_timer: Option<Delay>,
     _t: PhantomData<B>,
 }
 
+/// Active streams of the connection
+struct Streams {
+    active: Cell<usize>,
+    idle_since: Cell<Instant>,
+    task: RefCell<Option<Task>>,
+}
+
+/// Stream is active until guard is dropped
+struct StreamGuard(Rc<Streams>, ServiceConfig);
+
+impl StreamGuard {
+    fn new(streams: &Rc<Streams>, config: &ServiceConfig) -> Self {
+        streams.active.set(streams.active.get() + 1);
+        StreamGuard(streams.clone(), config.clone())
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        let active = self.0.active.get() - 1;
+        self.0.active.set(active);
+
+        // wake up dispatcher, connection is idle
+        if active == 0 {
+            self.0.idle_since.set(self.1.now());
+            if let Some(ref task) = *self.0.task.borrow() {
+                task.notify();
+            }
+        }
+    }
+}
+
+/// Keep-alive PING state
+struct KeepAlivePing {
+    pong: PingPong,
+    interval: Duration,
+    timeout: Duration,
+    timer: Delay,
+    sent: bool,
+}
+
 impl<T, S, B> Dispatcher<T, S, B>
 where
     T: IoStream,
@@ -53,39 +116,151 @@ where
 {
     pub(crate) fn new(
         service: CloneableService<S>,
-        connection: Connection<T, Bytes>,
+        mut connection: Connection<T, Bytes>,
         on_connect: Option<Box<dyn DataFactory>>,
         config: ServiceConfig,
-        timeout: Option<Delay>,
         peer_addr: Option<net::SocketAddr>,
     ) -> Self {
-        // let keepalive = config.keep_alive_enabled();
-        // let flags = if keepalive {
-        // Flags::KEEPALIVE | Flags::KEEPALIVE_ENABLED
-        // } else {
-        //     Flags::empty()
-        // };
-
-        // keep-alive timer
-        let (ka_expire, ka_timer) = if let Some(delay) = timeout {
-            (delay.deadline(), Some(delay))
-        } else if let Some(delay) = config.keep_alive_timer() {
-            (delay.deadline(), Some(delay))
+        let now = config.now();
+
+        // keep-alive pings
+        let ping = if let Some((interval, timeout)) = config.h2_ping() {
+            connection.ping_pong().map(|pong| KeepAlivePing {
+                pong,
+                interval,
+                timeout,
+                timer: Delay::new(now + interval),
+                sent: false,
+            })
         } else {
-            (config.now(), None)
+            None
         };
 
         Dispatcher {
             service,
-            config,
             peer_addr,
             connection,
             on_connect,
-            ka_expire,
-            ka_timer,
+            ping,
+            flags: Flags::empty(),
+            streams: Rc::new(Streams {
+                active: Cell::new(0),
+                idle_since: Cell::new(now),
+                task: RefCell::new(None),
+            }),
+            idle_timeout: config.h2_idle_timeout(),
+            ka_timer: None,
+            requests: 0,
+            age_timer: config.max_connection_age().map(|age| Delay::new(now + age)),
+            config,
             _t: PhantomData,
         }
     }
+
+    /// Gracefully close connection if it stays idle longer than keep-alive timeout.
+    ///
+    /// Returns `true` if connection shutdown has been initiated.
+    fn poll_keepalive(&mut self) -> Result<bool, DispatchError> {
+        let timeout = match self.idle_timeout {
+            Some(timeout) if !self.flags.contains(Flags::SHUTDOWN) => timeout,
+            _ => return Ok(false),
+        };
+
+        // streams notify dispatcher when connection becomes idle
+        *self.streams.task.borrow_mut() = Some(task::current());
+
+        if self.streams.active.get() != 0
+            || (timeout.as_secs() == 0 && !self.flags.contains(Flags::STARTED))
+        {
+            return Ok(false);
+        }
+
+        let expire = self.streams.idle_since.get() + timeout;
+        let expired = if expire <= self.config.now() {
+            true
+        } else {
+            let timer = self.ka_timer.get_or_insert_with(|| Delay::new(expire));
+            if timer.deadline() != expire {
+                timer.reset(expire);
+            }
+            timer
+                .poll()
+                .map_err(|e| {
+                    error!("Timer error {:?}", e);
+                    DispatchError::Unknown
+                })?
+                .is_ready()
+        };
+
+        if expired {
+            trace!("Keep-alive timeout, close h2 connection");
+            self.flags.insert(Flags::SHUTDOWN);
+            self.connection.graceful_shutdown();
+        }
+        Ok(expired)
+    }
+
+    /// Gracefully close connection if it served max number of requests
+    /// or reached max age.
+    ///
+    /// Returns `true` if connection shutdown has been initiated.
+    fn poll_lifetime(&mut self) -> Result<bool, DispatchError> {
+        if self.flags.contains(Flags::SHUTDOWN) {
+            return Ok(false);
+        }
+
+        let exhausted = self
+            .config
+            .max_requests_per_connection()
+            .map_or(false, |max| self.requests >= max);
+        let expired = if let Some(ref mut timer) = self.age_timer {
+            timer
+                .poll()
+                .map_err(|e| {
+                    error!("Timer error {:?}", e);
+                    DispatchError::Unknown
+                })?
+                .is_ready()
+        } else {
+            false
+        };
+
+        if exhausted || expired {
+            trace!("Connection reached max requests or max age, close h2 connection");
+            self.flags.insert(Flags::SHUTDOWN);
+            self.connection.graceful_shutdown();
+        }
+        Ok(exhausted || expired)
+    }
+
+    /// Send keep-alive PING frames and check for acknowledgements.
+    fn poll_ping(&mut self) -> Result<(), DispatchError> {
+        if let Some(ref mut ping) = self.ping {
+            loop {
+                if ping.sent && ping.pong.poll_pong()?.is_ready() {
+                    ping.sent = false;
+                    ping.timer.reset(self.config.now() + ping.interval);
+                }
+
+                match ping.timer.poll().map_err(|e| {
+                    error!("Timer error {:?}", e);
+                    DispatchError::Unknown
+                })? {
+                    Async::Ready(_) => {
+                        if ping.sent {
+                            trace!("Keep-alive ping timeout, drop h2 connection");
+                            return Err(DispatchError::PingTimeout);
+                        }
+                        ping.pong.send_ping(Ping::opaque())?;
+                        ping.sent = true;
+                        ping.timer.reset(self.config.now() + ping.timeout);
+                    }
+                    Async::NotReady => return Ok(()),
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<T, S, B> Future for Dispatcher<T, S, B>
@@ -106,14 +281,16 @@ where
             match self.connection.poll()? {
                 Async::Ready(None) => return Ok(Async::Ready(())),
                 Async::Ready(Some((req, res))) => {
-                    // update keep-alive expire
-                    if self.ka_timer.is_some() {
-                        if let Some(expire) = self.config.keep_alive_expire() {
-                            self.ka_expire = expire;
-                        }
-                    }
+                    self.flags.insert(Flags::STARTED);
+                    self.requests += 1;
 
                     let (parts, body) = req.into_parts();
+                    let reset = Rc::new(StreamReset::default());
+                    let body = crate::h2::Payload::with_rate(
+                        body,
+                        self.config.min_request_rate(),
+                        reset.clone(),
+                    );
                     let mut req = Request::with_payload(body.into());
 
                     let head = &mut req.head_mut();
@@ -122,6 +299,12 @@ where
                     head.version = parts.version;
                     head.headers = parts.headers.into();
                     head.peer_addr = self.peer_addr;
+
+                    // set on_connect data
+                    if let Some(ref on_connect) = self.on_connect {
+                        on_connect.set(&mut req.extensions_mut());
+                    }
+
                     tokio_current_thread::spawn(ServiceResponse::<S::Future, B> {
                         state: ServiceResponseState::ServiceCall(
                             self.service.call(req),
@@ -129,9 +312,22 @@ where
                         ),
                         config: self.config.clone(),
                         buffer: None,
-                    })
+                        rate: self.config.min_response_rate().map(RateTracker::new),
+                        reset,
+                        _stream: StreamGuard::new(&self.streams, &self.config),
+                    });
+
+                    // stop accepting new streams
+                    self.poll_lifetime()?;
+                }
+                Async::NotReady => {
+                    if self.poll_lifetime()? || self.poll_keepalive()? {
+                        // poll connection to send GOAWAY frame
+                        continue;
+                    }
+                    self.poll_ping()?;
+                    return Ok(Async::NotReady);
                 }
-                Async::NotReady => return Ok(Async::NotReady),
             }
         }
     }
@@ -141,6 +337,9 @@ struct ServiceResponse<F, B> {
     state: ServiceResponseState<F, B>,
     config: ServiceConfig,
     buffer: Option<Bytes>,
+    rate: Option<RateTracker>,
+    reset: Rc<StreamReset>,
+    _stream: StreamGuard,
 }
 
 enum ServiceResponseState<F, B> {
@@ -214,22 +413,16 @@ where
 
         res
     }
-}
-
-impl<F, B> Future for ServiceResponse<F, B>
-where
-    F: Future,
-    F::Error: Into<Error>,
-    F::Item: Into<Response<B>>,
-    B: MessageBody + 'static,
-{
-    type Item = ();
-    type Error = ();
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+    fn poll_response(&mut self) -> Poll<(), ()> {
         match self.state {
             ServiceResponseState::ServiceCall(ref mut call, ref mut send) => {
-                match call.poll() {
+                let res = call.poll();
+                // do not respond if request payload resets the stream
+                if self.reset.poll_reset() {
+                    return Err(());
+                }
+                match res {
                     Ok(Async::Ready(res)) => {
                         let (res, body) = res.into().replace_body(());
 
@@ -246,7 +439,7 @@ where
                             Ok(Async::Ready(()))
                         } else {
                             self.state = ServiceResponseState::SendPayload(stream, body);
-                            self.poll()
+                            self.poll_response()
                         }
                     }
                     Ok(Async::NotReady) => Ok(Async::NotReady),
@@ -270,7 +463,7 @@ where
                                 stream,
                                 body.into_body(),
                             );
-                            self.poll()
+                            self.poll_response()
                         }
                     }
                 }
@@ -279,11 +472,30 @@ where
                 loop {
                     if let Some(ref mut buffer) = self.buffer {
                         match stream.poll_capacity().map_err(|e| warn!("{:?}", e))? {
-                            Async::NotReady => return Ok(Async::NotReady),
+                            Async::NotReady => {
+                                // time is counted only while waiting for flow control
+                                // window from client
+                                if let Some(ref mut rate) = self.rate {
+                                    rate.resume();
+                                    if rate.poll_check() {
+                                        warn!(
+                                            "Response data rate is below minimum, \
+                                             reset h2 stream"
+                                        );
+                                        stream.send_reset(h2::Reason::CANCEL);
+                                        return Err(());
+                                    }
+                                }
+                                return Ok(Async::NotReady);
+                            }
                             Async::Ready(None) => return Ok(Async::Ready(())),
                             Async::Ready(Some(cap)) => {
                                 let len = buffer.len();
                                 let bytes = buffer.split_to(std::cmp::min(cap, len));
+                                if let Some(ref mut rate) = self.rate {
+                                    rate.feed(bytes.len());
+                                    rate.pause();
+                                }
 
                                 if let Err(e) = stream.send_data(bytes, false) {
                                     warn!("{:?}", e);
@@ -302,7 +514,16 @@ where
                                 return Ok(Async::NotReady);
                             }
                             Ok(Async::Ready(None)) => {
-                                if let Err(e) = stream.send_data(Bytes::new(), true) {
+                                let res = if let Some(trailers) = body.trailers() {
+                                    let mut map = http::HeaderMap::new();
+                                    for (key, value) in trailers.iter() {
+                                        map.append(key.clone(), value.clone());
+                                    }
+                                    stream.send_trailers(map)
+                                } else {
+                                    stream.send_data(Bytes::new(), true)
+                                };
+                                if let Err(e) = res {
                                     warn!("{:?}", e);
                                     return Err(());
                                 } else {
@@ -327,3 +548,33 @@ where
         }
     }
 }
+
+impl<F, B> Future for ServiceResponse<F, B>
+where
+    F: Future,
+    F::Error: Into<Error>,
+    F::Item: Into<Response<B>>,
+    B: MessageBody + 'static,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let res = self.poll_response();
+
+        // request payload is polled by service or response body
+        if self.reset.poll_reset() {
+            match self.state {
+                ServiceResponseState::ServiceCall(_, Some(ref mut send)) => {
+                    send.send_reset(h2::Reason::CANCEL)
+                }
+                ServiceResponseState::SendPayload(ref mut stream, _) => {
+                    stream.send_reset(h2::Reason::CANCEL)
+                }
+                _ => (),
+            }
+            return Err(());
+        }
+        res
+    }
+}
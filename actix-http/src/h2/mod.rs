@@ -1,26 +1,87 @@
 #![allow(dead_code, unused_imports)]
 
-use std::fmt;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::{fmt, io};
 
 use bytes::Bytes;
+use futures::task::AtomicTask;
 use futures::{Async, Poll, Stream};
 use h2::RecvStream;
 
 mod dispatcher;
 mod service;
+pub(crate) mod upgrade;
 
 pub use self::dispatcher::Dispatcher;
 pub use self::service::H2Service;
 use crate::error::PayloadError;
+use crate::header::HeaderMap;
+use crate::rate::{DataRate, RateTracker};
 
 /// H2 receive stream
 pub struct Payload {
     pl: RecvStream,
+    trailers: Option<HeaderMap>,
+    eof: bool,
+    rate: Option<RateTracker>,
+    reset: Option<Rc<StreamReset>>,
+}
+
+/// Payload asks response of the same stream to reset the stream
+#[derive(Default)]
+pub(crate) struct StreamReset {
+    reset: Cell<bool>,
+    task: AtomicTask,
+}
+
+impl StreamReset {
+    /// Request stream reset
+    pub(crate) fn reset(&self) {
+        self.reset.set(true);
+        self.task.notify();
+    }
+
+    /// Check if stream reset is requested, register current task for
+    /// notification otherwise
+    pub(crate) fn poll_reset(&self) -> bool {
+        self.task.register();
+        self.reset.get()
+    }
 }
 
 impl Payload {
     pub(crate) fn new(pl: RecvStream) -> Self {
-        Self { pl }
+        Self {
+            pl,
+            trailers: None,
+            eof: false,
+            rate: None,
+            reset: None,
+        }
+    }
+
+    /// Create receive stream with minimum data rate, stream is reset
+    /// if client sends data slower
+    pub(crate) fn with_rate(
+        pl: RecvStream,
+        rate: Option<DataRate>,
+        reset: Rc<StreamReset>,
+    ) -> Self {
+        Self {
+            pl,
+            trailers: None,
+            eof: false,
+            rate: rate.map(RateTracker::new),
+            reset: Some(reset),
+        }
+    }
+
+    /// Trailing headers of request stream
+    ///
+    /// Trailers are available after payload stream is complete.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.trailers.clone()
     }
 }
 
@@ -29,17 +90,53 @@ impl Stream for Payload {
     type Error = PayloadError;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // trailers are received once
+        if self.eof {
+            return Ok(Async::Ready(None));
+        }
+
         match self.pl.poll() {
             Ok(Async::Ready(Some(chunk))) => {
                 let len = chunk.len();
+                if let Some(ref mut rate) = self.rate {
+                    rate.feed(len);
+                    rate.pause();
+                }
                 if let Err(err) = self.pl.release_capacity().release_capacity(len) {
                     Err(err.into())
                 } else {
                     Ok(Async::Ready(Some(chunk)))
                 }
             }
-            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
-            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(None)) => match self.pl.poll_trailers() {
+                Ok(Async::Ready(trailers)) => {
+                    self.eof = true;
+                    self.trailers = trailers.map(HeaderMap::from);
+                    Ok(Async::Ready(None))
+                }
+                Ok(Async::NotReady) => Ok(Async::NotReady),
+                Err(err) => Err(err.into()),
+            },
+            Ok(Async::NotReady) => {
+                // time is counted only while application waits for data
+                if let Some(ref mut rate) = self.rate {
+                    rate.resume();
+                    if rate.poll_check() {
+                        warn!(
+                            "Request body data rate is below minimum, reset h2 stream"
+                        );
+                        self.rate.take();
+                        if let Some(ref reset) = self.reset {
+                            reset.reset();
+                        }
+                        return Err(PayloadError::Io(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "Request body data rate is below minimum",
+                        )));
+                    }
+                }
+                Ok(Async::NotReady)
+            }
             Err(err) => Err(err.into()),
         }
     }
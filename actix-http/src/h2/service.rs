@@ -8,7 +8,7 @@ use actix_service::{IntoNewService, NewService, Service};
 use bytes::Bytes;
 use futures::future::{ok, FutureResult};
 use futures::{try_ready, Async, Future, IntoFuture, Poll, Stream};
-use h2::server::{self, Connection, Handshake};
+use h2::server::{Connection, Handshake};
 use h2::RecvStream;
 use log::error;
 
@@ -195,7 +195,7 @@ where
                 Some(self.cfg.clone()),
                 peer_addr,
                 on_connect,
-                server::handshake(io),
+                self.cfg.h2_builder().handshake(io),
             ),
         }
     }
@@ -255,7 +255,6 @@ where
                         conn,
                         on_connect.take(),
                         config.take().unwrap(),
-                        None,
                         *peer_addr,
                     ));
                     self.poll()
@@ -0,0 +1,357 @@
+//! `Upgrade: h2c` support
+//!
+//! Upgraded request is replayed to http/2 connection as stream 1.
+//! Request headers get encoded as HEADERS frames and injected right after
+//! first SETTINGS frame of the client. Settings from `HTTP2-Settings` header
+//! are acknowledged implicitly (RFC 7540, section 3.2), so they are merged
+//! into that SETTINGS frame instead of being sent as a separate frame.
+use bytes::{BufMut, Bytes, BytesMut};
+use http::header::{self, HeaderName};
+
+use crate::message::RequestHead;
+use crate::service::HTTP2_PREFACE;
+
+/// Response for successful `Upgrade: h2c` handshake
+pub(crate) const SWITCHING_PROTOCOLS: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nconnection: upgrade\r\nupgrade: h2c\r\n\r\n";
+
+// Minimum max frame size, every http/2 peer must accept frames of this size
+const MAX_FRAME_SIZE: usize = 16_384;
+
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_SETTINGS: u8 = 0x4;
+const FRAME_CONTINUATION: u8 = 0x9;
+
+const FLAG_ACK: u8 = 0x1;
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+
+/// Check if request is a valid `Upgrade: h2c` request and return decoded
+/// settings payload of `HTTP2-Settings` header.
+pub(crate) fn settings(head: &RequestHead) -> Option<Bytes> {
+    let has_token = |name: HeaderName, token: &str| {
+        head.headers.get_all(name).any(|val| {
+            val.to_str()
+                .map(|s| s.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+                .unwrap_or(false)
+        })
+    };
+
+    if !has_token(header::UPGRADE, "h2c")
+        || !has_token(header::CONNECTION, "upgrade")
+        || !has_token(header::CONNECTION, "http2-settings")
+    {
+        return None;
+    }
+
+    // exactly one HTTP2-Settings header is allowed
+    let mut values = head.headers.get_all("http2-settings");
+    let value = match (values.next(), values.next()) {
+        (Some(value), None) => value,
+        _ => return None,
+    };
+
+    let payload = base64::decode_config(value.as_bytes(), base64::URL_SAFE_NO_PAD)
+        .or_else(|_| base64::decode_config(value.as_bytes(), base64::URL_SAFE))
+        .ok()?;
+
+    // every setting is 6 bytes long
+    if payload.len() % 6 == 0 {
+        Some(Bytes::from(payload))
+    } else {
+        None
+    }
+}
+
+/// Check if buffer contains client connection preface and complete first
+/// frame.
+///
+/// Frames larger than max frame size are never buffered, http/2
+/// connection rejects them anyway.
+pub(crate) fn has_first_frame(buf: &[u8]) -> bool {
+    let offset = HTTP2_PREFACE.len();
+    if buf.len() < offset + 9 {
+        return false;
+    }
+    let len = frame_len(&buf[offset..]);
+    len > MAX_FRAME_SIZE || buf.len() >= offset + 9 + len
+}
+
+/// Prepare read buffer of upgraded connection for http/2 handshake.
+///
+/// Upgrade settings get merged into first SETTINGS frame of the client,
+/// values from the frame take precedence, and request frames go right after
+/// it. Client receives single ACK for the frame, as it expects.
+pub(crate) fn replay(mut buf: BytesMut, settings: &[u8], frames: &[u8]) -> BytesMut {
+    let offset = HTTP2_PREFACE.len();
+    if !has_first_frame(&buf) || buf[..offset] != HTTP2_PREFACE[..] {
+        return buf;
+    }
+    let len = frame_len(&buf[offset..]);
+    if len > MAX_FRAME_SIZE {
+        return buf;
+    }
+
+    let rest = buf.split_off(offset + 9 + len);
+    let is_settings = buf[offset + 3] == FRAME_SETTINGS
+        && buf[offset + 4] & FLAG_ACK == 0
+        && buf[offset + 5..offset + 9] == [0, 0, 0, 0];
+    let mut buf = if is_settings && len + settings.len() <= MAX_FRAME_SIZE {
+        let payload = buf.split_off(offset + 9);
+        buf.truncate(offset);
+        buf.reserve(9 + settings.len() + payload.len());
+        write_frame_head(
+            &mut buf,
+            settings.len() + payload.len(),
+            FRAME_SETTINGS,
+            0,
+            0,
+        );
+        buf.extend_from_slice(settings);
+        buf.extend_from_slice(&payload);
+        buf
+    } else {
+        buf
+    };
+    buf.reserve(frames.len() + rest.len());
+    buf.extend_from_slice(frames);
+    buf.extend_from_slice(&rest);
+    buf
+}
+
+fn frame_len(head: &[u8]) -> usize {
+    (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize
+}
+
+/// Encode upgrade request head as http/2 frames of stream 1.
+pub(crate) fn frames(head: &RequestHead) -> BytesMut {
+    let block = encode_head(head);
+    let mut buf = BytesMut::with_capacity(block.len() + 64);
+
+    let mut chunks = block.chunks(MAX_FRAME_SIZE).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let mut flags = if chunks.peek().is_none() {
+            FLAG_END_HEADERS
+        } else {
+            0
+        };
+        let kind = if first {
+            // upgraded request never has a body
+            flags |= FLAG_END_STREAM;
+            FRAME_HEADERS
+        } else {
+            FRAME_CONTINUATION
+        };
+        first = false;
+
+        write_frame_head(&mut buf, chunk.len(), kind, flags, 1);
+        buf.extend_from_slice(chunk);
+    }
+    buf
+}
+
+fn write_frame_head(buf: &mut BytesMut, len: usize, kind: u8, flags: u8, stream: u32) {
+    buf.reserve(9 + len);
+    buf.put_uint_be(len as u64, 3);
+    buf.put_u8(kind);
+    buf.put_u8(flags);
+    buf.put_u32_be(stream);
+}
+
+/// Encode request head as hpack header block.
+///
+/// Every field is encoded as literal without indexing, so encoder does not
+/// need to track dynamic table state.
+fn encode_head(head: &RequestHead) -> BytesMut {
+    let mut buf = BytesMut::with_capacity(1024);
+
+    let path = head.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    let authority = head
+        .uri
+        .authority_part()
+        .map(|a| a.as_str().as_bytes())
+        .or_else(|| head.headers.get(header::HOST).map(|h| h.as_bytes()));
+
+    encode_field(&mut buf, b":method", head.method.as_str().as_bytes());
+    encode_field(&mut buf, b":scheme", b"http");
+    encode_field(&mut buf, b":path", path.as_bytes());
+    if let Some(authority) = authority {
+        encode_field(&mut buf, b":authority", authority);
+    }
+
+    for (name, value) in head.headers.iter() {
+        match *name {
+            header::CONNECTION
+            | header::UPGRADE
+            | header::HOST
+            | header::TRANSFER_ENCODING
+            | header::TE => continue,
+            _ => (),
+        }
+        let name = name.as_str();
+        if name == "http2-settings" || name == "keep-alive" || name == "proxy-connection"
+        {
+            continue;
+        }
+        encode_field(&mut buf, name.as_bytes(), value.as_bytes());
+    }
+    buf
+}
+
+fn encode_field(buf: &mut BytesMut, name: &[u8], value: &[u8]) {
+    buf.reserve(name.len() + value.len() + 12);
+    // literal header field without indexing, new name
+    buf.put_u8(0);
+    encode_str(buf, name);
+    encode_str(buf, value);
+}
+
+fn encode_str(buf: &mut BytesMut, s: &[u8]) {
+    // no huffman encoding, 7 bit prefix
+    encode_int(buf, s.len(), 7, 0);
+    buf.extend_from_slice(s);
+}
+
+fn encode_int(buf: &mut BytesMut, mut value: usize, prefix: u8, first: u8) {
+    let max = (1usize << prefix) - 1;
+    if value < max {
+        buf.put_u8(first | value as u8);
+        return;
+    }
+    buf.put_u8(first | max as u8);
+    value -= max;
+    while value >= 128 {
+        buf.put_u8((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn test_settings() {
+        let req = TestRequest::default()
+            .header(header::CONNECTION, "Upgrade, HTTP2-Settings")
+            .header(header::UPGRADE, "h2c")
+            .header("http2-settings", "AAMAAABkAAQAoAAA")
+            .finish();
+        let settings = settings(req.head()).unwrap();
+        assert_eq!(&settings[..], &[0, 3, 0, 0, 0, 100, 0, 4, 0, 160, 0, 0][..]);
+
+        let req = TestRequest::default()
+            .header(header::CONNECTION, "Upgrade")
+            .header(header::UPGRADE, "h2c")
+            .header("http2-settings", "AAMAAABkAAQAoAAA")
+            .finish();
+        assert!(super::settings(req.head()).is_none());
+
+        let req = TestRequest::default()
+            .header(header::CONNECTION, "Upgrade, HTTP2-Settings")
+            .header(header::UPGRADE, "websocket")
+            .header("http2-settings", "")
+            .finish();
+        assert!(super::settings(req.head()).is_none());
+    }
+
+    #[test]
+    fn test_frames() {
+        let req = TestRequest::with_uri("/index.html?q=1")
+            .header(header::HOST, "example.com")
+            .header(header::CONNECTION, "Upgrade, HTTP2-Settings")
+            .header(header::UPGRADE, "h2c")
+            .header("http2-settings", "")
+            .header("x-test", "value")
+            .finish();
+        let buf = frames(req.head());
+
+        // headers frame for stream 1
+        let len = frame_len(&buf);
+        assert_eq!(buf[3], FRAME_HEADERS);
+        assert_eq!(buf[4], FLAG_END_STREAM | FLAG_END_HEADERS);
+        assert_eq!(&buf[5..9], &[0, 0, 0, 1]);
+        assert_eq!(buf.len(), 9 + len);
+
+        let block = &buf[9..];
+        assert_eq!(&block[..10], b"\x00\x07:method\x03");
+        assert!(contains(block, b"\x05:path\x0f/index.html?q=1"));
+        assert!(contains(block, b"\x0a:authority\x0bexample.com"));
+        assert!(contains(block, b"\x06x-test\x05value"));
+        assert!(!contains(block, b"upgrade"));
+        assert!(!contains(block, b"http2-settings"));
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut buf = BytesMut::from(&HTTP2_PREFACE[..]);
+        assert!(!has_first_frame(&buf));
+        // client settings, initial window size
+        buf.extend_from_slice(&[0, 0, 6, FRAME_SETTINGS, 0, 0, 0, 0, 0]);
+        assert!(!has_first_frame(&buf));
+        buf.extend_from_slice(&[0, 4, 0, 0, 1, 0]);
+        assert!(has_first_frame(&buf));
+        // window update, sent right after settings
+        buf.extend_from_slice(&[0, 0, 4, 0x8, 0, 0, 0, 0, 0, 0, 0, 1, 0]);
+
+        let buf = replay(buf, &[0, 3, 0, 0, 0, 100], b"frames");
+        let offset = HTTP2_PREFACE.len();
+        assert_eq!(&buf[..offset], &HTTP2_PREFACE[..]);
+        assert_eq!(
+            &buf[offset..offset + 21],
+            &[
+                0,
+                0,
+                12,
+                FRAME_SETTINGS,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                3,
+                0,
+                0,
+                0,
+                100,
+                0,
+                4,
+                0,
+                0,
+                1,
+                0
+            ][..]
+        );
+        assert_eq!(&buf[offset + 21..offset + 27], b"frames");
+        assert_eq!(
+            &buf[offset + 27..],
+            &[0, 0, 4, 0x8, 0, 0, 0, 0, 0, 0, 0, 1, 0]
+        );
+
+        // first frame is not settings, nothing to merge into
+        let mut buf = BytesMut::from(&HTTP2_PREFACE[..]);
+        buf.extend_from_slice(&[0, 0, 0, FRAME_SETTINGS, FLAG_ACK, 0, 0, 0, 0]);
+        let buf = replay(buf, &[0, 3, 0, 0, 0, 100], b"frames");
+        assert_eq!(&buf[offset..offset + 9], &[0, 0, 0, 4, 1, 0, 0, 0, 0]);
+        assert_eq!(&buf[offset + 9..], b"frames");
+    }
+
+    #[test]
+    fn test_encode_int() {
+        let mut buf = BytesMut::new();
+        encode_int(&mut buf, 10, 5, 0);
+        assert_eq!(&buf[..], &[10]);
+
+        let mut buf = BytesMut::new();
+        encode_int(&mut buf, 1337, 5, 0);
+        assert_eq!(&buf[..], &[31, 154, 10]);
+    }
+
+    fn contains(buf: &[u8], item: &[u8]) -> bool {
+        buf.windows(item.len()).any(|w| w == item)
+    }
+}
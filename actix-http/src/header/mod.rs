@@ -141,6 +141,8 @@ pub enum ContentEncoding {
     Gzip,
     /// Indicates the identity function (i.e. no compression, nor modification)
     Identity,
+    /// A format using the Zstandard algorithm
+    Zstd,
 }
 
 impl ContentEncoding {
@@ -160,6 +162,7 @@ impl ContentEncoding {
             ContentEncoding::Br => "br",
             ContentEncoding::Gzip => "gzip",
             ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Zstd => "zstd",
             ContentEncoding::Identity | ContentEncoding::Auto => "identity",
         }
     }
@@ -169,6 +172,7 @@ impl ContentEncoding {
     pub fn quality(self) -> f64 {
         match self {
             ContentEncoding::Br => 1.1,
+            ContentEncoding::Zstd => 1.05,
             ContentEncoding::Gzip => 1.0,
             ContentEncoding::Deflate => 0.9,
             ContentEncoding::Identity | ContentEncoding::Auto => 0.1,
@@ -186,6 +190,8 @@ impl<'a> From<&'a str> for ContentEncoding {
             ContentEncoding::Gzip
         } else if s.eq_ignore_ascii_case("deflate") {
             ContentEncoding::Deflate
+        } else if s.eq_ignore_ascii_case("zstd") {
+            ContentEncoding::Zstd
         } else {
             ContentEncoding::Identity
         }
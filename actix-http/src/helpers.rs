@@ -194,6 +194,13 @@ impl<T: Clone + 'static> DataFactory for Data<T> {
     }
 }
 
+impl<A: DataFactory + ?Sized, B: DataFactory> DataFactory for (Box<A>, B) {
+    fn set(&self, ext: &mut Extensions) {
+        self.0.set(ext);
+        self.1.set(ext);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -23,6 +23,7 @@ mod httpcodes;
 pub mod httpmessage;
 mod message;
 mod payload;
+mod rate;
 mod request;
 mod response;
 mod service;
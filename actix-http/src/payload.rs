@@ -3,6 +3,7 @@ use futures::{Async, Poll, Stream};
 use h2::RecvStream;
 
 use crate::error::PayloadError;
+use crate::header::HeaderMap;
 
 /// Type represent boxed payload
 pub type PayloadStream = Box<dyn Stream<Item = Bytes, Error = PayloadError>>;
@@ -44,6 +45,17 @@ impl<S> Payload<S> {
     pub fn take(&mut self) -> Payload<S> {
         std::mem::replace(self, Payload::None)
     }
+
+    /// Trailing headers of request payload
+    ///
+    /// Trailers are available after payload stream is complete.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        match self {
+            Payload::H1(ref pl) => pl.trailers(),
+            Payload::H2(ref pl) => pl.trailers(),
+            Payload::None | Payload::Stream(_) => None,
+        }
+    }
 }
 
 impl<S> Stream for Payload<S>
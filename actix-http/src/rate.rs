@@ -0,0 +1,164 @@
+//! Minimum data rate enforcement
+use std::cmp;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future};
+use log::error;
+use tokio_timer::Delay;
+
+/// Interval of data rate checks after grace period
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Minimum data rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DataRate {
+    /// Minimum number of bytes per second
+    pub(crate) bytes_per_sec: u64,
+    /// Rate is not enforced until grace period elapses
+    pub(crate) grace_period: Duration,
+}
+
+impl DataRate {
+    /// Create data rate, grace period is in milliseconds.
+    ///
+    /// Returns `None` if `bytes_per_sec` is 0.
+    pub(crate) fn new(bytes_per_sec: u64, grace_period: u64) -> Option<DataRate> {
+        if bytes_per_sec == 0 {
+            None
+        } else {
+            Some(DataRate {
+                bytes_per_sec,
+                grace_period: Duration::from_millis(grace_period),
+            })
+        }
+    }
+}
+
+/// Data rate tracker
+///
+/// Time is counted only while tracker is resumed, i.e. while peer is
+/// expected to send or receive data.
+pub(crate) struct RateTracker {
+    rate: DataRate,
+    bytes: u64,
+    elapsed: Duration,
+    resumed: Option<Instant>,
+    timer: Option<Delay>,
+}
+
+impl RateTracker {
+    pub(crate) fn new(rate: DataRate) -> RateTracker {
+        RateTracker {
+            rate,
+            bytes: 0,
+            elapsed: Duration::from_secs(0),
+            resumed: None,
+            timer: None,
+        }
+    }
+
+    /// Start or resume time counting
+    pub(crate) fn resume(&mut self) {
+        if self.resumed.is_none() {
+            self.resumed = Some(Instant::now());
+        }
+    }
+
+    /// Pause time counting
+    pub(crate) fn pause(&mut self) {
+        if let Some(resumed) = self.resumed.take() {
+            self.elapsed += Instant::now() - resumed;
+        }
+    }
+
+    /// Reset tracker, i.e. when message is complete
+    pub(crate) fn reset(&mut self) {
+        self.bytes = 0;
+        self.elapsed = Duration::from_secs(0);
+        self.resumed = None;
+    }
+
+    /// Add number of transferred bytes
+    pub(crate) fn feed(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        if let Some(resumed) = self.resumed {
+            self.elapsed + (now - resumed)
+        } else {
+            self.elapsed
+        }
+    }
+
+    fn is_too_slow(&self, now: Instant) -> bool {
+        let elapsed = self.elapsed(now);
+        if elapsed <= self.rate.grace_period {
+            return false;
+        }
+        let millis = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+        self.bytes.saturating_mul(1000) < self.rate.bytes_per_sec.saturating_mul(millis)
+    }
+
+    /// Check data rate, returns `true` if data rate is below minimum.
+    ///
+    /// While tracker is resumed, check timer is registered with current task.
+    pub(crate) fn poll_check(&mut self) -> bool {
+        if self.resumed.is_none() {
+            return false;
+        }
+
+        let now = Instant::now();
+        if self.is_too_slow(now) {
+            return true;
+        }
+
+        let grace = self
+            .rate
+            .grace_period
+            .checked_sub(self.elapsed(now))
+            .unwrap_or_default();
+        let deadline = now + cmp::max(grace, CHECK_INTERVAL);
+        let timer = self.timer.get_or_insert_with(|| Delay::new(deadline));
+        match timer.poll() {
+            Ok(Async::Ready(_)) => {
+                timer.reset(deadline);
+                let _ = timer.poll();
+            }
+            Ok(Async::NotReady) => (),
+            Err(e) => error!("Timer error {:?}", e),
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_rate() {
+        assert!(DataRate::new(0, 1000).is_none());
+
+        let rate = DataRate::new(100, 1000).unwrap();
+        let mut tracker = RateTracker::new(rate);
+        let now = Instant::now();
+        assert!(!tracker.is_too_slow(now + Duration::from_secs(10)));
+
+        tracker.resume();
+        let now = Instant::now();
+        assert!(!tracker.is_too_slow(now + Duration::from_millis(500)));
+        assert!(tracker.is_too_slow(now + Duration::from_secs(2)));
+
+        tracker.feed(300);
+        assert!(!tracker.is_too_slow(now + Duration::from_secs(2)));
+        assert!(tracker.is_too_slow(now + Duration::from_secs(4)));
+
+        tracker.pause();
+        assert!(!tracker.is_too_slow(now + Duration::from_secs(10)));
+
+        tracker.reset();
+        assert_eq!(tracker.bytes, 0);
+        assert!(!tracker.poll_check());
+    }
+}
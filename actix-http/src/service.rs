@@ -8,13 +8,15 @@ use actix_server_config::{
 use actix_service::{IntoNewService, NewService, Service};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use futures::{try_ready, Async, Future, IntoFuture, Poll};
-use h2::server::{self, Handshake};
+use h2::server::Handshake;
+use tokio_timer::Delay;
 
 use crate::body::MessageBody;
 use crate::builder::HttpServiceBuilder;
 use crate::cloneable::CloneableService;
 use crate::config::{KeepAlive, ServiceConfig};
 use crate::error::{DispatchError, Error};
+use crate::h2::upgrade as h2c;
 use crate::helpers::DataFactory;
 use crate::request::Request;
 use crate::response::Response;
@@ -356,7 +358,7 @@ where
                 };
                 HttpServiceHandlerResponse {
                     state: State::Handshake(Some((
-                        server::handshake(io),
+                        self.cfg.h2_builder().handshake(io),
                         self.cfg.clone(),
                         self.srv.clone(),
                         peer_addr,
@@ -403,6 +405,18 @@ where
 {
     H1(h1::Dispatcher<T, S, B, X, U>),
     H2(Dispatcher<Io<T>, S, B>),
+    H2c(
+        Option<(
+            T,
+            BytesMut,
+            Bytes,
+            BytesMut,
+            ServiceConfig,
+            CloneableService<S>,
+            Option<Box<dyn DataFactory>>,
+            Option<Delay>,
+        )>,
+    ),
     Unknown(
         Option<(
             T,
@@ -441,7 +455,9 @@ where
     state: State<T, S, B, X, U>,
 }
 
-const HTTP2_PREFACE: [u8; 14] = *b"PRI * HTTP/2.0";
+/// Http/2 client connection preface, first 14 bytes are enough to tell it
+/// apart from http/1 request
+pub(crate) const HTTP2_PREFACE: [u8; 24] = *b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 
 impl<T, S, B, X, U> Future for HttpServiceHandlerResponse<T, S, B, X, U>
 where
@@ -461,7 +477,79 @@ where
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         match self.state {
-            State::H1(ref mut disp) => disp.poll(),
+            State::H1(ref mut disp) => {
+                try_ready!(disp.poll());
+
+                // connection got upgraded with `Upgrade: h2c` request
+                if let Some(parts) = disp.take_h2c() {
+                    let frames = h2c::frames(parts.request.head());
+                    // client has to send connection preface within client timeout
+                    let timer = parts.config.client_timer();
+                    self.state = State::H2c(Some((
+                        parts.io,
+                        parts.read_buf,
+                        parts.settings,
+                        frames,
+                        parts.config,
+                        parts.service,
+                        parts.on_connect,
+                        timer,
+                    )));
+                    self.poll()
+                } else {
+                    Ok(Async::Ready(()))
+                }
+            }
+            State::H2c(ref mut data) => {
+                if let Some(ref mut item) = data {
+                    // wait for client connection preface and settings
+                    while !h2c::has_first_frame(&item.1) {
+                        if let Some(ref mut timer) = item.7 {
+                            let expired = timer
+                                .poll()
+                                .map_err(|e| {
+                                    error!("Timer error {:?}", e);
+                                    DispatchError::Unknown
+                                })?
+                                .is_ready();
+                            if expired {
+                                trace!("Slow h2c connection preface, close connection");
+                                return Err(DispatchError::SlowRequestTimeout);
+                            }
+                        }
+                        item.1.reserve(HTTP2_PREFACE.len());
+                        // Safety - we only write to the returned slice.
+                        let b = unsafe { item.1.bytes_mut() };
+                        let n = try_ready!(item.0.poll_read(b));
+                        if n == 0 {
+                            return Ok(Async::Ready(()));
+                        }
+                        // Safety - we know that 'n' bytes have
+                        // been initialized via the contract of
+                        // 'poll_read'
+                        unsafe { item.1.advance_mut(n) };
+                    }
+                } else {
+                    panic!()
+                }
+                let (io, buf, settings, frames, cfg, srv, on_connect, _) =
+                    data.take().unwrap();
+                let buf = h2c::replay(buf, &settings, &frames);
+
+                let peer_addr = io.peer_addr();
+                let io = Io {
+                    inner: io,
+                    unread: Some(buf),
+                };
+                self.state = State::Handshake(Some((
+                    cfg.h2_builder().handshake(io),
+                    cfg,
+                    srv,
+                    peer_addr,
+                    on_connect,
+                )));
+                self.poll()
+            }
             State::H2(ref mut disp) => disp.poll(),
             State::Unknown(ref mut data) => {
                 if let Some(ref mut item) = data {
@@ -476,7 +564,7 @@ where
                         // been initialized via the contract of
                         // 'poll_read'
                         unsafe { item.1.advance_mut(n) };
-                        if item.1.len() >= HTTP2_PREFACE.len() {
+                        if item.1.len() >= 14 {
                             break;
                         }
                     }
@@ -485,31 +573,35 @@ where
                 }
                 let (io, buf, cfg, srv, expect, upgrade, on_connect) =
                     data.take().unwrap();
-                if buf[..14] == HTTP2_PREFACE[..] {
+                if buf[..14] == HTTP2_PREFACE[..14] {
                     let peer_addr = io.peer_addr();
                     let io = Io {
                         inner: io,
                         unread: Some(buf),
                     };
                     self.state = State::Handshake(Some((
-                        server::handshake(io),
+                        cfg.h2_builder().handshake(io),
                         cfg,
                         srv,
                         peer_addr,
                         on_connect,
                     )));
                 } else {
-                    self.state = State::H1(h1::Dispatcher::with_timeout(
-                        io,
-                        h1::Codec::new(cfg.clone()),
-                        cfg,
-                        buf,
-                        None,
-                        srv,
-                        expect,
-                        upgrade,
-                        on_connect,
-                    ))
+                    // cleartext connection, allow `Upgrade: h2c`
+                    self.state = State::H1(
+                        h1::Dispatcher::with_timeout(
+                            io,
+                            h1::Codec::new(cfg.clone()),
+                            cfg,
+                            buf,
+                            None,
+                            srv,
+                            expect,
+                            upgrade,
+                            on_connect,
+                        )
+                        .h2c(),
+                    )
                 }
                 self.poll()
             }
@@ -527,9 +619,8 @@ where
                     panic!()
                 };
                 let (_, cfg, srv, peer_addr, on_connect) = data.take().unwrap();
-                self.state = State::H2(Dispatcher::new(
-                    srv, conn, on_connect, cfg, None, peer_addr,
-                ));
+                self.state =
+                    State::H2(Dispatcher::new(srv, conn, on_connect, cfg, peer_addr));
                 self.poll()
             }
         }
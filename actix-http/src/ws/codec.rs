@@ -1,6 +1,7 @@
 use actix_codec::{Decoder, Encoder};
 use bytes::{Bytes, BytesMut};
 
+use super::deflate::{DeflateConfig, DeflateContext};
 use super::frame::Parser;
 use super::proto::{CloseReason, OpCode};
 use super::ProtocolError;
@@ -18,6 +19,8 @@ pub enum Message {
     Pong(String),
     /// Close message with optional reason
     Close(Option<CloseReason>),
+    /// Fragment of fragmented message
+    Continuation(Item),
     /// No-op. Useful for actix-net services
     Nop,
 }
@@ -35,13 +38,36 @@ pub enum Frame {
     Pong(String),
     /// Close message with optional reason
     Close(Option<CloseReason>),
+    /// Fragment of fragmented message
+    Continuation(Item),
 }
 
-#[derive(Debug, Copy, Clone)]
+/// `WebSocket` fragmented message item
+///
+/// Fragmented message starts with `FirstText` or `FirstBinary` item,
+/// followed by any number of `Continue` items and ends with `Last` item.
+/// Control frames could be sent in between, but not other data frames.
+#[derive(Debug, PartialEq)]
+pub enum Item {
+    /// First fragment of text message
+    FirstText(Bytes),
+    /// First fragment of binary message
+    FirstBinary(Bytes),
+    /// Intermediate fragment
+    Continue(Bytes),
+    /// Final fragment
+    Last(Bytes),
+}
+
+#[derive(Debug, Clone)]
 /// WebSockets protocol codec
 pub struct Codec {
     max_size: usize,
     server: bool,
+    continuation: bool,
+    w_continuation: bool,
+    compressed: bool,
+    deflate: Option<DeflateContext>,
 }
 
 impl Codec {
@@ -50,12 +76,17 @@ impl Codec {
         Codec {
             max_size: 65_536,
             server: true,
+            continuation: false,
+            w_continuation: false,
+            compressed: false,
+            deflate: None,
         }
     }
 
     /// Set max frame size
     ///
-    /// By default max size is set to 64kb
+    /// By default max size is set to 64kb. For compressed messages
+    /// limit is also applied to decompressed payload.
     pub fn max_size(mut self, size: usize) -> Self {
         self.max_size = size;
         self
@@ -68,6 +99,58 @@ impl Codec {
         self.server = false;
         self
     }
+
+    /// Enable `permessage-deflate` compression with negotiated settings.
+    ///
+    /// Text and binary messages get compressed, compressed messages
+    /// from peer get decompressed.
+    pub fn deflate(mut self, cfg: DeflateConfig) -> Self {
+        self.deflate = Some(DeflateContext::new(cfg));
+        self
+    }
+
+    /// Write data frame, payload gets compressed if compression is enabled
+    fn write_data(
+        &mut self,
+        dst: &mut BytesMut,
+        data: Bytes,
+        op: OpCode,
+        fin: bool,
+    ) -> Result<(), ProtocolError> {
+        if let Some(ref mut deflate) = self.deflate {
+            let data = deflate.compress(&data, fin, self.server)?;
+            // RSV1 bit is set on the first frame of the message only
+            let rsv1 = op != OpCode::Continue;
+            Parser::write_frame(dst, data, op, fin, rsv1, !self.server);
+        } else {
+            Parser::write_message(dst, data, op, fin, !self.server);
+        }
+        Ok(())
+    }
+
+    /// Decompress data frame payload of compressed message
+    fn read_data(
+        &mut self,
+        payload: Option<BytesMut>,
+        fin: bool,
+    ) -> Result<Option<BytesMut>, ProtocolError> {
+        match self.deflate {
+            Some(ref mut deflate) if self.compressed => {
+                let data = deflate.decompress(
+                    payload.as_ref().map(|pl| &pl[..]).unwrap_or(&[]),
+                    fin,
+                    self.server,
+                    self.max_size,
+                )?;
+                if data.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(data))
+                }
+            }
+            _ => Ok(payload),
+        }
+    }
 }
 
 impl Encoder for Codec {
@@ -77,11 +160,9 @@ impl Encoder for Codec {
     fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
             Message::Text(txt) => {
-                Parser::write_message(dst, txt, OpCode::Text, true, !self.server)
-            }
-            Message::Binary(bin) => {
-                Parser::write_message(dst, bin, OpCode::Binary, true, !self.server)
+                self.write_data(dst, txt.into(), OpCode::Text, true)?
             }
+            Message::Binary(bin) => self.write_data(dst, bin, OpCode::Binary, true)?,
             Message::Ping(txt) => {
                 Parser::write_message(dst, txt, OpCode::Ping, true, !self.server)
             }
@@ -89,6 +170,35 @@ impl Encoder for Codec {
                 Parser::write_message(dst, txt, OpCode::Pong, true, !self.server)
             }
             Message::Close(reason) => Parser::write_close(dst, reason, !self.server),
+            Message::Continuation(item) => match item {
+                Item::FirstText(data) => {
+                    if self.w_continuation {
+                        return Err(ProtocolError::ContinuationStarted);
+                    }
+                    self.w_continuation = true;
+                    self.write_data(dst, data, OpCode::Text, false)?
+                }
+                Item::FirstBinary(data) => {
+                    if self.w_continuation {
+                        return Err(ProtocolError::ContinuationStarted);
+                    }
+                    self.w_continuation = true;
+                    self.write_data(dst, data, OpCode::Binary, false)?
+                }
+                Item::Continue(data) => {
+                    if !self.w_continuation {
+                        return Err(ProtocolError::ContinuationNotStarted);
+                    }
+                    self.write_data(dst, data, OpCode::Continue, false)?
+                }
+                Item::Last(data) => {
+                    if !self.w_continuation {
+                        return Err(ProtocolError::ContinuationNotStarted);
+                    }
+                    self.w_continuation = false;
+                    self.write_data(dst, data, OpCode::Continue, true)?
+                }
+            },
             Message::Nop => (),
         }
         Ok(())
@@ -100,15 +210,59 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.server, self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
-                // continuation is not supported
+        match Parser::parse_frame(src, self.server, self.max_size) {
+            Ok(Some((finished, rsv1, opcode, payload))) => {
+                // compressed message has RSV1 bit set on the first frame only
+                match opcode {
+                    OpCode::Text | OpCode::Binary if !self.continuation => {
+                        self.compressed = rsv1 && self.deflate.is_some();
+                    }
+                    _ => {
+                        if rsv1 && self.deflate.is_some() {
+                            return Err(ProtocolError::Compression);
+                        }
+                    }
+                }
+
+                // fragmented message
                 if !finished {
-                    return Err(ProtocolError::NoContinuation);
+                    return match opcode {
+                        OpCode::Continue if self.continuation => {
+                            let data = freeze(self.read_data(payload, false)?);
+                            Ok(Some(Frame::Continuation(Item::Continue(data))))
+                        }
+                        OpCode::Continue => Err(ProtocolError::ContinuationNotStarted),
+                        OpCode::Text | OpCode::Binary if self.continuation => {
+                            Err(ProtocolError::ContinuationStarted)
+                        }
+                        OpCode::Text => {
+                            self.continuation = true;
+                            let data = freeze(self.read_data(payload, false)?);
+                            Ok(Some(Frame::Continuation(Item::FirstText(data))))
+                        }
+                        OpCode::Binary => {
+                            self.continuation = true;
+                            let data = freeze(self.read_data(payload, false)?);
+                            Ok(Some(Frame::Continuation(Item::FirstBinary(data))))
+                        }
+                        // control frames must not be fragmented
+                        _ => Err(ProtocolError::ContinuationFragment(opcode)),
+                    };
                 }
 
                 match opcode {
-                    OpCode::Continue => Err(ProtocolError::NoContinuation),
+                    OpCode::Continue => {
+                        if self.continuation {
+                            self.continuation = false;
+                            let data = freeze(self.read_data(payload, true)?);
+                            Ok(Some(Frame::Continuation(Item::Last(data))))
+                        } else {
+                            Err(ProtocolError::ContinuationNotStarted)
+                        }
+                    }
+                    OpCode::Text | OpCode::Binary if self.continuation => {
+                        Err(ProtocolError::ContinuationStarted)
+                    }
                     OpCode::Bad => Err(ProtocolError::BadOpCode),
                     OpCode::Close => {
                         if let Some(ref pl) = payload {
@@ -132,9 +286,11 @@ impl Decoder for Codec {
                             Ok(Some(Frame::Pong(String::new())))
                         }
                     }
-                    OpCode::Binary => Ok(Some(Frame::Binary(payload))),
+                    OpCode::Binary => {
+                        Ok(Some(Frame::Binary(self.read_data(payload, true)?)))
+                    }
                     OpCode::Text => {
-                        Ok(Some(Frame::Text(payload)))
+                        Ok(Some(Frame::Text(self.read_data(payload, true)?)))
                         //let tmp = Vec::from(payload.as_ref());
                         //match String::from_utf8(tmp) {
                         //    Ok(s) => Ok(Some(Message::Text(s))),
@@ -148,3 +304,175 @@ impl Decoder for Codec {
         }
     }
 }
+
+fn freeze(payload: Option<BytesMut>) -> Bytes {
+    payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(fin: bool, op: OpCode, data: &'static [u8]) -> BytesMut {
+        let mut buf = BytesMut::new();
+        Parser::write_message(&mut buf, data, op, fin, true);
+        buf
+    }
+
+    #[test]
+    fn test_decode_continuation() {
+        let mut codec = Codec::new();
+
+        let mut buf = frame(false, OpCode::Text, b"first");
+        buf.extend_from_slice(&frame(false, OpCode::Continue, b"second"));
+        buf.extend_from_slice(&frame(true, OpCode::Ping, b"ping"));
+        buf.extend_from_slice(&frame(true, OpCode::Continue, b"last"));
+
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::FirstText(Bytes::from_static(
+                b"first"
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Continue(Bytes::from_static(
+                b"second"
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Ping("ping".to_owned()))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Last(Bytes::from_static(b"last"))))
+        );
+
+        // regular message after fragmented one
+        let mut buf = frame(true, OpCode::Binary, b"data");
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Some(BytesMut::from(&b"data"[..]))))
+        );
+    }
+
+    #[test]
+    fn test_decode_continuation_errors() {
+        let mut buf = frame(true, OpCode::Continue, b"data");
+        match Codec::new().decode(&mut buf) {
+            Err(ProtocolError::ContinuationNotStarted) => (),
+            _ => panic!(),
+        }
+
+        let mut codec = Codec::new();
+        let mut buf = frame(false, OpCode::Binary, b"data");
+        buf.extend_from_slice(&frame(true, OpCode::Text, b"data"));
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        match codec.decode(&mut buf) {
+            Err(ProtocolError::ContinuationStarted) => (),
+            _ => panic!(),
+        }
+
+        let mut buf = frame(false, OpCode::Ping, b"data");
+        match Codec::new().decode(&mut buf) {
+            Err(ProtocolError::ContinuationFragment(OpCode::Ping)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_encode_continuation() {
+        let mut codec = Codec::new().client_mode();
+        let mut buf = BytesMut::new();
+
+        let item = Item::Continue(Bytes::from_static(b"data"));
+        match codec.encode(Message::Continuation(item), &mut buf) {
+            Err(ProtocolError::ContinuationNotStarted) => (),
+            _ => panic!(),
+        }
+
+        let items = vec![
+            Item::FirstBinary(Bytes::from_static(b"first")),
+            Item::Continue(Bytes::from_static(b"second")),
+            Item::Last(Bytes::from_static(b"last")),
+        ];
+        for item in items {
+            codec.encode(Message::Continuation(item), &mut buf).unwrap();
+        }
+
+        let mut decoder = Codec::new();
+        let mut frames = Vec::new();
+        while let Some(frm) = decoder.decode(&mut buf).unwrap() {
+            frames.push(frm);
+        }
+        assert_eq!(
+            frames,
+            vec![
+                Frame::Continuation(Item::FirstBinary(Bytes::from_static(b"first"))),
+                Frame::Continuation(Item::Continue(Bytes::from_static(b"second"))),
+                Frame::Continuation(Item::Last(Bytes::from_static(b"last"))),
+            ]
+        );
+    }
+
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    #[test]
+    fn test_deflate() {
+        let cfg = DeflateConfig::new();
+        let mut client = Codec::new().client_mode().deflate(cfg);
+        let mut srv = Codec::new().deflate(cfg);
+
+        let text = "{\"value\": 1, \"value\": 1, \"value\": 1, \"value\": 1}";
+        let mut buf = BytesMut::new();
+        client
+            .encode(Message::Text(text.to_owned()), &mut buf)
+            .unwrap();
+        // RSV1 bit is set
+        assert_eq!(buf[0], 0x80 | 0x40 | 0x1);
+        assert!(buf.len() < text.len());
+        assert_eq!(
+            srv.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Some(BytesMut::from(text))))
+        );
+
+        // control frames are not compressed
+        client
+            .encode(Message::Ping("ping".to_owned()), &mut buf)
+            .unwrap();
+        assert_eq!(buf[0], 0x80 | 0x9);
+        assert_eq!(
+            srv.decode(&mut buf).unwrap(),
+            Some(Frame::Ping("ping".to_owned()))
+        );
+
+        // fragmented message
+        let items = vec![
+            Item::FirstText(Bytes::from_static(b"first ")),
+            Item::Last(Bytes::from_static(b"last")),
+        ];
+        for item in items {
+            srv.encode(Message::Continuation(item), &mut buf).unwrap();
+        }
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::FirstText(Bytes::from_static(
+                b"first "
+            ))))
+        );
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Last(Bytes::from_static(b"last"))))
+        );
+
+        // uncompressed message
+        let mut buf = BytesMut::new();
+        Codec::new()
+            .encode(Message::Binary(Bytes::from_static(b"data")), &mut buf)
+            .unwrap();
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Some(BytesMut::from(&b"data"[..]))))
+        );
+    }
+}
@@ -0,0 +1,637 @@
+//! `permessage-deflate` extension (RFC 7692)
+use std::fmt;
+
+use bytes::{Bytes, BytesMut};
+use http::header::{HeaderValue, SEC_WEBSOCKET_EXTENSIONS};
+
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+use flate2::{
+    Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status,
+};
+
+use super::ProtocolError;
+use crate::header::HeaderMap;
+
+const EXTENSION: &str = "permessage-deflate";
+const SERVER_NO_CONTEXT_TAKEOVER: &str = "server_no_context_takeover";
+const CLIENT_NO_CONTEXT_TAKEOVER: &str = "client_no_context_takeover";
+const SERVER_MAX_WINDOW_BITS: &str = "server_max_window_bits";
+const CLIENT_MAX_WINDOW_BITS: &str = "client_max_window_bits";
+
+/// Compressor always uses largest window
+const MAX_WINDOW_BITS: u8 = 15;
+const MIN_WINDOW_BITS: u8 = 8;
+
+/// Every sync flushed deflate block ends with these bytes
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+const DEFLATE_TRAILER: [u8; 4] = [0, 0, 0xff, 0xff];
+
+/// `permessage-deflate` extension settings
+///
+/// Same type describes local preferences, extension offer and
+/// negotiated parameters. Server uses `negotiate()` to accept client offer,
+/// client sends settings as offer and uses `accept()` to check server
+/// response. Negotiated settings are passed to `Codec::deflate()`.
+///
+/// Compression is available with `flate2-zlib` or `flate2-rust` features,
+/// otherwise extension is never negotiated.
+///
+/// ```rust
+/// use actix_http::ws::DeflateConfig;
+///
+/// let cfg = DeflateConfig::new()
+///     .server_no_context_takeover(true)
+///     .compression_level(3);
+/// assert_eq!(cfg.to_string(), "permessage-deflate; server_no_context_takeover");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<u8>,
+    client_max_window_bits: Option<u8>,
+    compression_level: u32,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        DeflateConfig {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            server_max_window_bits: None,
+            client_max_window_bits: None,
+            compression_level: 6,
+        }
+    }
+}
+
+impl DeflateConfig {
+    /// Create default settings
+    pub fn new() -> Self {
+        DeflateConfig::default()
+    }
+
+    /// Server resets compression context after each message.
+    ///
+    /// Reduces memory usage on both sides at the cost of compression ratio.
+    pub fn server_no_context_takeover(mut self, val: bool) -> Self {
+        self.server_no_context_takeover = val;
+        self
+    }
+
+    /// Client resets compression context after each message.
+    pub fn client_no_context_takeover(mut self, val: bool) -> Self {
+        self.client_no_context_takeover = val;
+        self
+    }
+
+    /// Limit LZ77 window size of server compressor, value must be
+    /// in range 8..=15.
+    ///
+    /// Used by client, server compressor always uses 15 bits window and
+    /// declines offers with smaller values.
+    pub fn server_max_window_bits(mut self, bits: u8) -> Self {
+        self.server_max_window_bits = Some(window_bits(bits));
+        self
+    }
+
+    /// Limit LZ77 window size of client compressor, value must be
+    /// in range 8..=15.
+    ///
+    /// Used by server, limit is requested only if client offer
+    /// indicates support for it.
+    pub fn client_max_window_bits(mut self, bits: u8) -> Self {
+        self.client_max_window_bits = Some(window_bits(bits));
+        self
+    }
+
+    /// Set compression level, 0-9.
+    ///
+    /// By default compression level is set to 6.
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.compression_level = std::cmp::min(level, 9);
+        self
+    }
+
+    /// Check if compression is supported by this build.
+    pub fn is_supported() -> bool {
+        cfg!(any(feature = "flate2-zlib", feature = "flate2-rust"))
+    }
+
+    /// Negotiate extension parameters with client offers.
+    ///
+    /// Returns parameters of the first acceptable offer. Its string
+    /// representation should be sent in `Sec-WebSocket-Extensions`
+    /// response header.
+    pub fn negotiate(&self, headers: &HeaderMap) -> Option<DeflateConfig> {
+        if !DeflateConfig::is_supported() {
+            return None;
+        }
+
+        for hdr in headers.get_all(SEC_WEBSOCKET_EXTENSIONS) {
+            let hdr = match hdr.to_str() {
+                Ok(hdr) => hdr,
+                Err(_) => continue,
+            };
+            for (name, params) in extensions(hdr) {
+                if name.eq_ignore_ascii_case(EXTENSION) {
+                    if let Some(cfg) = self.accept_offer(&params) {
+                        return Some(cfg);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn accept_offer(&self, params: &[(&str, Option<&str>)]) -> Option<DeflateConfig> {
+        if has_duplicates(params) {
+            return None;
+        }
+
+        let mut cfg = DeflateConfig {
+            server_max_window_bits: None,
+            client_max_window_bits: None,
+            ..*self
+        };
+        for &(name, value) in params {
+            match (name, value) {
+                (SERVER_NO_CONTEXT_TAKEOVER, None) => {
+                    cfg.server_no_context_takeover = true
+                }
+                (CLIENT_NO_CONTEXT_TAKEOVER, None) => {
+                    cfg.client_no_context_takeover = true
+                }
+                (SERVER_MAX_WINDOW_BITS, Some(value)) => {
+                    // compressor window size can not be changed
+                    if parse_window_bits(value)? != MAX_WINDOW_BITS {
+                        return None;
+                    }
+                    cfg.server_max_window_bits = Some(MAX_WINDOW_BITS);
+                }
+                (CLIENT_MAX_WINDOW_BITS, value) => {
+                    let bits = match value {
+                        Some(value) => parse_window_bits(value)?,
+                        None => MAX_WINDOW_BITS,
+                    };
+                    if let Some(limit) = self.client_max_window_bits {
+                        cfg.client_max_window_bits = Some(std::cmp::min(limit, bits));
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(cfg)
+    }
+
+    /// Check server response to extension offer.
+    ///
+    /// Returns `Ok(None)` if server did not accept extension and error
+    /// if response is not valid for this offer.
+    pub fn accept(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<Option<DeflateConfig>, ProtocolError> {
+        let mut result = None;
+
+        for hdr in headers.get_all(SEC_WEBSOCKET_EXTENSIONS) {
+            let hdr = hdr.to_str().map_err(|_| ProtocolError::Compression)?;
+            for (name, params) in extensions(hdr) {
+                // only one extension could be accepted
+                if !name.eq_ignore_ascii_case(EXTENSION) || result.is_some() {
+                    return Err(ProtocolError::Compression);
+                }
+                result = Some(
+                    self.accept_response(&params)
+                        .ok_or(ProtocolError::Compression)?,
+                );
+            }
+        }
+        Ok(result)
+    }
+
+    fn accept_response(&self, params: &[(&str, Option<&str>)]) -> Option<DeflateConfig> {
+        if has_duplicates(params) || !DeflateConfig::is_supported() {
+            return None;
+        }
+
+        let mut cfg = DeflateConfig {
+            server_no_context_takeover: false,
+            server_max_window_bits: None,
+            client_max_window_bits: None,
+            ..*self
+        };
+        for &(name, value) in params {
+            match (name, value) {
+                (SERVER_NO_CONTEXT_TAKEOVER, None) => {
+                    cfg.server_no_context_takeover = true
+                }
+                (CLIENT_NO_CONTEXT_TAKEOVER, None) => {
+                    cfg.client_no_context_takeover = true
+                }
+                (SERVER_MAX_WINDOW_BITS, Some(value)) => {
+                    let bits = parse_window_bits(value)?;
+                    if bits > self.server_max_window_bits.unwrap_or(MAX_WINDOW_BITS) {
+                        return None;
+                    }
+                    cfg.server_max_window_bits = Some(bits);
+                }
+                // client never offers client_max_window_bits, compressor
+                // window size can not be changed
+                _ => return None,
+            }
+        }
+        Some(cfg)
+    }
+}
+
+impl fmt::Display for DeflateConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", EXTENSION)?;
+        if self.server_no_context_takeover {
+            write!(f, "; {}", SERVER_NO_CONTEXT_TAKEOVER)?;
+        }
+        if self.client_no_context_takeover {
+            write!(f, "; {}", CLIENT_NO_CONTEXT_TAKEOVER)?;
+        }
+        if let Some(bits) = self.server_max_window_bits {
+            write!(f, "; {}={}", SERVER_MAX_WINDOW_BITS, bits)?;
+        }
+        if let Some(bits) = self.client_max_window_bits {
+            write!(f, "; {}={}", CLIENT_MAX_WINDOW_BITS, bits)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<DeflateConfig> for HeaderValue {
+    fn from(cfg: DeflateConfig) -> HeaderValue {
+        HeaderValue::from_str(&cfg.to_string()).unwrap()
+    }
+}
+
+fn window_bits(bits: u8) -> u8 {
+    assert!(
+        bits >= MIN_WINDOW_BITS && bits <= MAX_WINDOW_BITS,
+        "Window bits must be in range 8..=15"
+    );
+    bits
+}
+
+fn parse_window_bits(value: &str) -> Option<u8> {
+    let value = value.trim_matches('"');
+    // leading zeros are not allowed
+    if value.starts_with('0') {
+        return None;
+    }
+    match value.parse::<u8>() {
+        Ok(bits) if bits >= MIN_WINDOW_BITS && bits <= MAX_WINDOW_BITS => Some(bits),
+        _ => None,
+    }
+}
+
+fn has_duplicates(params: &[(&str, Option<&str>)]) -> bool {
+    params
+        .iter()
+        .enumerate()
+        .any(|(idx, p)| params[..idx].iter().any(|p2| p2.0 == p.0))
+}
+
+/// Parse `Sec-WebSocket-Extensions` header value
+fn extensions(hdr: &str) -> Vec<(&str, Vec<(&str, Option<&str>)>)> {
+    hdr.split(',')
+        .filter_map(|ext| {
+            let mut parts = ext.split(';').map(|s| s.trim());
+            let name = parts.next().filter(|name| !name.is_empty())?;
+            let params = parts
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    if let Some(pos) = p.find('=') {
+                        (p[..pos].trim(), Some(p[pos + 1..].trim()))
+                    } else {
+                        (p, None)
+                    }
+                })
+                .collect();
+            Some((name, params))
+        })
+        .collect()
+}
+
+/// Compression context of negotiated extension
+#[derive(Debug)]
+pub(crate) struct DeflateContext {
+    config: DeflateConfig,
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    compress: Compress,
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    decompress: Decompress,
+}
+
+impl Clone for DeflateContext {
+    /// Clone settings, compression state is not cloned
+    fn clone(&self) -> Self {
+        DeflateContext::new(self.config)
+    }
+}
+
+#[cfg(not(any(feature = "flate2-zlib", feature = "flate2-rust")))]
+impl DeflateContext {
+    pub(crate) fn new(config: DeflateConfig) -> Self {
+        DeflateContext { config }
+    }
+
+    pub(crate) fn compress(
+        &mut self,
+        _: &[u8],
+        _: bool,
+        _: bool,
+    ) -> Result<Bytes, ProtocolError> {
+        Err(ProtocolError::Compression)
+    }
+
+    pub(crate) fn decompress(
+        &mut self,
+        _: &[u8],
+        _: bool,
+        _: bool,
+        _: usize,
+    ) -> Result<BytesMut, ProtocolError> {
+        Err(ProtocolError::Compression)
+    }
+}
+
+#[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+impl DeflateContext {
+    pub(crate) fn new(config: DeflateConfig) -> Self {
+        DeflateContext {
+            config,
+            compress: Compress::new(Compression::new(config.compression_level), false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    /// Compress message fragment, `fin` indicates last fragment of the message.
+    pub(crate) fn compress(
+        &mut self,
+        data: &[u8],
+        fin: bool,
+        server: bool,
+    ) -> Result<Bytes, ProtocolError> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let start = self.compress.total_in();
+
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            if out.len() == out.capacity() {
+                out.reserve(std::cmp::max(data.len() - consumed, 64));
+            }
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|_| ProtocolError::Compression)?;
+
+            // all input is consumed and flush is completed
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+
+        if fin {
+            if out.ends_with(&DEFLATE_TRAILER) {
+                out.truncate(out.len() - DEFLATE_TRAILER.len());
+            }
+            let reset = if server {
+                self.config.server_no_context_takeover
+            } else {
+                self.config.client_no_context_takeover
+            };
+            if reset {
+                self.compress.reset();
+            }
+        }
+        Ok(Bytes::from(out))
+    }
+
+    /// Decompress message fragment, `fin` indicates last fragment of the message.
+    pub(crate) fn decompress(
+        &mut self,
+        data: &[u8],
+        fin: bool,
+        server: bool,
+        max_size: usize,
+    ) -> Result<BytesMut, ProtocolError> {
+        let mut out = Vec::with_capacity(std::cmp::min(data.len() * 2 + 64, max_size));
+
+        let stream_end = self.decompress_chunk(data, &mut out, max_size)?;
+        if fin {
+            if !stream_end {
+                self.decompress_chunk(&DEFLATE_TRAILER, &mut out, max_size)?;
+            }
+
+            let reset = if server {
+                self.config.client_no_context_takeover
+            } else {
+                self.config.server_no_context_takeover
+            };
+            if reset {
+                self.decompress.reset(false);
+            }
+        }
+        Ok(BytesMut::from(out))
+    }
+
+    /// Returns `true` if final deflate block is decompressed
+    fn decompress_chunk(
+        &mut self,
+        data: &[u8],
+        out: &mut Vec<u8>,
+        max_size: usize,
+    ) -> Result<bool, ProtocolError> {
+        let start = self.decompress.total_in();
+
+        let stream_end = loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if out.len() == out.capacity() {
+                if out.len() >= max_size {
+                    return Err(ProtocolError::Overflow);
+                }
+                out.reserve(std::cmp::max(data.len() - consumed, 1024));
+            }
+            let status = self
+                .decompress
+                .decompress_vec(&data[consumed..], out, FlushDecompress::Sync)
+                .map_err(|_| ProtocolError::Compression)?;
+
+            // peer finished deflate stream, next message starts new one
+            if status == Status::StreamEnd {
+                self.decompress.reset(false);
+                break true;
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break false;
+            }
+        };
+
+        if out.len() > max_size {
+            Err(ProtocolError::Overflow)
+        } else {
+            Ok(stream_end)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(val: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from_static(val));
+        headers
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(DeflateConfig::new().to_string(), "permessage-deflate");
+        let cfg = DeflateConfig::new()
+            .server_no_context_takeover(true)
+            .client_no_context_takeover(true)
+            .server_max_window_bits(10)
+            .client_max_window_bits(12);
+        assert_eq!(
+            cfg.to_string(),
+            "permessage-deflate; server_no_context_takeover; \
+             client_no_context_takeover; server_max_window_bits=10; \
+             client_max_window_bits=12"
+        );
+    }
+
+    #[test]
+    fn test_extensions() {
+        let ext = extensions("foo, permessage-deflate; client_max_window_bits=\"10\";x");
+        assert_eq!(ext.len(), 2);
+        assert_eq!(ext[0], ("foo", vec![]));
+        assert_eq!(
+            ext[1],
+            (
+                "permessage-deflate",
+                vec![("client_max_window_bits", Some("\"10\"")), ("x", None)]
+            )
+        );
+    }
+
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    #[test]
+    fn test_negotiate() {
+        let srv = DeflateConfig::new();
+        assert_eq!(srv.negotiate(&HeaderMap::new()), None);
+        assert_eq!(srv.negotiate(&headers("x-webkit-deflate-frame")), None);
+
+        let cfg = srv
+            .negotiate(&headers("permessage-deflate; client_max_window_bits"))
+            .unwrap();
+        assert_eq!(cfg.to_string(), "permessage-deflate");
+
+        // first offer can not be accepted
+        let cfg = srv
+            .negotiate(&headers(
+                "permessage-deflate; server_max_window_bits=10, \
+                 permessage-deflate; client_no_context_takeover",
+            ))
+            .unwrap();
+        assert_eq!(
+            cfg.to_string(),
+            "permessage-deflate; client_no_context_takeover"
+        );
+
+        // unknown or duplicated params
+        assert_eq!(srv.negotiate(&headers("permessage-deflate; foo")), None);
+        assert_eq!(
+            srv.negotiate(&headers(
+                "permessage-deflate; server_no_context_takeover; \
+                 server_no_context_takeover"
+            )),
+            None
+        );
+
+        // server limits client window
+        let srv = DeflateConfig::new()
+            .client_max_window_bits(10)
+            .server_no_context_takeover(true);
+        let cfg = srv
+            .negotiate(&headers("permessage-deflate; client_max_window_bits"))
+            .unwrap();
+        assert_eq!(
+            cfg.to_string(),
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits=10"
+        );
+        let cfg = srv.negotiate(&headers("permessage-deflate")).unwrap();
+        assert_eq!(
+            cfg.to_string(),
+            "permessage-deflate; server_no_context_takeover"
+        );
+    }
+
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    #[test]
+    fn test_accept() {
+        let client = DeflateConfig::new().server_max_window_bits(12);
+        assert_eq!(client.accept(&HeaderMap::new()).unwrap(), None);
+
+        let cfg = client
+            .accept(&headers(
+                "permessage-deflate; server_no_context_takeover; server_max_window_bits=10",
+            ))
+            .unwrap()
+            .unwrap();
+        assert!(cfg.server_no_context_takeover);
+        assert_eq!(cfg.server_max_window_bits, Some(10));
+
+        assert!(client
+            .accept(&headers("permessage-deflate; server_max_window_bits=15"))
+            .is_err());
+        assert!(client
+            .accept(&headers("permessage-deflate; client_max_window_bits=10"))
+            .is_err());
+        assert!(client.accept(&headers("foo")).is_err());
+        assert!(client
+            .accept(&headers("permessage-deflate, permessage-deflate"))
+            .is_err());
+    }
+
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    #[test]
+    fn test_compress() {
+        let cfg = DeflateConfig::new().server_no_context_takeover(true);
+        let mut srv = DeflateContext::new(cfg);
+        let mut client = DeflateContext::new(cfg);
+
+        let data = b"{\"value\": 1, \"value\": 1, \"value\": 1, \"value\": 1}";
+        for _ in 0..3 {
+            let compressed = srv.compress(data, true, true).unwrap();
+            assert!(compressed.len() < data.len());
+            assert!(!compressed.ends_with(&DEFLATE_TRAILER));
+            let decompressed =
+                client.decompress(&compressed, true, false, 65_536).unwrap();
+            assert_eq!(&decompressed[..], &data[..]);
+        }
+
+        // fragmented message
+        let first = client.compress(b"Hello, ", false, false).unwrap();
+        let last = client.compress(b"World!", true, false).unwrap();
+        let mut msg = srv.decompress(&first, false, true, 65_536).unwrap();
+        msg.extend_from_slice(&srv.decompress(&last, true, true, 65_536).unwrap());
+        assert_eq!(&msg[..], b"Hello, World!");
+
+        // decompressed size limit
+        let data = vec![b'x'; 10_000];
+        let compressed = srv.compress(&data, true, true).unwrap();
+        match client.decompress(&compressed, true, false, 1024) {
+            Err(ProtocolError::Overflow) => (),
+            _ => panic!(),
+        }
+    }
+}
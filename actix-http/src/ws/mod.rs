@@ -3,6 +3,11 @@
 //! To setup a `WebSocket`, first do web socket handshake then on success
 //! convert `Payload` into a `WsStream` stream and then use `WsWriter` to
 //! communicate with the peer.
+//!
+//! Websockets are supported only for http/1.1 connections. Bootstrapping
+//! websockets over http/2 with extended CONNECT method (RFC 8441) requires
+//! `SETTINGS_ENABLE_CONNECT_PROTOCOL` setting and `:protocol` pseudo-header,
+//! both are not supported by `h2` crate.
 use std::io;
 
 use derive_more::{Display, From};
@@ -13,12 +18,14 @@ use crate::message::RequestHead;
 use crate::response::{Response, ResponseBuilder};
 
 mod codec;
+mod deflate;
 mod frame;
 mod mask;
 mod proto;
 mod transport;
 
-pub use self::codec::{Codec, Frame, Message};
+pub use self::codec::{Codec, Frame, Item, Message};
+pub use self::deflate::DeflateConfig;
 pub use self::frame::Parser;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
 pub use self::transport::Transport;
@@ -47,6 +54,18 @@ pub enum ProtocolError {
     /// Continuation is not supported
     #[display(fmt = "Continuation is not supported.")]
     NoContinuation,
+    /// Continuation has not started
+    #[display(fmt = "Continuation has not started.")]
+    ContinuationNotStarted,
+    /// Received new data frame before continuation is finished
+    #[display(fmt = "Received new continuation but it is already started.")]
+    ContinuationStarted,
+    /// Control frame or unknown frame is fragmented
+    #[display(fmt = "Unknown continuation fragment: {}", _0)]
+    ContinuationFragment(OpCode),
+    /// `permessage-deflate` extension error
+    #[display(fmt = "Compression error.")]
+    Compression,
     /// Bad utf-8 encoding
     #[display(fmt = "Bad utf-8 encoding.")]
     BadEncoding,
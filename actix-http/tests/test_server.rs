@@ -2,12 +2,13 @@ use std::io::{Read, Write};
 use std::time::Duration;
 use std::{net, thread};
 
-use actix_http_test::TestServer;
+use actix_http_test::{TestServer, TestServerRuntime};
 use actix_server_config::ServerConfig;
 use actix_service::{new_service_cfg, service_fn, NewService};
 use bytes::Bytes;
 use futures::future::{self, ok, Future};
 use futures::stream::{once, Stream};
+use futures::{try_ready, Async};
 use regex::Regex;
 use tokio_timer::sleep;
 
@@ -107,6 +108,273 @@ fn test_expect_continue_h1() {
     assert!(data.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n"));
 }
 
+#[test]
+#[should_panic(expected = "http/2 max frame size must be between")]
+fn test_h2_max_frame_size_range() {
+    let _ = HttpService::<tokio_tcp::TcpStream, (), _, body::Body>::build()
+        .h2_max_frame_size(16_777_216)
+        .h2::<_, (), body::Body>(|_| future::ok::<_, ()>(Response::Ok().finish()));
+}
+
+#[test]
+fn test_h2c_upgrade() {
+    let srv = TestServer::new(|| {
+        HttpService::build().finish(|req: Request| {
+            assert_eq!(req.version(), http::Version::HTTP_2);
+            assert_eq!(req.path(), "/test");
+            future::ok::<_, ()>(Response::Ok().body("h2c"))
+        })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let _ = stream.write_all(
+        b"GET /test HTTP/1.1\r\nhost: localhost\r\n\
+          connection: Upgrade, HTTP2-Settings\r\nupgrade: h2c\r\n\
+          http2-settings: AAMAAABkAAQAoAAA\r\n\r\n",
+    );
+    // client preface and empty settings frame
+    let _ = stream.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0");
+
+    let mut data = Vec::new();
+    let mut buf = [0; 1024];
+    let head = b"HTTP/1.1 101 Switching Protocols\r\n";
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        assert!(n > 0);
+        data.extend_from_slice(&buf[..n]);
+
+        if data.len() < head.len() {
+            continue;
+        }
+        assert_eq!(&data[..head.len()], &head[..]);
+        let pos = data.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+
+        // read frames until response on stream 1 is complete
+        let mut frames = &data[pos..];
+        let mut acks = 0;
+        let mut status = None;
+        let mut body = Vec::new();
+        let mut done = false;
+        while frames.len() >= 9 {
+            let len = ((frames[0] as usize) << 16)
+                | ((frames[1] as usize) << 8)
+                | frames[2] as usize;
+            if frames.len() < 9 + len {
+                break;
+            }
+            let (kind, flags, stream_id) = (frames[3], frames[4], &frames[5..9]);
+            let payload = &frames[9..9 + len];
+            match kind {
+                // settings ack
+                0x4 if flags & 0x1 != 0 => acks += 1,
+                // headers, `:status 200` is indexed field 8
+                0x1 if stream_id == [0, 0, 0, 1] => status = Some(payload[0]),
+                // data
+                0x0 if stream_id == [0, 0, 0, 1] => {
+                    body.extend_from_slice(payload);
+                    done = flags & 0x1 != 0;
+                }
+                _ => (),
+            }
+            frames = &frames[9 + len..];
+        }
+        if done {
+            // client settings are acknowledged once, upgrade settings
+            // are acknowledged implicitly
+            assert_eq!(acks, 1);
+            assert_eq!(status, Some(0x88));
+            assert_eq!(body, b"h2c");
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_h2c_upgrade_slow_preface() {
+    let srv = TestServer::new(|| {
+        HttpService::build()
+            .client_timeout(100)
+            .finish(|_| future::ok::<_, ()>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let _ = stream.write_all(
+        b"GET /test HTTP/1.1\r\nhost: localhost\r\n\
+          connection: Upgrade, HTTP2-Settings\r\nupgrade: h2c\r\n\
+          http2-settings: AAMAAABkAAQAoAAA\r\n\r\n",
+    );
+
+    // connection is closed if client does not send preface
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    assert!(data.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+}
+
+/// Open prior knowledge h2 connection to test server
+fn h2_client(
+    addr: net::SocketAddr,
+) -> impl Future<Item = h2::client::SendRequest<Bytes>, Error = h2::Error> {
+    tokio_tcp::TcpStream::connect(&addr)
+        .map_err(h2::Error::from)
+        .and_then(|io| h2::client::handshake(io))
+        .map(|(client, conn)| {
+            tokio_current_thread::spawn(conn.map_err(|_| ()));
+            client
+        })
+}
+
+#[test]
+fn test_h2_slow_request_body() {
+    let mut srv = TestServer::new(|| {
+        HttpService::build().min_request_data_rate(1_000_000, 0).h2(
+            |mut req: Request| {
+                req.take_payload()
+                    .concat2()
+                    .then(|_| future::ok::<_, ()>(Response::Ok().finish()))
+            },
+        )
+    });
+
+    let err = srv
+        .block_on(h2_client(srv.addr()).and_then(|mut client| {
+            let req = ::http::Request::post("/").body(()).unwrap();
+            let (res, mut body) = client.send_request(req, false).unwrap();
+            body.send_data(Bytes::from_static(b"ab"), false).unwrap();
+            // keep request stream open
+            res.then(move |res| {
+                drop(body);
+                res
+            })
+        }))
+        .unwrap_err();
+    assert_eq!(err.reason(), Some(h2::Reason::CANCEL));
+}
+
+#[test]
+fn test_h2_request_trailers() {
+    let mut srv = TestServer::new(|| {
+        HttpService::build().h2(|mut req: Request| {
+            let mut pl = req.take_payload();
+            future::poll_fn(move || {
+                while let Some(_) = try_ready!(pl.poll()) {}
+                // trailers are kept if stream is polled after end of stream
+                assert_eq!(pl.poll()?, Async::Ready(None));
+                Ok(Async::Ready(pl.trailers()))
+            })
+            .then(|res: Result<_, error::PayloadError>| {
+                let trailers = res.unwrap().unwrap();
+                let value = trailers.get("x-checksum").unwrap().as_bytes().to_vec();
+                future::ok::<_, ()>(Response::Ok().body(value))
+            })
+        })
+    });
+
+    let body = srv
+        .block_on(h2_client(srv.addr()).and_then(|mut client| {
+            let req = ::http::Request::post("/").body(()).unwrap();
+            let (res, mut body) = client.send_request(req, false).unwrap();
+            body.send_data(Bytes::from_static(b"ab"), false).unwrap();
+            let mut trailers = ::http::HeaderMap::new();
+            trailers.insert("x-checksum", ::http::HeaderValue::from_static("123"));
+            body.send_trailers(trailers).unwrap();
+            res.and_then(|res| res.into_body().concat2())
+        }))
+        .unwrap();
+    assert_eq!(&body[..], b"123");
+}
+
+#[test]
+fn test_h2_slow_response() {
+    let mut srv = TestServer::new(|| {
+        HttpService::build()
+            .min_response_data_rate(1_000_000, 0)
+            .h2(|_| future::ok::<_, ()>(Response::Ok().body(vec![b'x'; 1_000_000])))
+    });
+
+    let err = srv
+        .block_on(h2_client(srv.addr()).and_then(|mut client| {
+            let req = ::http::Request::get("/").body(()).unwrap();
+            let (res, _) = client.send_request(req, true).unwrap();
+            // flow control window is never released
+            res.and_then(|res| {
+                res.into_body()
+                    .fold(0, |n, chunk| Ok::<_, h2::Error>(n + chunk.len()))
+            })
+        }))
+        .unwrap_err();
+    assert_eq!(err.reason(), Some(h2::Reason::CANCEL));
+}
+
+#[test]
+fn test_h1_max_connection_age() {
+    let srv = TestServer::new(|| {
+        HttpService::build()
+            .keep_alive(30)
+            .max_connection_age(100)
+            .h1(|_| future::ok::<_, ()>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\n\r\n");
+
+    // idle keep-alive connection is closed once it reaches max age
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!data.contains("connection: close"));
+}
+
+/// Send request over prior knowledge h2 connection and wait until server
+/// closes connection gracefully
+fn h2_goaway(srv: &mut TestServerRuntime) -> Result<http::StatusCode, h2::Error> {
+    let fut = tokio_tcp::TcpStream::connect(&srv.addr())
+        .map_err(h2::Error::from)
+        .and_then(|io| h2::client::handshake(io))
+        .and_then(|(mut client, conn)| {
+            let req = ::http::Request::get("/").body(()).unwrap();
+            let (res, _) = client.send_request(req, true).unwrap();
+            // connection completes after GOAWAY frame from server
+            res.map(|res| res.status())
+                .join(conn)
+                .map(|(status, _)| status)
+        });
+    srv.block_on(
+        tokio_timer::Timeout::new(fut, Duration::from_secs(5))
+            .map_err(|e| e.into_inner().expect("GOAWAY frame is not received")),
+    )
+}
+
+#[test]
+fn test_h2_max_connection_age() {
+    let mut srv = TestServer::new(|| {
+        HttpService::build()
+            .keep_alive(30)
+            .max_connection_age(100)
+            .h2(|_| future::ok::<_, ()>(Response::Ok().finish()))
+    });
+    assert_eq!(h2_goaway(&mut srv).unwrap(), http::StatusCode::OK);
+}
+
+#[test]
+fn test_h2_max_requests_per_connection() {
+    let mut srv = TestServer::new(|| {
+        HttpService::build()
+            .keep_alive(30)
+            .max_requests_per_connection(1)
+            .h2(|_| future::ok::<_, ()>(Response::Ok().finish()))
+    });
+    assert_eq!(h2_goaway(&mut srv).unwrap(), http::StatusCode::OK);
+}
+
 #[test]
 fn test_chunked_payload() {
     let chunk_sizes = vec![32768, 32, 32768];
@@ -0,0 +1,571 @@
+//! Broadcast hub for websocket actors
+//!
+//! `Hub` is an actor that keeps track of connected clients and named rooms.
+//! Messages could be broadcast to all members of a room or to all connected
+//! clients.
+//!
+//! Every client gets messages through its own mailbox. Mailbox capacity
+//! limits number of undelivered messages, it could be configured with
+//! `WebsocketContext::set_mailbox_capacity()`. If client's mailbox is full,
+//! hub either drops message for this client or disconnects it, see
+//! `Overflow`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix::prelude::*;
+//! use actix_web::{web, App, Error, HttpRequest, HttpResponse};
+//! use actix_web_actors::{hub, ws};
+//!
+//! struct Chat {
+//!     id: Option<hub::ClientId>,
+//!     hub: Addr<hub::Hub>,
+//! }
+//!
+//! impl Actor for Chat {
+//!     type Context = ws::WebsocketContext<Self>;
+//!
+//!     fn started(&mut self, ctx: &mut Self::Context) {
+//!         ctx.set_mailbox_capacity(64);
+//!         self.hub
+//!             .send(hub::Connect::new(ctx.address().recipient()))
+//!             .into_actor(self)
+//!             .then(|res, act, ctx| {
+//!                 match res {
+//!                     Ok(id) => {
+//!                         act.hub.do_send(hub::Join::new(id, "main"));
+//!                         act.id = Some(id);
+//!                     }
+//!                     Err(_) => ctx.stop(),
+//!                 }
+//!                 fut::ok(())
+//!             })
+//!             .wait(ctx);
+//!     }
+//!
+//!     fn stopped(&mut self, _: &mut Self::Context) {
+//!         if let Some(id) = self.id {
+//!             self.hub.do_send(hub::Disconnect(id));
+//!         }
+//!     }
+//! }
+//!
+//! impl Handler<hub::Event> for Chat {
+//!     type Result = ();
+//!
+//!     fn handle(&mut self, ev: hub::Event, ctx: &mut Self::Context) {
+//!         ev.deliver(ctx)
+//!     }
+//! }
+//!
+//! impl StreamHandler<ws::Message, ws::ProtocolError> for Chat {
+//!     fn handle(&mut self, msg: ws::Message, ctx: &mut Self::Context) {
+//!         match msg {
+//!             ws::Message::Text(text) => {
+//!                 let ev = hub::Event::Text(text);
+//!                 let mut msg = hub::Broadcast::room("main", ev);
+//!                 if let Some(id) = self.id {
+//!                     msg = msg.skip(id);
+//!                 }
+//!                 self.hub.do_send(msg);
+//!             }
+//!             ws::Message::Ping(msg) => ctx.pong(&msg),
+//!             ws::Message::Close(_) => ctx.stop(),
+//!             _ => (),
+//!         }
+//!     }
+//! }
+//!
+//! fn index(
+//!     req: HttpRequest,
+//!     stream: web::Payload,
+//!     hub: web::Data<Addr<hub::Hub>>,
+//! ) -> Result<HttpResponse, Error> {
+//!     let chat = Chat {
+//!         id: None,
+//!         hub: hub.get_ref().clone(),
+//!     };
+//!     ws::start(chat, &req, stream)
+//! }
+//! ```
+use std::collections::{BTreeSet, HashMap};
+
+use actix::prelude::SendError;
+use actix::{Actor, ActorContext, Context, Handler, Message, MessageResult, Recipient};
+use bytes::Bytes;
+
+use crate::ws::{CloseCode, WebsocketContext};
+
+/// Client identifier, assigned by `Hub` on connect
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClientId(usize);
+
+/// Message delivered by hub to connected clients
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Text message
+    Text(String),
+    /// Binary message
+    Binary(Bytes),
+    /// Client is disconnected from hub because its mailbox is full
+    Disconnected,
+}
+
+impl Message for Event {
+    type Result = ();
+}
+
+impl Event {
+    /// Write event to websocket context.
+    ///
+    /// Text and binary events are sent to the peer. `Event::Disconnected`
+    /// closes connection with `CloseCode::Again` and stops the actor.
+    pub fn deliver<A>(self, ctx: &mut WebsocketContext<A>)
+    where
+        A: Actor<Context = WebsocketContext<A>>,
+    {
+        match self {
+            Event::Text(text) => ctx.text(text),
+            Event::Binary(bin) => ctx.binary(bin),
+            Event::Disconnected => {
+                ctx.close(Some(CloseCode::Again.into()));
+                ctx.stop();
+            }
+        }
+    }
+}
+
+/// What to do with a client that can not keep up with messages
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Overflow {
+    /// Drop messages that do not fit to client's mailbox
+    Drop,
+    /// Send `Event::Disconnected` to the client and remove it from the hub
+    Disconnect,
+}
+
+/// Connect new client to the hub, returns client id
+pub struct Connect {
+    client: Recipient<Event>,
+}
+
+impl Connect {
+    /// Create connect message for client's recipient
+    pub fn new(client: Recipient<Event>) -> Self {
+        Connect { client }
+    }
+}
+
+impl Message for Connect {
+    type Result = ClientId;
+}
+
+/// Remove client from the hub and from all its rooms
+pub struct Disconnect(pub ClientId);
+
+impl Message for Disconnect {
+    type Result = ();
+}
+
+/// Add client to a room, room is created if it does not exist
+pub struct Join {
+    id: ClientId,
+    room: String,
+}
+
+impl Join {
+    /// Create join message
+    pub fn new<T: Into<String>>(id: ClientId, room: T) -> Self {
+        Join {
+            id,
+            room: room.into(),
+        }
+    }
+}
+
+impl Message for Join {
+    type Result = ();
+}
+
+/// Remove client from a room, empty room is removed
+pub struct Leave {
+    id: ClientId,
+    room: String,
+}
+
+impl Leave {
+    /// Create leave message
+    pub fn new<T: Into<String>>(id: ClientId, room: T) -> Self {
+        Leave {
+            id,
+            room: room.into(),
+        }
+    }
+}
+
+impl Message for Leave {
+    type Result = ();
+}
+
+/// Send event to all members of a room or to all connected clients
+pub struct Broadcast {
+    room: Option<String>,
+    event: Event,
+    skip: Option<ClientId>,
+}
+
+impl Broadcast {
+    /// Send event to all members of the room
+    pub fn room<T: Into<String>>(room: T, event: Event) -> Self {
+        Broadcast {
+            room: Some(room.into()),
+            event,
+            skip: None,
+        }
+    }
+
+    /// Send event to all connected clients
+    pub fn all(event: Event) -> Self {
+        Broadcast {
+            room: None,
+            event,
+            skip: None,
+        }
+    }
+
+    /// Do not send event to the client, usually to the sender of the message
+    pub fn skip(mut self, id: ClientId) -> Self {
+        self.skip = Some(id);
+        self
+    }
+}
+
+impl Message for Broadcast {
+    type Result = ();
+}
+
+/// List members of a room
+pub struct Members(pub String);
+
+impl Message for Members {
+    type Result = Vec<ClientId>;
+}
+
+/// List existing rooms
+pub struct Rooms;
+
+impl Message for Rooms {
+    type Result = Vec<String>;
+}
+
+/// Broadcast hub actor
+pub struct Hub {
+    next_id: usize,
+    overflow: Overflow,
+    clients: HashMap<ClientId, Recipient<Event>>,
+    rooms: HashMap<String, BTreeSet<ClientId>>,
+}
+
+impl Default for Hub {
+    fn default() -> Self {
+        Hub::new()
+    }
+}
+
+impl Hub {
+    /// Create new hub, by default messages that do not fit to client's
+    /// mailbox are dropped.
+    pub fn new() -> Self {
+        Hub {
+            next_id: 0,
+            overflow: Overflow::Drop,
+            clients: HashMap::new(),
+            rooms: HashMap::new(),
+        }
+    }
+
+    /// Set slow client policy
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    fn remove(&mut self, id: ClientId) {
+        self.clients.remove(&id);
+        self.rooms.retain(|_, members| {
+            members.remove(&id);
+            !members.is_empty()
+        });
+    }
+
+    /// Deliver event to the client, returns false if client got removed
+    fn deliver(&self, id: ClientId, event: Event) -> bool {
+        let client = match self.clients.get(&id) {
+            Some(client) => client,
+            None => return true,
+        };
+        match client.try_send(event) {
+            Ok(()) => true,
+            Err(SendError::Full(_)) => match self.overflow {
+                Overflow::Drop => true,
+                Overflow::Disconnect => {
+                    // mailbox capacity is ignored by do_send
+                    let _ = client.do_send(Event::Disconnected);
+                    false
+                }
+            },
+            Err(SendError::Closed(_)) => false,
+        }
+    }
+}
+
+impl Actor for Hub {
+    type Context = Context<Self>;
+}
+
+impl Handler<Connect> for Hub {
+    type Result = MessageResult<Connect>;
+
+    fn handle(&mut self, msg: Connect, _: &mut Context<Self>) -> Self::Result {
+        let id = ClientId(self.next_id);
+        self.next_id += 1;
+        self.clients.insert(id, msg.client);
+        MessageResult(id)
+    }
+}
+
+impl Handler<Disconnect> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
+        self.remove(msg.0);
+    }
+}
+
+impl Handler<Join> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, msg: Join, _: &mut Context<Self>) {
+        if self.clients.contains_key(&msg.id) {
+            self.rooms
+                .entry(msg.room)
+                .or_insert_with(BTreeSet::new)
+                .insert(msg.id);
+        }
+    }
+}
+
+impl Handler<Leave> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, msg: Leave, _: &mut Context<Self>) {
+        let empty = if let Some(members) = self.rooms.get_mut(&msg.room) {
+            members.remove(&msg.id);
+            members.is_empty()
+        } else {
+            false
+        };
+        if empty {
+            self.rooms.remove(&msg.room);
+        }
+    }
+}
+
+impl Handler<Broadcast> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, msg: Broadcast, _: &mut Context<Self>) {
+        let ids: Vec<_> = if let Some(ref room) = msg.room {
+            match self.rooms.get(room) {
+                Some(members) => members.iter().cloned().collect(),
+                None => return,
+            }
+        } else {
+            self.clients.keys().cloned().collect()
+        };
+
+        let removed: Vec<_> = ids
+            .into_iter()
+            .filter(|id| Some(*id) != msg.skip)
+            .filter(|id| !self.deliver(*id, msg.event.clone()))
+            .collect();
+        for id in removed {
+            self.remove(id);
+        }
+    }
+}
+
+impl Handler<Members> for Hub {
+    type Result = MessageResult<Members>;
+
+    fn handle(&mut self, msg: Members, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            self.rooms
+                .get(&msg.0)
+                .map(|members| members.iter().cloned().collect())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl Handler<Rooms> for Hub {
+    type Result = MessageResult<Rooms>;
+
+    fn handle(&mut self, _: Rooms, _: &mut Context<Self>) -> Self::Result {
+        let mut rooms: Vec<_> = self.rooms.keys().cloned().collect();
+        rooms.sort();
+        MessageResult(rooms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use actix::{fut, AsyncContext, System};
+    use futures::sync::oneshot;
+    use futures::{future, Future};
+
+    use super::*;
+
+    struct Client {
+        events: Arc<Mutex<Vec<Event>>>,
+        /// Slow client, mailbox is not processed until gate is opened
+        gate: Option<oneshot::Receiver<()>>,
+    }
+
+    impl Client {
+        fn new(events: Arc<Mutex<Vec<Event>>>) -> Self {
+            Client { events, gate: None }
+        }
+    }
+
+    impl Actor for Client {
+        type Context = Context<Self>;
+
+        fn started(&mut self, ctx: &mut Context<Self>) {
+            if let Some(gate) = self.gate.take() {
+                ctx.set_mailbox_capacity(1);
+                ctx.wait(fut::wrap_future(gate.map_err(|_| ())));
+            }
+        }
+    }
+
+    /// Response is received after all previous events are handled
+    struct Flush;
+
+    impl Message for Flush {
+        type Result = ();
+    }
+
+    impl Handler<Flush> for Client {
+        type Result = ();
+
+        fn handle(&mut self, _: Flush, _: &mut Context<Self>) {}
+    }
+
+    impl Handler<Event> for Client {
+        type Result = ();
+
+        fn handle(&mut self, ev: Event, _: &mut Context<Self>) {
+            self.events.lock().unwrap().push(ev);
+        }
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let events1 = Arc::new(Mutex::new(Vec::new()));
+        let events2 = Arc::new(Mutex::new(Vec::new()));
+        let (ev1, ev2) = (events1.clone(), events2.clone());
+
+        System::new("test")
+            .block_on(future::lazy(move || {
+                let hub = Hub::new().start();
+                let c1 = Client::new(ev1).start();
+                let c2 = Client::new(ev2).start();
+
+                hub.send(Connect::new(c1.clone().recipient()))
+                    .join(hub.send(Connect::new(c2.clone().recipient())))
+                    .and_then(move |(id1, id2)| {
+                        hub.do_send(Join::new(id1, "room"));
+                        hub.do_send(Join::new(id2, "room"));
+                        hub.do_send(Join::new(id2, "other"));
+                        hub.do_send(Broadcast::room("room", Event::Text("1".into())));
+                        hub.do_send(Broadcast::all(Event::Text("2".into())).skip(id2));
+                        hub.do_send(Broadcast::room("other", Event::Text("3".into())));
+                        hub.do_send(Leave::new(id2, "other"));
+
+                        hub.send(Members("room".to_string()))
+                            .join(hub.send(Rooms))
+                            .map(move |(members, rooms)| {
+                                assert_eq!(members, vec![id1, id2]);
+                                assert_eq!(rooms, vec!["room".to_string()]);
+                            })
+                            .and_then(move |_| c1.send(Flush).join(c2.send(Flush)))
+                    })
+            }))
+            .unwrap();
+
+        assert_eq!(
+            *events1.lock().unwrap(),
+            vec![Event::Text("1".into()), Event::Text("2".into())]
+        );
+        assert_eq!(
+            *events2.lock().unwrap(),
+            vec![Event::Text("1".into()), Event::Text("3".into())]
+        );
+    }
+
+    /// Broadcast events to a slow client, returns received events and
+    /// room members after broadcast
+    fn broadcast_slow(overflow: Overflow) -> (Vec<Event>, Vec<ClientId>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let ev = events.clone();
+
+        let members = System::new("test")
+            .block_on(future::lazy(move || {
+                let hub = Hub::new().overflow(overflow).start();
+                let (tx, gate) = oneshot::channel();
+                let client = Client {
+                    events: ev,
+                    gate: Some(gate),
+                }
+                .start();
+
+                hub.send(Connect::new(client.clone().recipient()))
+                    .and_then(move |id| {
+                        hub.do_send(Join::new(id, "room"));
+                        for i in 0..4 {
+                            let ev = Event::Text(i.to_string());
+                            hub.do_send(Broadcast::room("room", ev));
+                        }
+                        hub.send(Members("room".to_string()))
+                    })
+                    .and_then(move |members| {
+                        let _ = tx.send(());
+                        client.send(Flush).map(move |_| members)
+                    })
+            }))
+            .unwrap();
+
+        let events = events.lock().unwrap().clone();
+        (events, members)
+    }
+
+    #[test]
+    fn test_overflow_drop() {
+        // events that do not fit to the mailbox are dropped,
+        // client stays connected
+        let (events, members) = broadcast_slow(Overflow::Drop);
+        assert_eq!(events, vec![Event::Text("0".into())]);
+        assert_eq!(members, vec![ClientId(0)]);
+    }
+
+    #[test]
+    fn test_overflow_disconnect() {
+        // client is notified and removed from the hub
+        let (events, members) = broadcast_slow(Overflow::Disconnect);
+        assert_eq!(events, vec![Event::Text("0".into()), Event::Disconnected]);
+        assert!(members.is_empty());
+    }
+}
@@ -1,6 +1,11 @@
 //! Websocket integration
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use actix::clock;
 
 use actix::dev::{
     AsyncContextParts, ContextFut, ContextParts, Envelope, Mailbox, StreamHandler,
@@ -14,7 +19,8 @@ use actix::{
 use actix_codec::{Decoder, Encoder};
 use actix_http::ws::{hash_key, Codec};
 pub use actix_http::ws::{
-    CloseCode, CloseReason, Frame, HandshakeError, Message, ProtocolError,
+    CloseCode, CloseReason, DeflateConfig, Frame, HandshakeError, Item, Message,
+    ProtocolError,
 };
 
 use actix_web::dev::HttpResponseBuilder;
@@ -26,6 +32,9 @@ use futures::sync::oneshot::Sender;
 use futures::{Async, Future, Poll, Stream};
 
 /// Do websocket handshake and start ws actor.
+///
+/// Only http/1.1 upgrade handshake is supported, for http/2 requests
+/// handshake fails with `HandshakeError::NoWebsocketUpgrade` error.
 pub fn start<A, T>(actor: A, req: &HttpRequest, stream: T) -> Result<HttpResponse, Error>
 where
     A: Actor<Context = WebsocketContext<A>> + StreamHandler<Message, ProtocolError>,
@@ -77,6 +86,31 @@ where
     Ok(res.streaming(WebsocketContext::create(actor, stream)))
 }
 
+/// Do websocket handshake with `permessage-deflate` compression and start
+/// ws actor.
+///
+/// Compression is enabled if client offers the extension and one of its
+/// offers is acceptable for `cfg`, otherwise messages are not compressed.
+pub fn start_with_deflate<A, T>(
+    actor: A,
+    cfg: DeflateConfig,
+    req: &HttpRequest,
+    stream: T,
+) -> Result<HttpResponse, Error>
+where
+    A: Actor<Context = WebsocketContext<A>> + StreamHandler<Message, ProtocolError>,
+    T: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    let mut res = handshake(req)?;
+    let codec = if let Some(cfg) = cfg.negotiate(req.headers()) {
+        res.header(header::SEC_WEBSOCKET_EXTENSIONS, cfg.to_string());
+        Codec::new().deflate(cfg)
+    } else {
+        Codec::new()
+    };
+    Ok(res.streaming(WebsocketContext::with_codec(actor, stream, codec)))
+}
+
 /// Prepare `WebSocket` handshake response.
 ///
 /// This function returns handshake `HttpResponse`, ready to send to peer.
@@ -176,6 +210,50 @@ where
 {
     inner: ContextParts<A>,
     messages: VecDeque<Option<Message>>,
+    state: Rc<StreamState>,
+    hb_handle: Option<SpawnHandle>,
+}
+
+/// Reason of websocket connection shutdown
+///
+/// Available via `WebsocketContext::stop_reason()`, i.e. in
+/// `Actor::stopping()` or `Actor::stopped()` methods.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// Nothing is received from the client during heartbeat timeout
+    HeartbeatTimeout,
+    /// Client sent close frame
+    PeerClose(Option<CloseReason>),
+    /// Client sent invalid frame or payload stream failed, contains error
+    /// description
+    ProtocolError(String),
+}
+
+/// State shared between context and incoming stream
+struct StreamState {
+    hb_enabled: Cell<bool>,
+    last_seen: Cell<Instant>,
+    pongs: RefCell<VecDeque<String>>,
+    stop_reason: RefCell<Option<StopReason>>,
+}
+
+impl StreamState {
+    fn new() -> Self {
+        StreamState {
+            hb_enabled: Cell::new(false),
+            last_seen: Cell::new(clock::now()),
+            pongs: RefCell::new(VecDeque::new()),
+            stop_reason: RefCell::new(None),
+        }
+    }
+
+    /// Only the first reason is kept
+    fn stopped_with(&self, reason: StopReason) {
+        let mut stop_reason = self.stop_reason.borrow_mut();
+        if stop_reason.is_none() {
+            *stop_reason = Some(reason);
+        }
+    }
 }
 
 impl<A> ActorContext for WebsocketContext<A>
@@ -235,6 +313,15 @@ impl<A> WebsocketContext<A>
 where
     A: Actor<Context = Self>,
 {
+    fn new(mb: &Mailbox<A>) -> Self {
+        WebsocketContext {
+            inner: ContextParts::new(mb.sender_producer()),
+            messages: VecDeque::new(),
+            state: Rc::new(StreamState::new()),
+            hb_handle: None,
+        }
+    }
+
     #[inline]
     /// Create a new Websocket context from a request and an actor
     pub fn create<S>(actor: A, stream: S) -> impl Stream<Item = Bytes, Error = Error>
@@ -261,11 +348,8 @@ where
         S: Stream<Item = Bytes, Error = PayloadError> + 'static,
     {
         let mb = Mailbox::default();
-        let mut ctx = WebsocketContext {
-            inner: ContextParts::new(mb.sender_producer()),
-            messages: VecDeque::new(),
-        };
-        ctx.add_stream(WsStream::new(stream, Codec::new()));
+        let mut ctx = WebsocketContext::new(&mb);
+        ctx.add_stream(WsStream::new(stream, Codec::new(), ctx.state.clone()));
 
         let addr = ctx.address();
 
@@ -284,11 +368,8 @@ where
         S: Stream<Item = Bytes, Error = PayloadError> + 'static,
     {
         let mb = Mailbox::default();
-        let mut ctx = WebsocketContext {
-            inner: ContextParts::new(mb.sender_producer()),
-            messages: VecDeque::new(),
-        };
-        ctx.add_stream(WsStream::new(stream, codec));
+        let mut ctx = WebsocketContext::new(&mb);
+        ctx.add_stream(WsStream::new(stream, codec.clone(), ctx.state.clone()));
 
         WebsocketContextFut::new(ctx, actor, mb, codec)
     }
@@ -304,11 +385,8 @@ where
         S: Stream<Item = Bytes, Error = PayloadError> + 'static,
     {
         let mb = Mailbox::default();
-        let mut ctx = WebsocketContext {
-            inner: ContextParts::new(mb.sender_producer()),
-            messages: VecDeque::new(),
-        };
-        ctx.add_stream(WsStream::new(stream, Codec::new()));
+        let mut ctx = WebsocketContext::new(&mb);
+        ctx.add_stream(WsStream::new(stream, Codec::new(), ctx.state.clone()));
 
         let act = f(&mut ctx);
 
@@ -361,6 +439,61 @@ where
         self.write_raw(Message::Close(reason));
     }
 
+    /// Send fragment of fragmented message
+    ///
+    /// Fragmented message starts with `Item::FirstText` or `Item::FirstBinary`
+    /// and must be finished with `Item::Last` before any other text or binary
+    /// message is sent. Ping, pong and close frames could be sent in between.
+    #[inline]
+    pub fn continuation(&mut self, item: Item) {
+        self.write_raw(Message::Continuation(item));
+    }
+
+    /// Enable automatic heartbeat
+    ///
+    /// Context sends ping frame to the client every `interval` and replies
+    /// to client's ping frames, ping messages are not passed to the actor.
+    ///
+    /// If nothing is received from the client during `timeout`, connection
+    /// gets closed with `CloseCode::Away`, actor is stopped and stop reason
+    /// is set to `StopReason::HeartbeatTimeout`.
+    ///
+    /// Usually heartbeat is enabled in `Actor::started()` method.
+    pub fn heartbeat(&mut self, interval: Duration, timeout: Duration) {
+        if let Some(handle) = self.hb_handle.take() {
+            self.cancel_future(handle);
+        }
+        self.state.hb_enabled.set(true);
+        self.state.last_seen.set(clock::now());
+
+        let handle = self.run_interval(interval, move |_, ctx| {
+            if clock::now().duration_since(ctx.state.last_seen.get()) < timeout {
+                ctx.ping("");
+            } else {
+                ctx.state.stopped_with(StopReason::HeartbeatTimeout);
+                ctx.close(Some(CloseCode::Away.into()));
+                ctx.stop();
+            }
+        });
+        self.hb_handle = Some(handle);
+    }
+
+    /// Disable automatic heartbeat
+    pub fn stop_heartbeat(&mut self) {
+        if let Some(handle) = self.hb_handle.take() {
+            self.cancel_future(handle);
+        }
+        self.state.hb_enabled.set(false);
+    }
+
+    /// Reason of connection shutdown
+    ///
+    /// Returns `None` if connection is not closed by the client, by protocol
+    /// error or by heartbeat timeout, i.e. actor is stopped by itself.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.state.stop_reason.borrow().clone()
+    }
+
     /// Handle of the running future
     ///
     /// SpawnHandle is the handle returned by `AsyncContext::spawn()` method.
@@ -422,6 +555,12 @@ where
             return Err(ErrorInternalServerError("error"));
         }
 
+        // automatic pong replies
+        let state = self.fut.ctx().state.clone();
+        while let Some(msg) = state.pongs.borrow_mut().pop_front() {
+            self.encoder.encode(Message::Pong(msg), &mut self.buf)?;
+        }
+
         // encode messages
         while let Some(item) = self.fut.ctx().messages.pop_front() {
             if let Some(msg) = item {
@@ -458,18 +597,20 @@ struct WsStream<S> {
     decoder: Codec,
     buf: BytesMut,
     closed: bool,
+    state: Rc<StreamState>,
 }
 
 impl<S> WsStream<S>
 where
     S: Stream<Item = Bytes, Error = PayloadError>,
 {
-    fn new(stream: S, codec: Codec) -> Self {
+    fn new(stream: S, codec: Codec, state: Rc<StreamState>) -> Self {
         Self {
             stream,
             decoder: codec,
             buf: BytesMut::new(),
             closed: false,
+            state,
         }
     }
 }
@@ -482,6 +623,26 @@ where
     type Error = ProtocolError;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let res = self.poll_message();
+        match res {
+            Ok(Async::Ready(Some(Message::Close(ref reason)))) => {
+                self.state
+                    .stopped_with(StopReason::PeerClose(reason.clone()));
+            }
+            Err(ref e) => self
+                .state
+                .stopped_with(StopReason::ProtocolError(e.to_string())),
+            _ => (),
+        }
+        res
+    }
+}
+
+impl<S> WsStream<S>
+where
+    S: Stream<Item = Bytes, Error = PayloadError>,
+{
+    fn poll_message(&mut self) -> Poll<Option<Message>, ProtocolError> {
         if !self.closed {
             loop {
                 match self.stream.poll() {
@@ -503,45 +664,63 @@ where
             }
         }
 
-        match self.decoder.decode(&mut self.buf)? {
-            None => {
-                if self.closed {
-                    Ok(Async::Ready(None))
-                } else {
-                    Ok(Async::NotReady)
+        loop {
+            let frm = match self.decoder.decode(&mut self.buf)? {
+                None => {
+                    return if self.closed {
+                        Ok(Async::Ready(None))
+                    } else {
+                        Ok(Async::NotReady)
+                    };
                 }
-            }
-            Some(frm) => {
-                let msg = match frm {
-                    Frame::Text(data) => {
-                        if let Some(data) = data {
-                            Message::Text(
-                                std::str::from_utf8(&data)
-                                    .map_err(|_| ProtocolError::BadEncoding)?
-                                    .to_string(),
-                            )
-                        } else {
-                            Message::Text(String::new())
-                        }
+                Some(frm) => frm,
+            };
+            self.state.last_seen.set(clock::now());
+
+            let msg = match frm {
+                Frame::Text(data) => {
+                    if let Some(data) = data {
+                        Message::Text(
+                            std::str::from_utf8(&data)
+                                .map_err(|_| ProtocolError::BadEncoding)?
+                                .to_string(),
+                        )
+                    } else {
+                        Message::Text(String::new())
                     }
-                    Frame::Binary(data) => Message::Binary(
-                        data.map(|b| b.freeze()).unwrap_or_else(Bytes::new),
-                    ),
-                    Frame::Ping(s) => Message::Ping(s),
-                    Frame::Pong(s) => Message::Pong(s),
-                    Frame::Close(reason) => Message::Close(reason),
-                };
-                Ok(Async::Ready(Some(msg)))
-            }
+                }
+                Frame::Binary(data) => {
+                    Message::Binary(data.map(|b| b.freeze()).unwrap_or_else(Bytes::new))
+                }
+                Frame::Ping(s) => {
+                    if self.state.hb_enabled.get() {
+                        self.state.pongs.borrow_mut().push_back(s);
+                        continue;
+                    }
+                    Message::Ping(s)
+                }
+                Frame::Pong(s) => Message::Pong(s),
+                Frame::Close(reason) => Message::Close(reason),
+                Frame::Continuation(item) => Message::Continuation(item),
+            };
+            return Ok(Async::Ready(Some(msg)));
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::sync::{Arc, Mutex};
+
     use actix_web::http::{header, Method};
     use actix_web::test::TestRequest;
+    use futures::executor::{self, Notify, NotifyHandle};
+    use futures::future;
+    use tokio_executor::park::{Park, Unpark};
+    use tokio_timer::clock::{Clock, Now};
+    use tokio_timer::Timer;
+
+    use super::*;
 
     #[test]
     fn test_handshake() {
@@ -737,4 +916,175 @@ mod tests {
                 .get(&header::SEC_WEBSOCKET_PROTOCOL)
         );
     }
+
+    /// Clock that is advanced only by the timer
+    #[derive(Clone)]
+    struct MockNow(Arc<Mutex<Instant>>);
+
+    impl Now for MockNow {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// Instead of waiting, timer moves the clock to the next deadline
+    struct MockPark(MockNow);
+
+    struct MockUnpark;
+
+    impl Unpark for MockUnpark {
+        fn unpark(&self) {}
+    }
+
+    impl Park for MockPark {
+        type Unpark = MockUnpark;
+        type Error = ();
+
+        fn unpark(&self) -> MockUnpark {
+            MockUnpark
+        }
+
+        fn park(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn park_timeout(&mut self, duration: Duration) -> Result<(), ()> {
+            *(self.0).0.lock().unwrap() += duration;
+            Ok(())
+        }
+    }
+
+    struct NoopNotify;
+
+    impl Notify for NoopNotify {
+        fn notify(&self, _: usize) {}
+    }
+
+    struct HbActor(Rc<RefCell<Option<StopReason>>>);
+
+    impl Actor for HbActor {
+        type Context = WebsocketContext<Self>;
+
+        fn started(&mut self, ctx: &mut Self::Context) {
+            ctx.heartbeat(Duration::from_secs(1), Duration::from_secs(3));
+        }
+
+        fn stopped(&mut self, ctx: &mut Self::Context) {
+            *self.0.borrow_mut() = ctx.stop_reason();
+        }
+    }
+
+    impl StreamHandler<Message, ProtocolError> for HbActor {
+        fn handle(&mut self, msg: Message, ctx: &mut Self::Context) {
+            if let Message::Close(_) = msg {
+                ctx.stop();
+            }
+        }
+    }
+
+    /// Run actor on mock clock until connection is closed, returns sent
+    /// frames, seconds elapsed and stop reason
+    fn run(chunks: Vec<Bytes>) -> (Vec<Frame>, u64, Option<StopReason>) {
+        let now = MockNow(Arc::new(Mutex::new(Instant::now())));
+        let start = now.now();
+        let clock = Clock::new_with_now(now.clone());
+        let mut timer = Timer::new_with_now(MockPark(now.clone()), clock.clone());
+        let handle = timer.handle();
+        let reason = Rc::new(RefCell::new(None));
+        let mut frames = Vec::new();
+
+        let mut enter = tokio_executor::enter().unwrap();
+        tokio_timer::clock::with_default(&clock, &mut enter, |enter| {
+            tokio_timer::with_default(&handle, enter, |_| {
+                // payload stream is never closed by the client
+                let payload = futures::stream::iter_ok(chunks)
+                    .chain(future::empty::<Bytes, PayloadError>().into_stream());
+                let mut stream = executor::spawn(WebsocketContext::create(
+                    HbActor(reason.clone()),
+                    payload,
+                ));
+                let notify = NotifyHandle::from(Arc::new(NoopNotify));
+                let mut codec = Codec::new().client_mode();
+
+                loop {
+                    match stream.poll_stream_notify(&notify, 0).unwrap() {
+                        Async::Ready(Some(chunk)) => {
+                            let mut buf = BytesMut::from(chunk);
+                            while let Some(frame) = codec.decode(&mut buf).unwrap() {
+                                frames.push(frame);
+                            }
+                        }
+                        Async::Ready(None) => break,
+                        Async::NotReady => {
+                            timer.turn(None).unwrap();
+                        }
+                    }
+                }
+            })
+        });
+
+        let elapsed = now.now().duration_since(start).as_secs();
+        let reason = reason.borrow_mut().take();
+        (frames, elapsed, reason)
+    }
+
+    fn encode(msg: Message, client: bool) -> Bytes {
+        let mut codec = if client {
+            Codec::new().client_mode()
+        } else {
+            Codec::new()
+        };
+        let mut buf = BytesMut::new();
+        codec.encode(msg, &mut buf).unwrap();
+        buf.freeze()
+    }
+
+    #[test]
+    fn test_heartbeat_timeout() {
+        let (frames, elapsed, reason) = run(vec![]);
+        assert_eq!(
+            frames,
+            vec![
+                Frame::Ping(String::new()),
+                Frame::Ping(String::new()),
+                Frame::Close(Some(CloseCode::Away.into())),
+            ]
+        );
+        assert_eq!(elapsed, 3);
+        assert_eq!(reason, Some(StopReason::HeartbeatTimeout));
+    }
+
+    #[test]
+    fn test_heartbeat_ping_reply() {
+        // client ping is answered by the context
+        let (frames, elapsed, reason) =
+            run(vec![encode(Message::Ping("text".to_string()), true)]);
+        assert_eq!(frames[0], Frame::Pong("text".to_string()));
+        assert_eq!(frames.len(), 4);
+        assert_eq!(elapsed, 3);
+        assert_eq!(reason, Some(StopReason::HeartbeatTimeout));
+    }
+
+    #[test]
+    fn test_stop_reason() {
+        let (frames, elapsed, reason) = run(vec![encode(
+            Message::Close(Some(CloseCode::Normal.into())),
+            true,
+        )]);
+        assert!(frames.is_empty());
+        assert_eq!(elapsed, 0);
+        assert_eq!(
+            reason,
+            Some(StopReason::PeerClose(Some(CloseCode::Normal.into())))
+        );
+
+        // frames of the client must be masked
+        let (_, _, reason) = run(vec![encode(Message::Text("text".to_string()), false)]);
+        assert_eq!(
+            reason,
+            Some(StopReason::ProtocolError(
+                ProtocolError::UnmaskedFrame.to_string()
+            ))
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use actix::prelude::*;
 use actix_http::HttpService;
 use actix_http_test::TestServer;
@@ -19,6 +21,7 @@ impl StreamHandler<ws::Message, ws::ProtocolError> for Ws {
             ws::Message::Text(text) => ctx.text(text),
             ws::Message::Binary(bin) => ctx.binary(bin),
             ws::Message::Close(reason) => ctx.close(reason),
+            ws::Message::Continuation(item) => ctx.continuation(item),
             _ => (),
         }
     }
@@ -56,6 +59,32 @@ fn test_simple() {
     let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
     assert_eq!(item, Some(ws::Frame::Pong("text".to_string().into())));
 
+    let framed = srv
+        .block_on(framed.send(ws::Message::Continuation(ws::Item::FirstText(
+            Bytes::from_static(b"first"),
+        ))))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(
+        item,
+        Some(ws::Frame::Continuation(ws::Item::FirstText(
+            Bytes::from_static(b"first")
+        )))
+    );
+
+    let framed = srv
+        .block_on(framed.send(ws::Message::Continuation(ws::Item::Last(
+            Bytes::from_static(b"last"),
+        ))))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(
+        item,
+        Some(ws::Frame::Continuation(ws::Item::Last(Bytes::from_static(
+            b"last"
+        ))))
+    );
+
     let framed = srv
         .block_on(framed.send(ws::Message::Close(Some(ws::CloseCode::Normal.into()))))
         .unwrap();
@@ -66,3 +95,40 @@ fn test_simple() {
         Some(ws::Frame::Close(Some(ws::CloseCode::Normal.into())))
     );
 }
+
+struct WsHeartbeat;
+
+impl Actor for WsHeartbeat {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.heartbeat(Duration::from_secs(5), Duration::from_secs(10));
+    }
+}
+
+impl StreamHandler<ws::Message, ws::ProtocolError> for WsHeartbeat {
+    fn handle(&mut self, msg: ws::Message, ctx: &mut Self::Context) {
+        if let ws::Message::Text(text) = msg {
+            ctx.text(text)
+        }
+    }
+}
+
+#[test]
+fn test_heartbeat() {
+    let mut srv = TestServer::new(|| {
+        HttpService::new(App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| {
+                ws::start(WsHeartbeat, &req, stream)
+            },
+        )))
+    });
+
+    // client pings are answered by context
+    let framed = srv.ws().unwrap();
+    let framed = srv
+        .block_on(framed.send(ws::Message::Ping("text".into())))
+        .unwrap();
+    let (item, _) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(item, Some(ws::Frame::Pong("text".to_string())));
+}
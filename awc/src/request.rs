@@ -26,13 +26,34 @@ use crate::error::{InvalidUrl, PayloadError, SendRequestError};
 use crate::response::ClientResponse;
 use crate::ClientConfig;
 
-#[cfg(any(feature = "brotli", feature = "flate2-zlib", feature = "flate2-rust"))]
-const HTTPS_ENCODING: &str = "br, gzip, deflate";
-#[cfg(all(
-    any(feature = "flate2-zlib", feature = "flate2-rust"),
-    not(feature = "brotli")
+/// `Accept-Encoding` value for automatic response decompression
+///
+/// br and zstd are requested only over https.
+#[cfg(any(
+    feature = "brotli",
+    feature = "zstd",
+    feature = "flate2-zlib",
+    feature = "flate2-rust"
 ))]
-const HTTPS_ENCODING: &str = "gzip, deflate";
+fn accept_encoding(https: bool) -> String {
+    let mut encodings = Vec::new();
+    #[cfg(feature = "brotli")]
+    encodings.push(("br", true));
+    #[cfg(feature = "zstd")]
+    encodings.push(("zstd", true));
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    {
+        encodings.push(("gzip", false));
+        encodings.push(("deflate", false));
+    }
+
+    encodings
+        .into_iter()
+        .filter(|(_, https_only)| https || !https_only)
+        .map(|(enc, _)| enc)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
 /// An HTTP Client request builder
 ///
@@ -411,9 +432,10 @@ impl ClientRequest {
 
         let mut slf = self;
 
-        // enable br only for https
+        // enable br and zstd only for https
         #[cfg(any(
             feature = "brotli",
+            feature = "zstd",
             feature = "flate2-zlib",
             feature = "flate2-rust"
         ))]
@@ -426,15 +448,10 @@ impl ClientRequest {
                     .map(|s| s == &uri::Scheme::HTTPS)
                     .unwrap_or(true);
 
-                if https {
-                    slf = slf.set_header_if_none(header::ACCEPT_ENCODING, HTTPS_ENCODING)
-                } else {
-                    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
-                    {
-                        slf = slf
-                            .set_header_if_none(header::ACCEPT_ENCODING, "gzip, deflate")
-                    }
-                };
+                let encoding = accept_encoding(https);
+                if !encoding.is_empty() {
+                    slf = slf.set_header_if_none(header::ACCEPT_ENCODING, encoding)
+                }
             }
         }
 
@@ -569,6 +586,15 @@ mod tests {
         assert!(repr.contains("x-test"));
     }
 
+    #[test]
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    fn test_accept_encoding() {
+        assert_eq!(accept_encoding(false), "gzip, deflate");
+        assert!(accept_encoding(true).ends_with("gzip, deflate"));
+        #[cfg(feature = "zstd")]
+        assert!(accept_encoding(true).contains("zstd"));
+    }
+
     #[test]
     fn test_basics() {
         let mut req = Client::new()
@@ -11,7 +11,9 @@ use futures::future::{err, Either, Future};
 use percent_encoding::{percent_encode, USERINFO_ENCODE_SET};
 use tokio_timer::Timeout;
 
-pub use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Message};
+pub use actix_http::ws::{
+    CloseCode, CloseReason, Codec, DeflateConfig, Frame, Item, Message,
+};
 
 use crate::connect::BoxedSocket;
 use crate::error::{InvalidUrl, SendRequestError, WsClientError};
@@ -33,6 +35,7 @@ pub struct WebsocketsRequest {
     addr: Option<SocketAddr>,
     max_size: usize,
     server_mode: bool,
+    deflate: Option<DeflateConfig>,
     cookies: Option<CookieJar>,
     config: Rc<ClientConfig>,
 }
@@ -62,6 +65,7 @@ impl WebsocketsRequest {
             protocols: None,
             max_size: 65_536,
             server_mode: false,
+            deflate: None,
             cookies: None,
         }
     }
@@ -127,6 +131,14 @@ impl WebsocketsRequest {
         self
     }
 
+    /// Offer `permessage-deflate` extension to the server.
+    ///
+    /// Compression is used only if server accepts the offer.
+    pub fn deflate(mut self, cfg: DeflateConfig) -> Self {
+        self.deflate = Some(cfg);
+        self
+    }
+
     /// Append a header.
     ///
     /// Header gets appended to existing header.
@@ -267,6 +279,12 @@ impl WebsocketsRequest {
             );
         }
 
+        if let Some(ref cfg) = self.deflate {
+            self.head
+                .headers
+                .insert(header::SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from(*cfg));
+        }
+
         // Generate a random key for the `Sec-WebSocket-Key` header.
         // a base64-encoded (see Section 4 of [RFC4648]) value that,
         // when decoded, is 16 bytes in length (RFC 6455)
@@ -281,6 +299,7 @@ impl WebsocketsRequest {
         let head = self.head;
         let max_size = self.max_size;
         let server_mode = self.server_mode;
+        let deflate = self.deflate;
 
         let fut = self
             .config
@@ -345,14 +364,29 @@ impl WebsocketsRequest {
                     return Err(WsClientError::MissingWebSocketAcceptHeader);
                 };
 
+                // negotiated extensions
+                let deflate = if let Some(ref cfg) = deflate {
+                    cfg.accept(&head.headers).map_err(WsClientError::Protocol)?
+                } else if head.headers.contains_key(&header::SEC_WEBSOCKET_EXTENSIONS) {
+                    log::trace!("Server accepted extension that was not offered");
+                    return Err(WsClientError::Protocol(ws::ProtocolError::Compression));
+                } else {
+                    None
+                };
+
                 // response and ws framed
                 Ok((
                     ClientResponse::new(head, Payload::None),
                     framed.map_codec(|_| {
-                        if server_mode {
+                        let codec = if server_mode {
                             ws::Codec::new().max_size(max_size)
                         } else {
                             ws::Codec::new().max_size(max_size).client_mode()
+                        };
+                        if let Some(cfg) = deflate {
+                            codec.deflate(cfg)
+                        } else {
+                            codec
                         }
                     }),
                 ))
@@ -420,6 +454,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deflate() {
+        let req = Client::new().ws("/").deflate(DeflateConfig::new());
+        assert!(req.deflate.is_some());
+        assert!(!req
+            .head
+            .headers
+            .contains_key(header::SEC_WEBSOCKET_EXTENSIONS));
+    }
+
     #[test]
     fn basic_auth() {
         let req = Client::new()
@@ -0,0 +1,75 @@
+//! Compare prefix tree router with linear `actix_router::Router`.
+//!
+//! Run with `cargo bench --bench router`.
+use std::time::{Duration, Instant};
+
+use actix_router::{Path, Router as LinearRouter};
+use actix_web::dev::Router;
+
+const ITERATIONS: u32 = 100_000;
+
+/// Route table of api with `size` resources
+fn patterns(size: usize) -> Vec<String> {
+    let mut patterns = Vec::new();
+    for idx in 0..size / 4 {
+        patterns.push(format!("/api/v1/resource{}", idx));
+        patterns.push(format!("/api/v1/resource{}/{{id}}", idx));
+        patterns.push(format!("/api/v1/resource{}/{{id}}/items", idx));
+        patterns.push(format!("/api/v1/resource{}/{{id}}/items/{{item:\\d+}}", idx));
+    }
+    patterns
+}
+
+/// Request paths, first, middle, last and not registered resources
+fn paths(size: usize) -> Vec<String> {
+    let last = size / 4 - 1;
+    vec![
+        "/api/v1/resource0".to_string(),
+        format!("/api/v1/resource{}/10", last / 2),
+        format!("/api/v1/resource{}/10/items", last),
+        format!("/api/v1/resource{}/10/items/20", last),
+        "/api/v2/unknown".to_string(),
+    ]
+}
+
+fn run<F: FnMut(&str) -> Option<usize>>(paths: &[String], mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for path in paths {
+            let _ = f(path);
+        }
+    }
+    start.elapsed() / (ITERATIONS * paths.len() as u32)
+}
+
+fn main() {
+    for size in &[8, 40, 200, 400, 1000] {
+        let patterns = patterns(*size);
+        let paths = paths(*size);
+
+        let mut linear = LinearRouter::<usize>::build();
+        let mut tree = Router::<usize>::build();
+        for (idx, pattern) in patterns.iter().enumerate() {
+            linear.path(pattern, idx);
+            tree.path(pattern, idx);
+        }
+        let mut linear = linear.finish();
+        let mut tree = tree.finish();
+
+        let linear = run(&paths, |p| {
+            let mut path = Path::new(p);
+            linear
+                .recognize_mut_checked(&mut path, |_, _| true)
+                .map(|(idx, _)| *idx)
+        });
+        let tree = run(&paths, |p| {
+            let mut path = Path::new(p);
+            tree.recognize_mut_checked(&mut path, |_, _| true)
+                .map(|(idx, _)| *idx)
+        });
+        println!(
+            "{:>5} resources: linear {:>10?}, tree {:>10?}",
+            size, linear, tree
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::fmt;
 use std::marker::PhantomData;
 use std::rc::Rc;
+use std::sync::Once;
 
 use actix_http::body::{Body, MessageBody};
 use actix_service::boxed::{self, BoxedNewService};
@@ -17,6 +18,7 @@ use crate::dev::ResourceDef;
 use crate::error::Error;
 use crate::resource::Resource;
 use crate::normalized_resource::NormalizedResource;
+use crate::rmap::ResourceMap;
 use crate::route::Route;
 use crate::service::{
     HttpServiceFactory, ServiceFactory, ServiceFactoryWrapper, ServiceRequest,
@@ -38,6 +40,8 @@ pub struct App<T, B> {
     data_factories: Vec<FnDataFactory>,
     config: AppConfigInner,
     external: Vec<ResourceDef>,
+    inspect_routes: Option<Rc<dyn Fn(&ResourceMap)>>,
+    strict_routes: bool,
     _t: PhantomData<(B)>,
 }
 
@@ -54,6 +58,8 @@ impl App<AppEntry, Body> {
             factory_ref: fref,
             config: AppConfigInner::default(),
             external: Vec::new(),
+            inspect_routes: None,
+            strict_routes: false,
             _t: PhantomData,
         }
     }
@@ -181,6 +187,12 @@ where
     /// This method can be used multiple times with same path, in that case
     /// multiple resources with one route would be registered for same resource path.
     ///
+    /// Route guards are moved to the registered resource, requests with other
+    /// methods do not reach it. *HEAD* requests are handled by *GET* routes,
+    /// but *OPTIONS* requests are not answered automatically and *404* is
+    /// returned instead of *405* with `Allow` header. Use `App::service()`
+    /// with `web::resource()` to get complete method handling.
+    ///
     /// ```rust
     /// use actix_web::{web, App, HttpResponse};
     ///
@@ -333,6 +345,71 @@ where
         self
     }
 
+    /// Inspect application routes.
+    ///
+    /// Callback is called with application's `ResourceMap` after all
+    /// services are registered. Http server constructs an application
+    /// instance for each worker, so callback is called once per worker.
+    ///
+    /// ```rust
+    /// use actix_web::{dev::ResourceKind, web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .route("/index.html", web::get().to(|| HttpResponse::Ok()))
+    ///         .inspect_routes(|rmap| {
+    ///             for entry in rmap.resources() {
+    ///                 if entry.kind() == ResourceKind::Resource {
+    ///                     println!("{:?} {}", entry.methods(), entry.pattern());
+    ///                 }
+    ///             }
+    ///         });
+    /// }
+    /// ```
+    pub fn inspect_routes<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ResourceMap) + 'static,
+    {
+        self.inspect_routes = Some(Rc::new(f));
+        self
+    }
+
+    /// Print application route table to stdout on startup.
+    ///
+    /// Route table is printed only once per process, by the first
+    /// constructed application instance. Check
+    /// [`ResourceMap::route_table()`](dev/struct.ResourceMap.html#method.route_table)
+    /// for table format.
+    pub fn print_routes(self) -> Self {
+        self.inspect_routes(|rmap| {
+            static PRINTED: Once = Once::new();
+            PRINTED.call_once(|| print!("{}", rmap.route_table()));
+        })
+    }
+
+    /// Fail application construction on unreachable routes.
+    ///
+    /// Route is unreachable if route registered before it in the same
+    /// application or scope matches all of its paths and requests, i.e.
+    /// `/users/{id}` registered before `/users/me`. By default unreachable
+    /// routes are reported with warnings in log, in strict mode they are
+    /// reported as errors and application fails to start.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .strict_routes()
+    ///         .route("/users/me", web::get().to(|| HttpResponse::Ok()))
+    ///         .route("/users/{id}", web::get().to(|| HttpResponse::Ok()));
+    /// }
+    /// ```
+    pub fn strict_routes(mut self) -> Self {
+        self.strict_routes = true;
+        self
+    }
+
     /// Registers middleware, in the form of a middleware component (type),
     /// that runs during inbound and/or outbound processing in the request
     /// lifecycle (request -> response), modifying request/response as
@@ -398,6 +475,8 @@ where
             factory_ref: self.factory_ref,
             config: self.config,
             external: self.external,
+            inspect_routes: self.inspect_routes,
+            strict_routes: self.strict_routes,
             _t: PhantomData,
         }
     }
@@ -471,6 +550,8 @@ where
             endpoint: self.endpoint,
             services: Rc::new(RefCell::new(self.services)),
             external: RefCell::new(self.external),
+            inspect_routes: self.inspect_routes,
+            strict_routes: self.strict_routes,
             default: self.default,
             factory_ref: self.factory_ref,
             config: RefCell::new(AppConfig(Rc::new(self.config))),
@@ -480,17 +561,20 @@ where
 
 #[cfg(test)]
 mod tests {
+    use actix_server_config::ServerConfig;
     use actix_service::Service;
     use bytes::Bytes;
     use futures::{Future, IntoFuture};
 
     use super::*;
     use crate::http::{header, HeaderValue, Method, StatusCode};
+    use crate::rmap::ResourceKind;
     use crate::service::{ServiceRequest, ServiceResponse};
     use crate::test::{
-        block_fn, block_on, call_service, init_service, read_body, TestRequest,
+        block_fn, block_on, call_service, init_service, read_body, run_on,
+        TestRequest,
     };
-    use crate::{web, Error, HttpRequest, HttpResponse};
+    use crate::{guard, web, Error, HttpRequest, HttpResponse};
 
     #[test]
     fn test_default_resource() {
@@ -673,4 +757,147 @@ mod tests {
         let body = read_body(resp);
         assert_eq!(body, Bytes::from_static(b"https://youtube.com/watch/12345"));
     }
+
+    #[test]
+    fn test_inspect_routes() {
+        let table = Rc::new(RefCell::new(None));
+        let table2 = table.clone();
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        let entries2 = entries.clone();
+
+        let _ = init_service(
+            App::new()
+                .external_resource("youtube", "https://youtube.com/watch/{video_id}")
+                .route("/index.html", web::get().to(|| HttpResponse::Ok()))
+                .service(
+                    web::resource("/users/{id}")
+                        .name("user")
+                        .route(web::get().to(|| HttpResponse::Ok()))
+                        .route(web::put().to(|| HttpResponse::Ok())),
+                )
+                .service(
+                    web::scope("/api")
+                        .service(web::resource("/items").to(|| HttpResponse::Ok()))
+                        .default_service(|r: ServiceRequest| {
+                            r.into_response(HttpResponse::NotFound())
+                        }),
+                )
+                .default_service(|r: ServiceRequest| {
+                    r.into_response(HttpResponse::NotFound())
+                })
+                .inspect_routes(move |rmap| {
+                    *table2.borrow_mut() = Some(rmap.route_table());
+                    *entries2.borrow_mut() = rmap.resources();
+                }),
+        );
+
+        let entries = entries.borrow();
+        assert_eq!(entries.len(), 7);
+        assert_eq!(entries[1].pattern(), "/users/{id}");
+        assert_eq!(entries[1].name(), Some("user"));
+        assert_eq!(entries[1].methods(), Some(&[Method::GET, Method::PUT][..]));
+        assert_eq!(entries[1].kind(), ResourceKind::Resource);
+        assert_eq!(entries[3].pattern(), "/api/items");
+        assert_eq!(entries[3].methods(), None);
+        assert_eq!(entries[4].kind(), ResourceKind::Default);
+        assert_eq!(entries[5].kind(), ResourceKind::External);
+
+        assert_eq!(
+            table.borrow().as_ref().unwrap(),
+            "GET     /index.html\n\
+             GET,PUT /users/{id} name=user\n\
+             *       /api (scope)\n\
+             *       /api/items\n\
+             *       /api (default)\n\
+             *       https://youtube.com/watch/{video_id} name=youtube (external)\n\
+             *       / (default)\n"
+        );
+    }
+
+    #[test]
+    fn test_strict_routes() {
+        let app = || {
+            App::new()
+                .route("/users/{id}", web::get().to(|| HttpResponse::Ok()))
+                .route("/users/me", web::get().to(|| HttpResponse::Created()))
+                .service(
+                    web::scope("/api")
+                        .route("/items", web::get().to(|| HttpResponse::Ok()))
+                        .route("/items", web::post().to(|| HttpResponse::Ok())),
+                )
+        };
+
+        // unreachable routes are reported with warnings
+        let mut srv = init_service(app());
+        let req = TestRequest::with_uri("/users/me").to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let cfg = ServerConfig::new("127.0.0.1:8080".parse().unwrap());
+        let srv = app().strict_routes().into_new_service();
+        let fut = run_on(move || srv.new_service(&cfg));
+        assert!(block_on(fut).is_err());
+
+        let cfg = ServerConfig::new("127.0.0.1:8080".parse().unwrap());
+        let srv = App::new()
+            .strict_routes()
+            .service(
+                web::scope("/api")
+                    .route("/items", web::get().to(|| HttpResponse::Ok()))
+                    .route("/items", web::get().to(|| HttpResponse::Ok())),
+            )
+            .into_new_service();
+        let fut = run_on(move || srv.new_service(&cfg));
+        assert!(block_on(fut).is_err());
+
+        // requests without header reach second resource
+        let cfg = ServerConfig::new("127.0.0.1:8080".parse().unwrap());
+        let srv = App::new()
+            .strict_routes()
+            .service(
+                web::resource("/items")
+                    .guard(guard::All(guard::Get()).and(guard::Header("x-v", "2")))
+                    .to(|| HttpResponse::Created()),
+            )
+            .service(
+                web::resource("/items")
+                    .guard(guard::Get())
+                    .to(|| HttpResponse::Ok()),
+            )
+            .into_new_service();
+        let fut = run_on(move || srv.new_service(&cfg));
+        assert!(block_on(fut).is_ok());
+    }
+
+    #[test]
+    fn test_route_head() {
+        let mut srv = init_service(
+            App::new()
+                .route("/test", web::post().to(|| HttpResponse::Created()))
+                .route("/test", web::get().to(|| HttpResponse::Ok().body("test")))
+                .service(
+                    web::scope("/app")
+                        .route("/test", web::get().to(|| HttpResponse::Ok())),
+                ),
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::HEAD)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/app/test")
+            .method(Method::HEAD)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // each route is a separate resource
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -2,8 +2,9 @@ use std::cell::RefCell;
 use std::marker::PhantomData;
 use std::rc::Rc;
 
+use actix_http::http::Method;
 use actix_http::{Extensions, Request, Response};
-use actix_router::{Path, ResourceDef, ResourceInfo, Router, Url};
+use actix_router::{Path, ResourceDef, ResourceInfo, Url};
 use actix_server_config::ServerConfig;
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{service_fn, NewService, Service};
@@ -15,7 +16,8 @@ use crate::data::DataFactory;
 use crate::error::Error;
 use crate::guard::Guard;
 use crate::request::{HttpRequest, HttpRequestPool};
-use crate::rmap::ResourceMap;
+use crate::rmap::{ResourceKind, ResourceMap};
+use crate::router::Router;
 use crate::service::{ServiceFactory, ServiceRequest, ServiceResponse};
 
 type Guards = Vec<Box<dyn Guard>>;
@@ -48,6 +50,8 @@ where
     pub(crate) default: Option<Rc<HttpNewService>>,
     pub(crate) factory_ref: Rc<RefCell<Option<AppRoutingFactory>>>,
     pub(crate) external: RefCell<Vec<ResourceDef>>,
+    pub(crate) inspect_routes: Option<Rc<dyn Fn(&ResourceMap)>>,
+    pub(crate) strict_routes: bool,
 }
 
 impl<T, B> NewService for AppInit<T, B>
@@ -95,6 +99,16 @@ where
             .into_iter()
             .for_each(|mut srv| srv.register(&mut config));
 
+        // unreachable routes
+        let conflicts = config.route_conflicts();
+        for conflict in &conflicts {
+            if self.strict_routes {
+                log::error!("{}", conflict);
+            } else {
+                log::warn!("{}", conflict);
+            }
+        }
+
         let mut rmap = ResourceMap::new(ResourceDef::new(""));
 
         let (config, services) = config.into_services();
@@ -105,8 +119,8 @@ where
             services: Rc::new(
                 services
                     .into_iter()
-                    .map(|(mut rdef, srv, guards, nested)| {
-                        rmap.add(&mut rdef, nested);
+                    .map(|(mut rdef, srv, guards, nested, methods)| {
+                        rmap.add_service(&mut rdef, nested, methods);
                         (rdef, srv, RefCell::new(guards))
                     })
                     .collect(),
@@ -115,13 +129,20 @@ where
 
         // external resources
         for mut rdef in std::mem::replace(&mut *self.external.borrow_mut(), Vec::new()) {
-            rmap.add(&mut rdef, None);
+            rmap.add_entry(&mut rdef, None, ResourceKind::External, Default::default());
+        }
+        if self.default.is_some() {
+            rmap.set_default();
         }
 
         // complete ResourceMap tree creation
         let rmap = Rc::new(rmap);
         rmap.finish(rmap.clone());
 
+        if let Some(ref f) = self.inspect_routes {
+            f(&rmap);
+        }
+
         AppInitResult {
             endpoint: None,
             endpoint_fut: self.endpoint.new_service(&()),
@@ -130,6 +151,7 @@ where
             data_factories_fut: self.data_factories.iter().map(|f| f()).collect(),
             config,
             rmap,
+            failed: self.strict_routes && !conflicts.is_empty(),
             _t: PhantomData,
         }
     }
@@ -143,6 +165,7 @@ where
     endpoint_fut: T::Future,
     rmap: Rc<ResourceMap>,
     config: AppConfig,
+    failed: bool,
     data: Rc<Vec<Box<dyn DataFactory>>>,
     data_factories: Vec<Box<dyn DataFactory>>,
     data_factories_fut: Vec<Box<dyn Future<Item = Box<dyn DataFactory>, Error = ()>>>,
@@ -163,6 +186,11 @@ where
     type Error = ();
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        // unreachable routes in strict mode
+        if self.failed {
+            return Err(());
+        }
+
         // async data factories
         let mut idx = 0;
         while idx < self.data_factories_fut.len() {
@@ -396,7 +424,7 @@ impl Service for AppRouting {
     }
 
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
-        let res = self.router.recognize_mut_checked(&mut req, |req, guards| {
+        let check = |req: &ServiceRequest, guards: &Option<Guards>| {
             if let Some(ref guards) = guards {
                 for f in guards {
                     if !f.check(req.head()) {
@@ -405,7 +433,16 @@ impl Service for AppRouting {
                 }
             }
             true
-        });
+        };
+        let mut res = self.router.recognize_mut_checked(&mut req, &check);
+
+        // *HEAD* request could be handled by resource restricted to *GET*,
+        // i.e. registered with `route()`
+        if res.is_none() && *req.method() == Method::HEAD {
+            req.head_mut().method = Method::GET;
+            res = self.router.recognize_mut_checked(&mut req, &check);
+            req.head_mut().method = Method::HEAD;
+        }
 
         if let Some((srv, _info)) = res {
             srv.call(req)
@@ -6,11 +6,12 @@ use actix_router::ResourceDef;
 use actix_service::{boxed, IntoNewService, NewService};
 
 use crate::data::{Data, DataFactory};
+use crate::conflict::{route_conflicts, RouteConflict};
 use crate::error::Error;
 use crate::guard::Guard;
 use crate::resource::Resource;
 use crate::normalized_resource::NormalizedResource;
-use crate::rmap::ResourceMap;
+use crate::rmap::{ResourceMap, ResourceMethods};
 use crate::route::Route;
 use crate::service::{
     HttpServiceFactory, ServiceFactory, ServiceFactoryWrapper, ServiceRequest,
@@ -31,8 +32,10 @@ pub struct AppService {
         HttpNewService,
         Option<Guards>,
         Option<Rc<ResourceMap>>,
+        ResourceMethods,
     )>,
     service_data: Rc<Vec<Box<dyn DataFactory>>>,
+    conflicts: Vec<RouteConflict>,
 }
 
 impl AppService {
@@ -48,6 +51,7 @@ impl AppService {
             service_data,
             root: true,
             services: Vec::new(),
+            conflicts: Vec::new(),
         }
     }
 
@@ -65,6 +69,7 @@ impl AppService {
             HttpNewService,
             Option<Guards>,
             Option<Rc<ResourceMap>>,
+            ResourceMethods,
         )>,
     ) {
         (self.config, self.services)
@@ -77,9 +82,26 @@ impl AppService {
             services: Vec::new(),
             root: false,
             service_data: self.service_data.clone(),
+            conflicts: Vec::new(),
         }
     }
 
+    /// Unreachable routes of registered services and nested scopes
+    pub(crate) fn route_conflicts(&mut self) -> Vec<RouteConflict> {
+        let mut conflicts = route_conflicts(
+            self.services
+                .iter()
+                .map(|(rdef, _, guards, _, _)| (rdef, guards.as_ref())),
+        );
+        conflicts.extend(self.conflicts.drain(..));
+        conflicts
+    }
+
+    /// Add unreachable route of nested scope
+    pub(crate) fn add_route_conflict(&mut self, conflict: RouteConflict) {
+        self.conflicts.push(conflict);
+    }
+
     /// Service configuration
     pub fn config(&self) -> &AppConfig {
         &self.config
@@ -120,6 +142,35 @@ impl AppService {
             boxed::new_service(service.into_new_service()),
             guards,
             nested,
+            ResourceMethods::default(),
+        ));
+    }
+
+    /// Register http service that handles specific http methods.
+    ///
+    /// Methods and api operations are reported by application `ResourceMap`.
+    pub(crate) fn register_resource<F, S>(
+        &mut self,
+        rdef: ResourceDef,
+        guards: Option<Vec<Box<dyn Guard>>>,
+        service: F,
+        methods: ResourceMethods,
+    ) where
+        F: IntoNewService<S>,
+        S: NewService<
+                Config = (),
+                Request = ServiceRequest,
+                Response = ServiceResponse,
+                Error = Error,
+                InitError = (),
+            > + 'static,
+    {
+        self.services.push((
+            rdef,
+            boxed::new_service(service.into_new_service()),
+            guards,
+            None,
+            methods,
         ));
     }
 }
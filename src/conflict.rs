@@ -0,0 +1,498 @@
+//! Detection of unreachable routes
+//!
+//! Resource is unreachable if resource registered before it matches every
+//! path of the resource and its guards pass every request of the resource.
+//! Resource is unreachable for some of its methods, if resources registered
+//! before it match every path of the resource for these methods.
+//! Only patterns with static segments, `{param}` segments and tail
+//! segment are compared, guards are compared by http methods. Routes that
+//! could not be compared are never reported.
+use std::fmt;
+
+use actix_router::ResourceDef;
+
+use crate::guard::{self, Guard};
+use crate::http::Method;
+
+/// Route that never matches because of route registered before it
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RouteConflict {
+    pattern: String,
+    shadowed_by: String,
+    /// Unreachable methods, `None` if route is unreachable for every request
+    methods: Option<Vec<Method>>,
+}
+
+impl RouteConflict {
+    /// Prepend scope prefix to patterns
+    pub(crate) fn with_prefix(mut self, prefix: &str) -> Self {
+        self.pattern.insert_str(0, prefix);
+        self.shadowed_by.insert_str(0, prefix);
+        self
+    }
+}
+
+impl fmt::Display for RouteConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Route \"{}\" is unreachable", self.pattern)?;
+        if let Some(ref methods) = self.methods {
+            let methods: Vec<_> = methods.iter().map(|m| m.as_str()).collect();
+            write!(f, " for {} requests", methods.join(", "))?;
+        }
+        if self.pattern == self.shadowed_by {
+            write!(f, ", same route is registered before")
+        } else {
+            write!(f, ", shadowed by \"{}\"", self.shadowed_by)
+        }
+    }
+}
+
+/// Find routes shadowed by routes registered before them
+pub(crate) fn route_conflicts<'a, I>(routes: I) -> Vec<RouteConflict>
+where
+    I: IntoIterator<Item = (&'a ResourceDef, Option<&'a Vec<Box<dyn Guard>>>)>,
+{
+    let routes: Vec<_> = routes
+        .into_iter()
+        .map(|(rdef, guards)| (rdef, Shape::parse(rdef), guards))
+        .collect();
+
+    let mut conflicts = Vec::new();
+    for (idx, (rdef, shape, guards)) in routes.iter().enumerate() {
+        let shape = match shape {
+            Some(shape) => shape,
+            None => continue,
+        };
+
+        // first route that shadows every request, or routes that shadow
+        // some of the methods
+        let mut shadowed_by = None;
+        let mut methods = Vec::new();
+        for (prev, prev_shape, prev_guards) in &routes[..idx] {
+            if !prev_shape
+                .as_ref()
+                .map(|p| p.covers(shape))
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            match shadow(*prev_guards, *guards) {
+                Some(Shadow::All) => {
+                    shadowed_by = Some((prev, true));
+                    break;
+                }
+                Some(Shadow::Methods(shadowed)) => {
+                    if shadowed_by.is_none() {
+                        shadowed_by = Some((prev, false));
+                    }
+                    methods.extend(shadowed);
+                }
+                None => (),
+            }
+        }
+
+        let (prev, all) = match shadowed_by {
+            Some(item) => item,
+            None => continue,
+        };
+        let methods = if all {
+            None
+        } else {
+            let route_methods = guards
+                .and_then(|g| guard::all_methods(g))
+                .unwrap_or_default();
+            let unreachable: Vec<_> = route_methods
+                .iter()
+                .filter(|m| methods.contains(m))
+                .cloned()
+                .collect();
+            if unreachable.len() == route_methods.len() {
+                None
+            } else {
+                Some(unreachable)
+            }
+        };
+        conflicts.push(RouteConflict {
+            pattern: rdef.pattern().to_string(),
+            shadowed_by: prev.pattern().to_string(),
+            methods,
+        });
+    }
+    conflicts
+}
+
+/// Requests of a route that pass guards of route registered before it
+#[derive(Debug, PartialEq)]
+enum Shadow {
+    /// Every request
+    All,
+    /// Requests with these methods
+    Methods(Vec<Method>),
+}
+
+/// Check which requests that pass `other` guards also pass `guards`,
+/// `None` if there are no such requests or guards could not be compared
+fn shadow(
+    guards: Option<&Vec<Box<dyn Guard>>>,
+    other: Option<&Vec<Box<dyn Guard>>>,
+) -> Option<Shadow> {
+    let guards = match guards {
+        Some(guards) if !guards.is_empty() => guards,
+        _ => return Some(Shadow::All),
+    };
+    // requests could be rejected by guards for other reasons than method
+    if !guards.iter().all(|g| g.is_method_only()) {
+        return None;
+    }
+    let methods = guard::all_methods(guards)?;
+    let other = other.and_then(|other| guard::all_methods(other))?;
+
+    let shadowed: Vec<_> = other
+        .iter()
+        .filter(|m| methods.contains(m))
+        .cloned()
+        .collect();
+    if shadowed.is_empty() {
+        None
+    } else if shadowed.len() == other.len() {
+        Some(Shadow::All)
+    } else {
+        Some(Shadow::Methods(shadowed))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Static(String),
+    /// `{name}` matches any non-empty segment
+    Param,
+}
+
+#[derive(Debug, PartialEq)]
+enum Rest {
+    /// Path has no more segments
+    Empty,
+    /// Path could have any number of segments
+    Any,
+    /// Path has at least one more segment
+    NonEmpty,
+}
+
+/// Set of paths matched by resource definition
+#[derive(Debug, PartialEq)]
+struct Shape {
+    segments: Vec<Segment>,
+    rest: Rest,
+}
+
+impl Shape {
+    /// Returns `None` if pattern could not be compared with other patterns
+    fn parse(rdef: &ResourceDef) -> Option<Shape> {
+        let pattern = rdef.pattern();
+        if pattern.ends_with('*') && !pattern.contains('{') {
+            return None;
+        }
+
+        let mut segments = Vec::new();
+        let mut rest = Rest::Empty;
+        let mut parts = pattern.split('/').peekable();
+        while let Some(part) = parts.next() {
+            let last = parts.peek().is_none();
+            if !part.contains(|c| c == '{' || c == '}') {
+                segments.push(Segment::Static(part.to_string()));
+            } else if is_param(part) {
+                segments.push(Segment::Param);
+            } else if last && is_tail(part) {
+                rest = Rest::NonEmpty;
+            } else {
+                return None;
+            }
+        }
+
+        // sample path must match, otherwise pattern is not parsed correctly
+        let sample = segments
+            .iter()
+            .map(|s| match s {
+                Segment::Static(s) => s.as_str(),
+                Segment::Param => "p",
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if rest == Rest::NonEmpty {
+            return if rdef.is_match(&format!("{}/", sample)) {
+                Some(Shape { segments, rest })
+            } else {
+                None
+            };
+        }
+        if !rdef.is_match(&sample) {
+            return None;
+        }
+
+        // prefix resource
+        if rdef.is_match(&format!("{}/p", sample)) {
+            match segments.last() {
+                // prefix with trailing slash
+                Some(Segment::Static(ref s)) if s.is_empty() && segments.len() > 1 => {
+                    segments.pop();
+                    rest = Rest::NonEmpty;
+                }
+                // dynamic prefix does not check segment boundary
+                Some(Segment::Static(_)) if pattern.contains('{') => return None,
+                _ => rest = Rest::Any,
+            }
+        }
+        Some(Shape { segments, rest })
+    }
+
+    /// Check if every path matched by `other` is matched by this shape
+    fn covers(&self, other: &Shape) -> bool {
+        let len = self.segments.len();
+        let other_len = other.segments.len();
+        if other_len < len {
+            return false;
+        }
+        let segments_match =
+            self.segments
+                .iter()
+                .zip(other.segments.iter())
+                .all(|(seg, other)| match (seg, other) {
+                    (Segment::Static(s), Segment::Static(o)) => s == o,
+                    (Segment::Param, Segment::Static(o)) => !o.is_empty(),
+                    (Segment::Param, Segment::Param) => true,
+                    (Segment::Static(_), Segment::Param) => false,
+                });
+        if !segments_match {
+            return false;
+        }
+
+        match self.rest {
+            Rest::Any => true,
+            Rest::Empty => other_len == len && other.rest == Rest::Empty,
+            Rest::NonEmpty => other_len > len || other.rest == Rest::NonEmpty,
+        }
+    }
+}
+
+/// `{name}` segment with default regex
+fn is_param(part: &str) -> bool {
+    part.len() > 2
+        && part.starts_with('{')
+        && part.ends_with('}')
+        && !part[1..part.len() - 1].contains(|c| c == '{' || c == '}' || c == ':')
+}
+
+/// `{name}*` or `{name:.*}` segment
+fn is_tail(part: &str) -> bool {
+    (part.ends_with('*') && is_param(&part[..part.len() - 1]))
+        || (part.starts_with('{')
+            && part.ends_with(":.*}")
+            && is_param(&format!("{}}}", &part[..part.len() - 4])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guard::{Get, Header, Post, Put};
+
+    fn shape(rdef: ResourceDef) -> Option<Shape> {
+        Shape::parse(&rdef)
+    }
+
+    fn covers(a: ResourceDef, b: ResourceDef) -> bool {
+        shape(a).unwrap().covers(&shape(b).unwrap())
+    }
+
+    #[test]
+    fn test_shape() {
+        assert_eq!(
+            shape(ResourceDef::new("/users/{id}")),
+            Some(Shape {
+                segments: vec![
+                    Segment::Static("".to_string()),
+                    Segment::Static("users".to_string()),
+                    Segment::Param
+                ],
+                rest: Rest::Empty,
+            })
+        );
+        assert_eq!(
+            shape(ResourceDef::prefix("/api/")),
+            Some(Shape {
+                segments: vec![
+                    Segment::Static("".to_string()),
+                    Segment::Static("api".to_string()),
+                ],
+                rest: Rest::NonEmpty,
+            })
+        );
+        assert_eq!(shape(ResourceDef::prefix("")).unwrap().rest, Rest::Any);
+        assert_eq!(
+            shape(ResourceDef::new("/files/{tail}*")).unwrap().rest,
+            Rest::NonEmpty
+        );
+        assert_eq!(
+            shape(ResourceDef::new("/files/{tail:.*}")).unwrap().rest,
+            Rest::NonEmpty
+        );
+        assert_eq!(shape(ResourceDef::new("/users/{id:\\d+}")), None);
+        assert_eq!(shape(ResourceDef::new("/file/{name}.{ext}")), None);
+        assert_eq!(shape(ResourceDef::prefix("/{project}/api")), None);
+        assert_eq!(shape(ResourceDef::new("/static*")), None);
+    }
+
+    #[test]
+    fn test_covers() {
+        assert!(covers(ResourceDef::new("/a"), ResourceDef::new("/a")));
+        assert!(!covers(ResourceDef::new("/a"), ResourceDef::new("/a/")));
+        assert!(covers(
+            ResourceDef::new("/users/{id}"),
+            ResourceDef::new("/users/me")
+        ));
+        assert!(!covers(
+            ResourceDef::new("/users/me"),
+            ResourceDef::new("/users/{id}")
+        ));
+        assert!(!covers(
+            ResourceDef::new("/users/{id}"),
+            ResourceDef::new("/users/")
+        ));
+        assert!(covers(
+            ResourceDef::prefix("/api"),
+            ResourceDef::new("/api")
+        ));
+        assert!(covers(
+            ResourceDef::prefix("/api"),
+            ResourceDef::new("/api/users/{id}")
+        ));
+        assert!(!covers(
+            ResourceDef::prefix("/api/"),
+            ResourceDef::new("/api")
+        ));
+        assert!(covers(
+            ResourceDef::prefix("/api/"),
+            ResourceDef::new("/api/users")
+        ));
+        assert!(!covers(
+            ResourceDef::prefix("/api"),
+            ResourceDef::new("/apix")
+        ));
+        assert!(covers(
+            ResourceDef::prefix(""),
+            ResourceDef::new("/index.html")
+        ));
+        assert!(covers(
+            ResourceDef::new("/files/{tail}*"),
+            ResourceDef::new("/files/index.html")
+        ));
+        assert!(!covers(
+            ResourceDef::new("/files/{tail}*"),
+            ResourceDef::new("/files")
+        ));
+        assert!(covers(
+            ResourceDef::prefix("/{project}"),
+            ResourceDef::new("/project/index.html")
+        ));
+    }
+
+    #[test]
+    fn test_shadow() {
+        let get: Vec<Box<dyn Guard>> = vec![Box::new(Get())];
+        let post: Vec<Box<dyn Guard>> = vec![Box::new(Post())];
+        let get_post: Vec<Box<dyn Guard>> =
+            vec![Box::new(crate::guard::Any(Get()).or(Post()))];
+        let header: Vec<Box<dyn Guard>> = vec![Box::new(Header("x-test", "1"))];
+
+        assert_eq!(shadow(None, None), Some(Shadow::All));
+        assert_eq!(shadow(None, Some(&header)), Some(Shadow::All));
+        assert_eq!(shadow(Some(&get), Some(&get)), Some(Shadow::All));
+        assert_eq!(shadow(Some(&get_post), Some(&post)), Some(Shadow::All));
+        assert_eq!(
+            shadow(Some(&get), Some(&get_post)),
+            Some(Shadow::Methods(vec![Method::GET]))
+        );
+        assert_eq!(shadow(Some(&get), Some(&post)), None);
+        assert_eq!(shadow(Some(&get), None), None);
+        assert_eq!(shadow(Some(&header), Some(&header)), None);
+        assert_eq!(shadow(Some(&get), Some(&header)), None);
+
+        // header is checked in addition to method
+        let get_header: Vec<Box<dyn Guard>> =
+            vec![Box::new(crate::guard::All(Get()).and(Header("x-v", "2")))];
+        assert_eq!(shadow(Some(&get_header), Some(&get)), None);
+        let any_header: Vec<Box<dyn Guard>> = vec![Box::new(
+            crate::guard::Any(Post())
+                .or(crate::guard::All(Get()).and(Header("x-v", "2"))),
+        )];
+        assert_eq!(shadow(Some(&any_header), Some(&get)), None);
+        assert_eq!(
+            shadow(Some(&get_post), Some(&get_header)),
+            Some(Shadow::All)
+        );
+
+        let custom: Vec<Box<dyn Guard>> = vec![Box::new(crate::guard::Method(
+            Method::from_bytes(b"PURGE").unwrap(),
+        ))];
+        assert_eq!(shadow(Some(&custom), Some(&get)), None);
+    }
+
+    #[test]
+    fn test_route_conflicts() {
+        let get: Vec<Box<dyn Guard>> = vec![Box::new(Get())];
+        let post: Vec<Box<dyn Guard>> = vec![Box::new(Post())];
+        let rdefs = vec![
+            ResourceDef::new("/users/{id}"),
+            ResourceDef::new("/users/me"),
+            ResourceDef::new("/items"),
+            ResourceDef::new("/items"),
+            ResourceDef::new("/items"),
+            ResourceDef::new("/users/{id:\\d+}"),
+        ];
+        let guards = vec![None, None, Some(&get), Some(&post), Some(&get), None];
+
+        let conflicts = route_conflicts(rdefs.iter().zip(guards.into_iter()));
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "Route \"/users/me\" is unreachable, shadowed by \"/users/{id}\""
+        );
+        assert_eq!(
+            conflicts[1].clone().with_prefix("/api").to_string(),
+            "Route \"/api/items\" is unreachable, same route is registered before"
+        );
+    }
+
+    #[test]
+    fn test_method_conflicts() {
+        let get_post: Vec<Box<dyn Guard>> =
+            vec![Box::new(crate::guard::Any(Get()).or(Post()))];
+        let post_put: Vec<Box<dyn Guard>> =
+            vec![Box::new(crate::guard::Any(Post()).or(Put()))];
+        let put: Vec<Box<dyn Guard>> = vec![Box::new(Put())];
+        let rdefs = vec![
+            ResourceDef::new("/items"),
+            ResourceDef::new("/items"),
+            ResourceDef::new("/{name}"),
+            ResourceDef::new("/items"),
+        ];
+        let guards = vec![
+            Some(&get_post),
+            Some(&post_put),
+            Some(&put),
+            Some(&post_put),
+        ];
+
+        let conflicts = route_conflicts(rdefs.iter().zip(guards.into_iter()));
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "Route \"/items\" is unreachable for POST requests, \
+             same route is registered before"
+        );
+        // every method is shadowed by some of the routes
+        assert_eq!(
+            conflicts[1].to_string(),
+            "Route \"/items\" is unreachable, same route is registered before"
+        );
+    }
+}
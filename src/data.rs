@@ -1,8 +1,10 @@
+use std::any::Any;
 use std::ops::Deref;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use actix_http::error::{Error, ErrorInternalServerError};
-use actix_http::Extensions;
+use actix_http::{Extensions, RequestHead};
 
 use crate::dev::Payload;
 use crate::extract::FromRequest;
@@ -126,6 +128,90 @@ impl<T: 'static> DataFactory for Data<T> {
     }
 }
 
+/// Connection data.
+///
+/// Connection data is computed once per connection by the callback
+/// registered with `HttpServer::on_connect()` method and is available
+/// to all requests received on that connection. Internally `ConnectionData`
+/// type uses `Rc`.
+///
+/// If connection data is not set or has different type, using
+/// `ConnectionData<T>` extractor would cause *Internal Server Error* response.
+///
+/// ```rust
+/// use std::any::Any;
+/// use actix_web::{web, App, HttpResponse, HttpServer};
+///
+/// struct Peer(String);
+///
+/// /// Use `ConnectionData<T>` extractor to access connection data in handler.
+/// fn index(peer: web::ConnectionData<Peer>) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("hello {}", peer.0))
+/// }
+///
+/// fn main() {
+///     HttpServer::new(|| App::new().route("/", web::get().to(index)))
+///         .on_connect(|_: &dyn Any| Peer("world".to_owned()));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ConnectionData<T>(Rc<T>);
+
+/// Type erased connection data stored in request extensions.
+#[derive(Clone)]
+pub(crate) struct ConnectionDataAny(pub(crate) Rc<dyn Any>);
+
+impl<T: 'static> ConnectionData<T> {
+    /// Get connection data of the connection the request was received on.
+    pub fn from_head(head: &RequestHead) -> Option<ConnectionData<T>> {
+        head.extensions()
+            .get::<ConnectionDataAny>()
+            .and_then(|data| data.0.clone().downcast::<T>().ok())
+            .map(ConnectionData)
+    }
+
+    /// Get reference to inner connection data.
+    pub fn get_ref(&self) -> &T {
+        self.0.as_ref()
+    }
+}
+
+impl<T> Deref for ConnectionData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0.as_ref()
+    }
+}
+
+impl<T> Clone for ConnectionData<T> {
+    fn clone(&self) -> ConnectionData<T> {
+        ConnectionData(self.0.clone())
+    }
+}
+
+impl<T: 'static> FromRequest for ConnectionData<T> {
+    type Config = ();
+    type Error = Error;
+    type Future = Result<Self, Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(data) = ConnectionData::from_head(req.head()) {
+            Ok(data)
+        } else {
+            log::debug!(
+                "Failed to construct ConnectionData extractor. \
+                 Request path: {:?}",
+                req.path()
+            );
+            Err(ErrorInternalServerError(
+                "Connection data is not configured, to configure use HttpServer::on_connect()",
+            ))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_service::Service;
@@ -218,4 +304,17 @@ mod tests {
         let resp = block_on(srv.call(req)).unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
     }
+
+    #[test]
+    fn test_connection_data_extractor() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(ConnectionData::<usize>::from_request(&req, &mut pl).is_err());
+
+        req.head()
+            .extensions_mut()
+            .insert(ConnectionDataAny(Rc::new(10usize)));
+        let data = ConnectionData::<usize>::from_request(&req, &mut pl).unwrap();
+        assert_eq!(*data, 10);
+        assert!(ConnectionData::<u32>::from_request(&req, &mut pl).is_err());
+    }
 }
@@ -52,7 +52,8 @@ pub enum UrlencodedError {
 impl ResponseError for UrlencodedError {
     fn error_response(&self) -> HttpResponse {
         match *self {
-            UrlencodedError::Overflow => {
+            UrlencodedError::Overflow
+            | UrlencodedError::Payload(PayloadError::DecompressionLimit) => {
                 HttpResponse::new(StatusCode::PAYLOAD_TOO_LARGE)
             }
             UrlencodedError::UnknownLength => {
@@ -84,7 +85,8 @@ pub enum JsonPayloadError {
 impl ResponseError for JsonPayloadError {
     fn error_response(&self) -> HttpResponse {
         match *self {
-            JsonPayloadError::Overflow => {
+            JsonPayloadError::Overflow
+            | JsonPayloadError::Payload(PayloadError::DecompressionLimit) => {
                 HttpResponse::new(StatusCode::PAYLOAD_TOO_LARGE)
             }
             _ => HttpResponse::new(StatusCode::BAD_REQUEST),
@@ -170,6 +172,9 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::LENGTH_REQUIRED);
         let resp: HttpResponse = UrlencodedError::ContentType.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp: HttpResponse =
+            UrlencodedError::Payload(PayloadError::DecompressionLimit).error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 
     #[test]
@@ -178,6 +183,9 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
         let resp: HttpResponse = JsonPayloadError::ContentType.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp: HttpResponse =
+            JsonPayloadError::Payload(PayloadError::DecompressionLimit).error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 
     #[test]
@@ -37,6 +37,48 @@ use std::rc::Rc;
 pub trait Guard {
     /// Check if request matches predicate
     fn check(&self, request: &RequestHead) -> bool;
+
+    /// Http methods matched by the guard
+    ///
+    /// Returns `None` if guard does not restrict request method. Methods
+    /// are used for `Allow` header of automatic *405* and *OPTIONS* responses.
+    fn methods(&self) -> Option<Vec<http::Method>> {
+        None
+    }
+
+    /// Check if guard result depends on request method only
+    ///
+    /// Guards of routes registered on the same path are compared by methods
+    /// only if all of them are method guards.
+    fn is_method_only(&self) -> bool {
+        false
+    }
+}
+
+/// Methods matched by all of the guards, `None` if no guard restricts method.
+pub(crate) fn all_methods(guards: &[Box<dyn Guard>]) -> Option<Vec<http::Method>> {
+    let mut result: Option<Vec<http::Method>> = None;
+    for methods in guards.iter().filter_map(|g| g.methods()) {
+        result = Some(match result {
+            Some(prev) => prev.into_iter().filter(|m| methods.contains(m)).collect(),
+            None => methods,
+        });
+    }
+    result
+}
+
+/// Methods matched by any of the guards, `None` if any guard does not
+/// restrict method.
+fn any_methods(guards: &[Box<dyn Guard>]) -> Option<Vec<http::Method>> {
+    let mut result = Vec::new();
+    for guard in guards {
+        for method in guard.methods()? {
+            if !result.contains(&method) {
+                result.push(method);
+            }
+        }
+    }
+    Some(result)
 }
 
 /// Create guard object for supplied function.
@@ -119,6 +161,14 @@ impl Guard for AnyGuard {
         }
         false
     }
+
+    fn methods(&self) -> Option<Vec<http::Method>> {
+        any_methods(&self.0)
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.0.iter().all(|g| g.is_method_only())
+    }
 }
 
 /// Return guard that matches if all of the supplied guards.
@@ -159,6 +209,14 @@ impl Guard for AllGuard {
         }
         true
     }
+
+    fn methods(&self) -> Option<Vec<http::Method>> {
+        all_methods(&self.0)
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.0.iter().all(|g| g.is_method_only())
+    }
 }
 
 /// Return guard that matches if supplied guard does not match.
@@ -183,6 +241,14 @@ impl Guard for MethodGuard {
     fn check(&self, request: &RequestHead) -> bool {
         request.method == self.0
     }
+
+    fn methods(&self) -> Option<Vec<http::Method>> {
+        Some(vec![self.0.clone()])
+    }
+
+    fn is_method_only(&self) -> bool {
+        true
+    }
 }
 
 /// Guard to match *GET* http method
@@ -328,6 +394,14 @@ impl Guard for Rc<Vec<Box<dyn Guard>>> {
         }
         false
     }
+
+    fn methods(&self) -> Option<Vec<http::Method>> {
+        any_methods(self.as_slice())
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.iter().all(|g| g.is_method_only())
+    }
 }
 
 #[cfg(test)]
@@ -479,4 +553,27 @@ mod tests {
         assert!(Any(Get()).or(Trace()).check(r.head()));
         assert!(!Any(Get()).or(Get()).check(r.head()));
     }
+
+    #[test]
+    fn test_guard_methods() {
+        assert_eq!(Get().methods(), Some(vec![Method::GET]));
+        assert_eq!(Header("content-type", "text/plain").methods(), None);
+        assert_eq!(Not(Get()).methods(), None);
+
+        assert_eq!(
+            Any(Get()).or(Post()).or(Get()).methods(),
+            Some(vec![Method::GET, Method::POST])
+        );
+        assert_eq!(Any(Get()).or(Header("a", "b")).methods(), None);
+
+        assert_eq!(
+            All(Any(Get()).or(Post())).and(Post()).methods(),
+            Some(vec![Method::POST])
+        );
+        assert_eq!(
+            All(Header("a", "b")).and(Put()).methods(),
+            Some(vec![Method::PUT])
+        );
+        assert_eq!(All(Get()).and(Post()).methods(), Some(vec![]));
+    }
 }
@@ -14,13 +14,15 @@ pub struct ConnectionInfo {
     host: String,
     remote: Option<String>,
     peer: Option<String>,
+    sni: Option<String>,
 }
 
 impl ConnectionInfo {
     /// Create *ConnectionInfo* instance for a request.
     pub fn get<'a>(req: &'a RequestHead, cfg: &AppConfig) -> Ref<'a, Self> {
         if !req.extensions().contains::<ConnectionInfo>() {
-            req.extensions_mut().insert(ConnectionInfo::new(req, cfg));
+            let info = ConnectionInfo::new(req, cfg);
+            req.extensions_mut().insert(info);
         }
         Ref::map(req.extensions(), |e| e.get().unwrap())
     }
@@ -122,8 +124,18 @@ impl ConnectionInfo {
             }
         }
 
+        // tls server name indication
+        #[cfg(any(feature = "ssl", feature = "rust-tls"))]
+        let sni = req
+            .extensions()
+            .get::<crate::tls::TlsInfo>()
+            .and_then(|info| info.sni.clone());
+        #[cfg(not(any(feature = "ssl", feature = "rust-tls")))]
+        let sni = None;
+
         ConnectionInfo {
             peer,
+            sni,
             scheme: scheme.unwrap_or("http").to_owned(),
             host: host.unwrap_or("localhost").to_owned(),
             remote: remote.map(|s| s.to_owned()),
@@ -172,6 +184,14 @@ impl ConnectionInfo {
             None
         }
     }
+
+    /// Hostname requested by the client via TLS server name indication.
+    ///
+    /// Available only for tls connections accepted by `HttpServer`.
+    #[inline]
+    pub fn sni_hostname(&self) -> Option<&str> {
+        self.sni.as_ref().map(|s| s.as_str())
+    }
 }
 
 #[cfg(test)]
@@ -225,5 +245,6 @@ mod tests {
             .to_http_request();
         let info = req.connection_info();
         assert_eq!(info.scheme(), "https");
+        assert_eq!(info.sni_hostname(), None);
     }
 }
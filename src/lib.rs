@@ -69,7 +69,8 @@
 //!
 //! * `client` - enables http client (default enabled)
 //! * `ssl` - enables ssl support via `openssl` crate, supports `http/2`
-//! * `rust-tls` - enables ssl support via `rustls` crate, supports `http/2`
+//! * `rust-tls` - enables ssl support via `rustls` crate, supports `http/2`,
+//!   includes SNI based certificate resolver with certificates reloading
 //! * `secure-cookies` - enables secure cookies support, includes `ring` crate as
 //!   dependency
 //! * `brotli` - enables `brotli` compression support, requires `c`
@@ -85,6 +86,7 @@
 mod app;
 mod app_service;
 mod config;
+mod conflict;
 mod data;
 pub mod error;
 mod extract;
@@ -92,16 +94,20 @@ pub mod guard;
 mod handler;
 mod info;
 pub mod middleware;
+pub mod openapi;
 mod request;
 mod resource;
 mod normalized_resource;
 mod responder;
 mod rmap;
 mod route;
+mod router;
 mod scope;
 mod server;
 mod service;
 pub mod test;
+#[cfg(any(feature = "ssl", feature = "rust-tls"))]
+pub mod tls;
 mod types;
 pub mod web;
 
@@ -141,7 +147,8 @@ pub mod dev {
     #[doc(hidden)]
     pub use crate::handler::{AsyncFactory, Factory};
     pub use crate::info::ConnectionInfo;
-    pub use crate::rmap::ResourceMap;
+    pub use crate::rmap::{ResourceEntry, ResourceKind, ResourceMap};
+    pub use crate::router::{Router, RouterBuilder};
     pub use crate::service::{
         HttpServiceFactory, ServiceRequest, ServiceResponse, WebService,
     };
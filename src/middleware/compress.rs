@@ -1,18 +1,36 @@
 //! `Middleware` for compressing response body.
-use std::cmp;
 use std::marker::PhantomData;
+use std::rc::Rc;
 use std::str::FromStr;
 
-use actix_http::body::MessageBody;
+use actix_http::body::{BodySize, MessageBody};
 use actix_http::encoding::Encoder;
-use actix_http::http::header::{ContentEncoding, ACCEPT_ENCODING};
-use actix_http::{Error, Response, ResponseBuilder};
+use actix_http::http::header::{
+    ContentEncoding, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, VARY,
+};
+use actix_http::{Error, Response, ResponseBuilder, ResponseHead};
 use actix_service::{Service, Transform};
 use futures::future::{ok, FutureResult};
 use futures::{Async, Future, Poll};
 
 use crate::service::{ServiceRequest, ServiceResponse};
 
+/// Content types that are already compressed
+const DEFAULT_DENY: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "video/*",
+    "audio/*",
+    "font/woff",
+    "font/woff2",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+];
+
 struct Enc(ContentEncoding);
 
 /// Helper trait that allows to set specific encoding for response.
@@ -40,6 +58,11 @@ impl<B> BodyEncoding for Response<B> {
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
+/// Responses with already compressed content types (png, jpeg, zip, etc)
+/// are sent uncompressed, `Vary: Accept-Encoding` header is added to
+/// responses that could be compressed. Use `Compress::builder()` for
+/// configuring compression levels, content types and minimum body size.
+///
 /// ```rust
 /// use actix_web::{web, middleware, App, HttpResponse};
 ///
@@ -53,12 +76,55 @@ impl<B> BodyEncoding for Response<B> {
 ///         );
 /// }
 /// ```
-pub struct Compress(ContentEncoding);
+pub struct Compress(Rc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    encoding: ContentEncoding,
+    levels: Vec<(ContentEncoding, u32)>,
+    min_size: u64,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    preference: Vec<ContentEncoding>,
+}
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress(encoding)
+        Compress::builder().encoding(encoding).finish()
+    }
+
+    /// Create `Compress` middleware builder.
+    ///
+    /// ```rust
+    /// use actix_web::{middleware, App};
+    /// use actix_web::http::ContentEncoding;
+    ///
+    /// fn main() {
+    ///     let app = App::new().wrap(
+    ///         middleware::Compress::builder()
+    ///             .level(ContentEncoding::Gzip, 6)
+    ///             .min_size(1024)
+    ///             .deny_content_type("application/pdf")
+    ///             .preference(&[ContentEncoding::Gzip, ContentEncoding::Br])
+    ///             .finish(),
+    ///     );
+    /// }
+    /// ```
+    pub fn builder() -> CompressBuilder {
+        CompressBuilder(Inner {
+            encoding: ContentEncoding::Auto,
+            levels: Vec::new(),
+            min_size: 0,
+            allow: Vec::new(),
+            deny: DEFAULT_DENY.iter().map(|s| s.to_string()).collect(),
+            preference: vec![
+                ContentEncoding::Br,
+                ContentEncoding::Zstd,
+                ContentEncoding::Gzip,
+                ContentEncoding::Deflate,
+            ],
+        })
     }
 }
 
@@ -68,6 +134,75 @@ impl Default for Compress {
     }
 }
 
+/// `Compress` middleware builder
+pub struct CompressBuilder(Inner);
+
+impl CompressBuilder {
+    /// Set response encoding.
+    ///
+    /// By default encoding is negotiated with `Accept-Encoding` header
+    /// (`ContentEncoding::Auto`).
+    pub fn encoding(mut self, encoding: ContentEncoding) -> Self {
+        self.0.encoding = encoding;
+        self
+    }
+
+    /// Set compression level for encoding algorithm.
+    ///
+    /// Level is clamped to the range supported by algorithm: 0-9 for gzip
+    /// and deflate, 0-11 for brotli and 1-21 for zstd.
+    pub fn level(mut self, encoding: ContentEncoding, level: u32) -> Self {
+        self.0.levels.retain(|(enc, _)| *enc != encoding);
+        self.0.levels.push((encoding, level));
+        self
+    }
+
+    /// Set minimum body size for compression.
+    ///
+    /// Responses with known body size smaller than `size` are sent
+    /// uncompressed. Streaming responses are always compressed.
+    /// By default minimum size is 0.
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.0.min_size = size;
+        self
+    }
+
+    /// Compress only responses with this content type.
+    ///
+    /// Content type could be exact, i.e. `text/html`, or a wildcard,
+    /// i.e. `text/*`. If allowed content types are set, responses with other
+    /// content types are sent uncompressed. Exact allowed content type takes
+    /// precedence over denied content types.
+    pub fn allow_content_type(mut self, content_type: &str) -> Self {
+        self.0.allow.push(content_type.to_lowercase());
+        self
+    }
+
+    /// Do not compress responses with this content type.
+    ///
+    /// Content type could be exact, i.e. `image/png`, or a wildcard,
+    /// i.e. `video/*`. By default already compressed formats are denied.
+    pub fn deny_content_type(mut self, content_type: &str) -> Self {
+        self.0.deny.push(content_type.to_lowercase());
+        self
+    }
+
+    /// Set server preference order of encodings.
+    ///
+    /// Preference order is used if client accepts several encodings with
+    /// the same quality value. Encodings not present in the list are not
+    /// used for negotiation. By default order is br, zstd, gzip, deflate.
+    pub fn preference(mut self, encodings: &[ContentEncoding]) -> Self {
+        self.0.preference = encodings.to_vec();
+        self
+    }
+
+    /// Finish configuration and create `Compress` middleware.
+    pub fn finish(self) -> Compress {
+        Compress(Rc::new(self.0))
+    }
+}
+
 impl<S, B> Transform<S> for Compress
 where
     B: MessageBody,
@@ -83,14 +218,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(CompressMiddleware {
             service,
-            encoding: self.0,
+            inner: self.0.clone(),
         })
     }
 }
 
 pub struct CompressMiddleware<S> {
     service: S,
-    encoding: ContentEncoding,
+    inner: Rc<Inner>,
 }
 
 impl<S, B> Service for CompressMiddleware<S>
@@ -111,7 +246,7 @@ where
         // negotiate content-encoding
         let encoding = if let Some(val) = req.headers().get(&ACCEPT_ENCODING) {
             if let Ok(enc) = val.to_str() {
-                AcceptEncoding::parse(enc, self.encoding)
+                self.inner.negotiate(enc)
             } else {
                 ContentEncoding::Identity
             }
@@ -121,6 +256,7 @@ where
 
         CompressResponse {
             encoding,
+            inner: self.inner.clone(),
             fut: self.service.call(req),
             _t: PhantomData,
         }
@@ -135,6 +271,7 @@ where
 {
     fut: S::Future,
     encoding: ContentEncoding,
+    inner: Rc<Inner>,
     _t: PhantomData<(B)>,
 }
 
@@ -149,84 +286,269 @@ where
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         let resp = futures::try_ready!(self.fut.poll());
 
-        let enc = if let Some(enc) = resp.response().extensions().get::<Enc>() {
-            enc.0
-        } else {
-            self.encoding
-        };
+        let enc = resp.response().extensions().get::<Enc>().map(|enc| enc.0);
+        let encoding = self.encoding;
+        let inner = self.inner.clone();
 
         Ok(Async::Ready(resp.map_body(move |head, body| {
-            Encoder::response(enc, head, body)
+            let enc = if let Some(enc) = enc {
+                enc
+            } else if inner.is_compressible(head, body.size()) {
+                add_vary(head);
+                encoding
+            } else {
+                ContentEncoding::Identity
+            };
+            Encoder::response_with_level(enc, inner.level(enc), head, body)
         })))
     }
 }
 
-struct AcceptEncoding {
-    encoding: ContentEncoding,
-    quality: f64,
-}
+impl Inner {
+    /// Select response encoding for raw `Accept-Encoding` header value.
+    fn negotiate(&self, raw: &str) -> ContentEncoding {
+        if self.encoding != ContentEncoding::Auto {
+            return if quality(raw, self.encoding) > 0.0 {
+                self.encoding
+            } else {
+                ContentEncoding::Identity
+            };
+        }
 
-impl Eq for AcceptEncoding {}
+        let mut selected = (ContentEncoding::Identity, 0.0);
+        for enc in &self.preference {
+            if is_supported(*enc) {
+                let q = quality(raw, *enc);
+                if q > selected.1 {
+                    selected = (*enc, q);
+                }
+            }
+        }
+        selected.0
+    }
+
+    fn level(&self, encoding: ContentEncoding) -> Option<u32> {
+        self.levels
+            .iter()
+            .find(|(enc, _)| *enc == encoding)
+            .map(|(_, level)| *level)
+    }
 
-impl Ord for AcceptEncoding {
-    fn cmp(&self, other: &AcceptEncoding) -> cmp::Ordering {
-        if self.quality > other.quality {
-            cmp::Ordering::Less
-        } else if self.quality < other.quality {
-            cmp::Ordering::Greater
+    /// Check if response could be compressed, encoder itself also skips
+    /// `101` and `204` responses.
+    fn is_compressible(&self, head: &ResponseHead, size: BodySize) -> bool {
+        if head.headers().contains_key(&CONTENT_ENCODING) {
+            return false;
+        }
+        match size {
+            BodySize::None | BodySize::Empty => return false,
+            BodySize::Sized(size) if (size as u64) < self.min_size => return false,
+            BodySize::Sized64(size) if size < self.min_size => return false,
+            _ => (),
+        }
+
+        let ct = match head.headers().get(&CONTENT_TYPE).map(|ct| ct.to_str()) {
+            Some(Ok(ct)) => ct.split(';').next().unwrap_or("").trim().to_lowercase(),
+            Some(Err(_)) => return false,
+            None => return true,
+        };
+        if self.allow.iter().any(|allow| *allow == ct) {
+            true
+        } else if self.deny.iter().any(|deny| matches_content_type(deny, &ct)) {
+            false
         } else {
-            cmp::Ordering::Equal
+            self.allow.is_empty()
+                || self
+                    .allow
+                    .iter()
+                    .any(|allow| matches_content_type(allow, &ct))
         }
     }
 }
 
-impl PartialOrd for AcceptEncoding {
-    fn partial_cmp(&self, other: &AcceptEncoding) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
+fn matches_content_type(pattern: &str, ct: &str) -> bool {
+    if pattern.ends_with("/*") {
+        ct.starts_with(&pattern[..pattern.len() - 1])
+    } else {
+        pattern == ct
     }
 }
 
-impl PartialEq for AcceptEncoding {
-    fn eq(&self, other: &AcceptEncoding) -> bool {
-        self.quality == other.quality
+/// Add `Vary: Accept-Encoding` header, if it is not present yet
+fn add_vary(head: &mut ResponseHead) {
+    let present = head.headers().get_all(&VARY).any(|val| {
+        val.to_str()
+            .map(|s| {
+                s.split(',').any(|v| {
+                    let v = v.trim();
+                    v == "*" || v.eq_ignore_ascii_case("accept-encoding")
+                })
+            })
+            .unwrap_or(false)
+    });
+    if !present {
+        head.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
     }
 }
 
-impl AcceptEncoding {
-    fn new(tag: &str) -> Option<AcceptEncoding> {
-        let parts: Vec<&str> = tag.split(';').collect();
-        let encoding = match parts.len() {
-            0 => return None,
-            _ => ContentEncoding::from(parts[0]),
-        };
-        let quality = match parts.len() {
-            1 => encoding.quality(),
-            _ => match f64::from_str(parts[1]) {
-                Ok(q) => q,
-                Err(_) => 0.0,
-            },
-        };
-        Some(AcceptEncoding { encoding, quality })
-    }
-
-    /// Parse a raw Accept-Encoding header value into an ordered list.
-    pub fn parse(raw: &str, encoding: ContentEncoding) -> ContentEncoding {
-        let mut encodings: Vec<_> = raw
-            .replace(' ', "")
-            .split(',')
-            .map(|l| AcceptEncoding::new(l))
-            .collect();
-        encodings.sort();
-
-        for enc in encodings {
-            if let Some(enc) = enc {
-                if encoding == ContentEncoding::Auto {
-                    return enc.encoding;
-                } else if encoding == enc.encoding {
-                    return encoding;
-                }
-            }
+/// Quality value of encoding in raw `Accept-Encoding` header value,
+/// `0.0` if encoding is not acceptable.
+fn quality(raw: &str, encoding: ContentEncoding) -> f64 {
+    let mut wildcard = None;
+
+    for item in raw.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let q = parts
+            .map(|param| param.trim())
+            .find(|param| param.starts_with("q=") || param.starts_with("Q="))
+            .map(|param| f64::from_str(&param[2..]).unwrap_or(0.0))
+            .unwrap_or(1.0);
+
+        if name == "*" {
+            wildcard = Some(q);
+        } else if name.eq_ignore_ascii_case(encoding.as_str()) {
+            return q;
+        }
+    }
+    wildcard.unwrap_or(0.0)
+}
+
+/// Check if encoding is enabled with cargo features
+fn is_supported(encoding: ContentEncoding) -> bool {
+    match encoding {
+        ContentEncoding::Br => cfg!(feature = "brotli"),
+        ContentEncoding::Zstd => cfg!(feature = "zstd"),
+        ContentEncoding::Gzip | ContentEncoding::Deflate => {
+            cfg!(any(feature = "flate2-zlib", feature = "flate2-rust"))
         }
-        ContentEncoding::Identity
+        ContentEncoding::Identity | ContentEncoding::Auto => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::test::{block_on, TestRequest};
+    use crate::HttpResponse;
+
+    #[test]
+    fn test_quality() {
+        assert_eq!(quality("gzip, br", ContentEncoding::Br), 1.0);
+        assert_eq!(quality("gzip;q=0.5, br", ContentEncoding::Gzip), 0.5);
+        assert_eq!(quality("gzip; q=0, br", ContentEncoding::Gzip), 0.0);
+        assert_eq!(quality("gzip", ContentEncoding::Br), 0.0);
+        assert_eq!(quality("gzip, *;q=0.3", ContentEncoding::Br), 0.3);
+        assert_eq!(quality("GZIP;Q=0.8", ContentEncoding::Gzip), 0.8);
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "brotli",
+        any(feature = "flate2-zlib", feature = "flate2-rust")
+    ))]
+    fn test_negotiate() {
+        let compress = Compress::default();
+        assert_eq!(compress.0.negotiate("gzip, br"), ContentEncoding::Br);
+        assert_eq!(
+            compress.0.negotiate("gzip, br;q=0.5"),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(
+            compress.0.negotiate("br;q=0, gzip;q=0.5, *;q=0.2"),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(compress.0.negotiate("identity"), ContentEncoding::Identity);
+
+        let compress = Compress::builder()
+            .preference(&[ContentEncoding::Deflate, ContentEncoding::Gzip])
+            .finish();
+        assert_eq!(
+            compress.0.negotiate("gzip, br, deflate"),
+            ContentEncoding::Deflate
+        );
+        assert_eq!(compress.0.negotiate("br"), ContentEncoding::Identity);
+
+        let compress = Compress::new(ContentEncoding::Gzip);
+        assert_eq!(compress.0.negotiate("br, gzip"), ContentEncoding::Gzip);
+        assert_eq!(compress.0.negotiate("br"), ContentEncoding::Identity);
+    }
+
+    #[test]
+    fn test_content_type() {
+        let compress = Compress::builder()
+            .deny_content_type("text/*")
+            .allow_content_type("text/html")
+            .finish();
+        let size = BodySize::Stream;
+
+        let resp = HttpResponse::Ok().content_type("text/plain").finish();
+        assert!(!compress.0.is_compressible(resp.head(), size));
+        let resp = HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .finish();
+        assert!(compress.0.is_compressible(resp.head(), size));
+        let resp = HttpResponse::Ok().content_type("image/png").finish();
+        assert!(!compress.0.is_compressible(resp.head(), size));
+        let resp = HttpResponse::Ok().content_type("video/mp4").finish();
+        assert!(!compress.0.is_compressible(resp.head(), size));
+        let resp = HttpResponse::Ok().content_type("image/svg+xml").finish();
+        assert!(!compress.0.is_compressible(resp.head(), size));
+
+        let compress = Compress::builder().allow_content_type("text/*").finish();
+        let resp = HttpResponse::Ok().content_type("text/css").finish();
+        assert!(compress.0.is_compressible(resp.head(), size));
+        let resp = HttpResponse::Ok().content_type("application/json").finish();
+        assert!(!compress.0.is_compressible(resp.head(), size));
+    }
+
+    #[test]
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    fn test_min_size_and_vary() {
+        let srv = |req: ServiceRequest| {
+            let body = if req.path() == "/small" {
+                "small"
+            } else {
+                "large body"
+            };
+            req.into_response(
+                HttpResponse::Ok()
+                    .header(VARY, "origin")
+                    .content_type("text/plain")
+                    .body(body),
+            )
+        };
+        let mut mw = block_on(
+            Compress::builder()
+                .min_size(10)
+                .finish()
+                .new_transform(srv.into_service()),
+        )
+        .unwrap();
+
+        let req = TestRequest::with_uri("/small")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_srv_request();
+        let resp = block_on(mw.call(req)).unwrap();
+        assert!(!resp.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(resp.headers().get_all(VARY).count(), 1);
+
+        let req = TestRequest::with_uri("/large")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_srv_request();
+        let resp = block_on(mw.call(req)).unwrap();
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let mut vary: Vec<_> = resp.headers().get_all(VARY).collect();
+        vary.sort();
+        assert_eq!(vary, vec!["accept-encoding", "origin"]);
+
+        // response varies by accept-encoding even if it is not compressed
+        let req = TestRequest::with_uri("/large").to_srv_request();
+        let resp = block_on(mw.call(req)).unwrap();
+        assert!(!resp.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(resp.headers().get_all(VARY).count(), 2);
     }
 }
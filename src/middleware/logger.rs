@@ -15,7 +15,7 @@ use time;
 
 use crate::dev::{BodySize, MessageBody, ResponseBody};
 use crate::error::{Error, Result};
-use crate::http::{HeaderName, HttpTryFrom, StatusCode};
+use crate::http::{HeaderMap, HeaderName, HttpTryFrom, StatusCode};
 use crate::service::{ServiceRequest, ServiceResponse};
 use crate::HttpResponse;
 
@@ -261,6 +261,10 @@ impl<B: MessageBody> MessageBody for StreamLog<B> {
             val => Ok(val),
         }
     }
+
+    fn trailers(&mut self) -> Option<HeaderMap> {
+        self.body.trailers()
+    }
 }
 
 /// A formatting style for the `Logger`, consisting of multiple
@@ -1,6 +1,6 @@
 //! Middlewares
 mod compress;
-pub use self::compress::{BodyEncoding, Compress};
+pub use self::compress::{BodyEncoding, Compress, CompressBuilder};
 
 mod defaultheaders;
 pub mod errhandlers;
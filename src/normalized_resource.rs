@@ -20,6 +20,7 @@ use crate::responder::Responder;
 use crate::route::{Route};
 use crate::service::{ServiceRequest, ServiceResponse};
 use crate::resource::{
+    resource_methods,
     CreateResourceService,
     HttpNewService,
     ResourceService,
@@ -377,6 +378,7 @@ where
         > + 'static,
 {
     fn register(mut self, config: &mut AppService) {
+        let methods = resource_methods(&self.routes, &self.guards);
         let guards_are_empty = self.guards.is_empty();
         let guards = if guards_are_empty {
             None
@@ -417,8 +419,8 @@ where
          };
 
         let service_rc = Rc::new(self.into_new_service());
-        config.register_service(rdef, guards1, service_rc.clone(), None);
-        config.register_service(secondary_rdef, guards2, service_rc.clone(), None);
+        config.register_resource(rdef, guards1, service_rc.clone(), methods.clone());
+        config.register_resource(secondary_rdef, guards2, service_rc.clone(), methods);
     }
 }
 
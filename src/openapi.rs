@@ -0,0 +1,815 @@
+//! OpenAPI 3 document generation
+//!
+//! Document is derived from application's route table. Every resource
+//! with method guards is described with its path parameters. Routes
+//! registered with `Route::api_to()` are described by handler's extractors
+//! and responder, `Route::describe()` adds summary, tags, etc.
+//!
+//! Types used by `Json<T>`, `Query<T>`, `Form<T>` and `Path<T>` must
+//! implement `Schema` trait.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix_web::openapi::{OpenApi, Schema};
+//! use actix_web::{web, App};
+//! use serde_derive::Serialize;
+//! use serde_json::{json, Value};
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! impl Schema for User {
+//!     fn schema() -> Value {
+//!         json!({
+//!             "type": "object",
+//!             "properties": {"name": String::schema()},
+//!             "required": ["name"],
+//!         })
+//!     }
+//! }
+//!
+//! fn user(path: web::Path<(u32,)>) -> web::Json<User> {
+//!     web::Json(User { name: format!("user{}", path.0) })
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .service(web::resource("/users/{id}").route(
+//!             web::get()
+//!                 .api_to(user)
+//!                 .describe(|op| {
+//!                     op.summary("Get user").tag("users");
+//!                 }),
+//!         ))
+//!         .service(OpenApi::new("Users", "1.0").path("/openapi.json"));
+//! }
+//! ```
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use actix_http::http::{Method, StatusCode};
+use actix_http::Response;
+use bytes::Bytes;
+use serde_json::{json, Map, Value};
+
+use crate::data::Data;
+use crate::dev::{AppService, HttpServiceFactory, ResourceKind, ResourceMap};
+use crate::request::HttpRequest;
+use crate::responder::Either;
+use crate::service::ServiceRequest;
+use crate::types::{Form, Json, Path, Query};
+use crate::web;
+
+/// Json schema of a type, used in OpenAPI document
+pub trait Schema {
+    /// Schema object
+    fn schema() -> Value;
+}
+
+macro_rules! schema_impl {
+    ($tp:expr => $($t:ty),+) => {$(
+        impl Schema for $t {
+            fn schema() -> Value {
+                json!({ "type": $tp })
+            }
+        }
+    )+};
+    ($tp:expr, $fmt:expr => $($t:ty),+) => {$(
+        impl Schema for $t {
+            fn schema() -> Value {
+                json!({ "type": $tp, "format": $fmt })
+            }
+        }
+    )+};
+}
+
+schema_impl!("boolean" => bool);
+schema_impl!("string" => String, char);
+schema_impl!("integer", "int32" => i8, i16, i32, u8, u16, u32);
+schema_impl!("integer", "int64" => i64, u64, isize, usize);
+schema_impl!("number", "float" => f32);
+schema_impl!("number", "double" => f64);
+
+impl<'a> Schema for &'a str {
+    fn schema() -> Value {
+        String::schema()
+    }
+}
+
+impl<T: Schema> Schema for Option<T> {
+    fn schema() -> Value {
+        let mut schema = T::schema();
+        if let Some(obj) = schema.as_object_mut() {
+            obj.insert("nullable".to_string(), Value::Bool(true));
+        }
+        schema
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: Schema> Schema for HashMap<String, T> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+impl<T: Schema> Schema for BTreeMap<String, T> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+/// Tuples are described as fixed size arrays, schemas of elements are
+/// listed in `anyOf` in order of elements.
+macro_rules! tuple_schema_impl {
+    ($n:expr, $($T:ident),+) => {
+        impl<$($T: Schema),+> Schema for ($($T,)+) {
+            fn schema() -> Value {
+                json!({
+                    "type": "array",
+                    "items": { "anyOf": [$($T::schema()),+] },
+                    "minItems": $n,
+                    "maxItems": $n,
+                })
+            }
+        }
+    };
+}
+
+tuple_schema_impl!(1, A);
+tuple_schema_impl!(2, A, B);
+tuple_schema_impl!(3, A, B, C);
+tuple_schema_impl!(4, A, B, C, D);
+tuple_schema_impl!(5, A, B, C, D, E);
+
+/// Extractor or responder that contributes to api operation description
+///
+/// Extractors describe request parameters and request body, responders
+/// describe responses.
+pub trait ApiComponent {
+    /// Describe request parameters or request body
+    fn describe_request(_: &mut Operation) {}
+
+    /// Describe responses
+    fn describe_response(_: &mut Operation) {}
+}
+
+impl<T: Schema> ApiComponent for Json<T> {
+    fn describe_request(op: &mut Operation) {
+        op.request_body("application/json", T::schema());
+    }
+
+    fn describe_response(op: &mut Operation) {
+        op.response_content(StatusCode::OK, "", "application/json", T::schema());
+    }
+}
+
+impl<T: Schema> ApiComponent for Form<T> {
+    fn describe_request(op: &mut Operation) {
+        op.request_body("application/x-www-form-urlencoded", T::schema());
+    }
+
+    fn describe_response(op: &mut Operation) {
+        op.response_content(
+            StatusCode::OK,
+            "",
+            "application/x-www-form-urlencoded",
+            T::schema(),
+        );
+    }
+}
+
+impl<T: Schema> ApiComponent for Query<T> {
+    fn describe_request(op: &mut Operation) {
+        let schema = T::schema();
+        let required = schema["required"].as_array().cloned().unwrap_or_default();
+        if let Some(props) = schema["properties"].as_object() {
+            for (name, prop) in props {
+                let is_required = required.iter().any(|r| r == name);
+                op.parameter(name, "query", is_required, prop.clone());
+            }
+        }
+    }
+}
+
+impl<T: Schema> ApiComponent for Path<T> {
+    fn describe_request(op: &mut Operation) {
+        op.path_schema(T::schema());
+    }
+}
+
+impl ApiComponent for String {
+    fn describe_request(op: &mut Operation) {
+        op.request_body("text/plain", String::schema());
+    }
+
+    fn describe_response(op: &mut Operation) {
+        op.response_content(StatusCode::OK, "", "text/plain", String::schema());
+    }
+}
+
+impl ApiComponent for &'static str {
+    fn describe_response(op: &mut Operation) {
+        String::describe_response(op)
+    }
+}
+
+impl ApiComponent for Bytes {
+    fn describe_request(op: &mut Operation) {
+        op.request_body(
+            "application/octet-stream",
+            json!({ "type": "string", "format": "binary" }),
+        );
+    }
+
+    fn describe_response(op: &mut Operation) {
+        op.response_content(
+            StatusCode::OK,
+            "",
+            "application/octet-stream",
+            json!({ "type": "string", "format": "binary" }),
+        );
+    }
+}
+
+impl<T: ApiComponent> ApiComponent for Option<T> {
+    fn describe_request(op: &mut Operation) {
+        T::describe_request(op)
+    }
+
+    fn describe_response(op: &mut Operation) {
+        T::describe_response(op)
+    }
+}
+
+impl<T: ApiComponent, E> ApiComponent for Result<T, E> {
+    fn describe_request(op: &mut Operation) {
+        T::describe_request(op)
+    }
+
+    fn describe_response(op: &mut Operation) {
+        T::describe_response(op)
+    }
+}
+
+impl<A: ApiComponent, B: ApiComponent> ApiComponent for Either<A, B> {
+    fn describe_response(op: &mut Operation) {
+        A::describe_response(op);
+        B::describe_response(op);
+    }
+}
+
+impl<T> ApiComponent for Data<T> {}
+
+impl ApiComponent for HttpRequest {}
+
+impl ApiComponent for Response {}
+
+impl ApiComponent for StatusCode {}
+
+impl ApiComponent for () {}
+
+macro_rules! tuple_component_impl {
+    ($($T:ident),+) => {
+        impl<$($T: ApiComponent),+> ApiComponent for ($($T,)+) {
+            fn describe_request(op: &mut Operation) {
+                $($T::describe_request(op);)+
+            }
+
+            fn describe_response(op: &mut Operation) {
+                $($T::describe_response(op);)+
+            }
+        }
+    };
+}
+
+tuple_component_impl!(A);
+tuple_component_impl!(A, B);
+tuple_component_impl!(A, B, C);
+tuple_component_impl!(A, B, C, D);
+tuple_component_impl!(A, B, C, D, E);
+tuple_component_impl!(A, B, C, D, E, F);
+tuple_component_impl!(A, B, C, D, E, F, G);
+tuple_component_impl!(A, B, C, D, E, F, G, H);
+tuple_component_impl!(A, B, C, D, E, F, G, H, I);
+tuple_component_impl!(A, B, C, D, E, F, G, H, I, J);
+
+/// Api operation description
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    operation_id: Option<String>,
+    tags: Vec<String>,
+    deprecated: bool,
+    path: Option<Value>,
+    parameters: Vec<Value>,
+    request_body: Option<(String, Value)>,
+    responses: BTreeMap<String, Value>,
+}
+
+impl Operation {
+    /// Set operation summary
+    pub fn summary(&mut self, summary: &str) -> &mut Self {
+        self.summary = Some(summary.to_string());
+        self
+    }
+
+    /// Set operation description
+    pub fn description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set unique operation id
+    pub fn operation_id(&mut self, id: &str) -> &mut Self {
+        self.operation_id = Some(id.to_string());
+        self
+    }
+
+    /// Add operation tag
+    pub fn tag(&mut self, tag: &str) -> &mut Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Mark operation as deprecated
+    pub fn deprecated(&mut self) -> &mut Self {
+        self.deprecated = true;
+        self
+    }
+
+    /// Set schema of path parameters.
+    ///
+    /// Object schema describes parameters by name, array schema describes
+    /// parameters by position. Parameters without schema are strings.
+    pub fn path_schema(&mut self, schema: Value) -> &mut Self {
+        self.path = Some(schema);
+        self
+    }
+
+    /// Add request parameter, location is `query`, `header` or `cookie`
+    pub fn parameter(
+        &mut self,
+        name: &str,
+        location: &str,
+        required: bool,
+        schema: Value,
+    ) -> &mut Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": location,
+            "required": required,
+            "schema": schema,
+        }));
+        self
+    }
+
+    /// Set request body
+    pub fn request_body(&mut self, content_type: &str, schema: Value) -> &mut Self {
+        self.request_body = Some((content_type.to_string(), schema));
+        self
+    }
+
+    /// Add response without content
+    pub fn response(&mut self, status: StatusCode, description: &str) -> &mut Self {
+        self.responses.insert(
+            status.as_str().to_string(),
+            response_value(status, description),
+        );
+        self
+    }
+
+    /// Add response content.
+    ///
+    /// Response could have content of multiple content types. If description
+    /// is empty, canonical reason of status code is used.
+    pub fn response_content(
+        &mut self,
+        status: StatusCode,
+        description: &str,
+        content_type: &str,
+        schema: Value,
+    ) -> &mut Self {
+        let response = self
+            .responses
+            .entry(status.as_str().to_string())
+            .or_insert_with(|| response_value(status, description));
+        response["content"][content_type] = json!({ "schema": schema });
+        self
+    }
+
+    /// Operation object of OpenAPI document, `params` are names of
+    /// path parameters
+    fn to_value(&self, params: &[String]) -> Value {
+        let mut op = Map::new();
+        if let Some(ref summary) = self.summary {
+            op.insert("summary".to_string(), json!(summary));
+        }
+        if let Some(ref description) = self.description {
+            op.insert("description".to_string(), json!(description));
+        }
+        if let Some(ref id) = self.operation_id {
+            op.insert("operationId".to_string(), json!(id));
+        }
+        if !self.tags.is_empty() {
+            op.insert("tags".to_string(), json!(self.tags));
+        }
+        if self.deprecated {
+            op.insert("deprecated".to_string(), json!(true));
+        }
+
+        let mut parameters: Vec<_> = params
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": self.path_param_schema(name, idx),
+                })
+            })
+            .collect();
+        parameters.extend(self.parameters.iter().cloned());
+        if !parameters.is_empty() {
+            op.insert("parameters".to_string(), Value::Array(parameters));
+        }
+
+        if let Some((ref content_type, ref schema)) = self.request_body {
+            op.insert(
+                "requestBody".to_string(),
+                json!({
+                    "required": true,
+                    "content": { content_type.as_str(): { "schema": schema } },
+                }),
+            );
+        }
+
+        let responses = if self.responses.is_empty() {
+            json!({ "default": { "description": "Default response" } })
+        } else {
+            json!(self.responses)
+        };
+        op.insert("responses".to_string(), responses);
+        Value::Object(op)
+    }
+
+    fn path_param_schema(&self, name: &str, idx: usize) -> Value {
+        let schema = self.path.as_ref().and_then(|path| {
+            if path["type"] == "array" {
+                path["items"]["anyOf"].get(idx).cloned()
+            } else {
+                path["properties"].get(name).cloned()
+            }
+        });
+        schema.unwrap_or_else(String::schema)
+    }
+}
+
+fn response_value(status: StatusCode, description: &str) -> Value {
+    let description = if description.is_empty() {
+        status.canonical_reason().unwrap_or("")
+    } else {
+        description
+    };
+    json!({ "description": description })
+}
+
+/// Convert resource pattern to OpenAPI path template, returns template
+/// and names of path parameters.
+///
+/// Custom regex of dynamic segment is dropped, `/{id:\d+}` becomes `/{id}`.
+fn path_template(pattern: &str) -> (String, Vec<String>) {
+    let mut path = String::new();
+    let mut params = Vec::new();
+    let mut chars = pattern.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '{' {
+            path.push(ch);
+            continue;
+        }
+        let mut name = String::new();
+        let mut in_regex = false;
+        let mut depth = 1;
+        for ch in &mut chars {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                ':' if depth == 1 => in_regex = true,
+                _ => (),
+            }
+            if !in_regex {
+                name.push(ch);
+            }
+        }
+        path.push('{');
+        path.push_str(&name);
+        path.push('}');
+        params.push(name);
+    }
+    if !path.starts_with('/') {
+        path.insert(0, '/');
+    }
+    (path, params)
+}
+
+/// OpenAPI document service
+///
+/// Service describes all resources of the application, regardless of
+/// scope it is registered in. Document is generated on first request.
+pub struct OpenApi {
+    path: String,
+    info: Map<String, Value>,
+    servers: Vec<Value>,
+}
+
+impl OpenApi {
+    /// Create document service with api title and version.
+    ///
+    /// By default document is served at `/openapi.json`.
+    pub fn new(title: &str, version: &str) -> Self {
+        let mut info = Map::new();
+        info.insert("title".to_string(), json!(title));
+        info.insert("version".to_string(), json!(version));
+        OpenApi {
+            info,
+            path: "/openapi.json".to_string(),
+            servers: Vec::new(),
+        }
+    }
+
+    /// Set path of document endpoint
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Set api description
+    pub fn description(mut self, description: &str) -> Self {
+        self.info
+            .insert("description".to_string(), json!(description));
+        self
+    }
+
+    /// Add server url
+    pub fn server(mut self, url: &str) -> Self {
+        self.servers.push(json!({ "url": url }));
+        self
+    }
+
+    /// Generate OpenAPI document for resources of the resource map.
+    ///
+    /// Only resources restricted to specific methods are described.
+    pub fn document(&self, rmap: &ResourceMap) -> Value {
+        let mut paths = Map::new();
+
+        for entry in rmap.resources() {
+            if entry.kind() != ResourceKind::Resource {
+                continue;
+            }
+            let methods = match entry.methods() {
+                Some(methods) => methods,
+                None => continue,
+            };
+            let (path, params) = path_template(entry.pattern());
+
+            let mut item = Map::new();
+            for method in methods.iter().filter(|m| is_api_method(m)) {
+                let op = entry
+                    .operations()
+                    .iter()
+                    .find(|(m, _)| m == method)
+                    .map(|(_, op)| op.to_value(&params))
+                    .unwrap_or_else(|| Operation::default().to_value(&params));
+                item.insert(method.as_str().to_lowercase(), op);
+            }
+            if item.is_empty() {
+                continue;
+            }
+
+            if let Some(Value::Object(existing)) = paths.get_mut(&path) {
+                for (method, op) in item {
+                    existing.entry(method).or_insert(op);
+                }
+            } else {
+                paths.insert(path, Value::Object(item));
+            }
+        }
+
+        let mut doc = json!({
+            "openapi": "3.0.2",
+            "info": self.info,
+            "paths": paths,
+        });
+        if !self.servers.is_empty() {
+            doc["servers"] = json!(self.servers);
+        }
+        doc
+    }
+}
+
+/// Methods supported by OpenAPI path item object
+fn is_api_method(method: &Method) -> bool {
+    match *method {
+        Method::GET
+        | Method::PUT
+        | Method::POST
+        | Method::DELETE
+        | Method::OPTIONS
+        | Method::HEAD
+        | Method::PATCH
+        | Method::TRACE => true,
+        _ => false,
+    }
+}
+
+impl HttpServiceFactory for OpenApi {
+    fn register(self, config: &mut AppService) {
+        let path = self.path.clone();
+        let api = Rc::new(self);
+        let cache: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+        web::service(&path)
+            .finish(move |req: ServiceRequest| {
+                let body = cache
+                    .borrow_mut()
+                    .get_or_insert_with(|| api.document(req.resource_map()).to_string())
+                    .clone();
+                req.into_response(
+                    Response::Ok().content_type("application/json").body(body),
+                )
+            })
+            .register(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::http::header;
+    use crate::test::{call_service, init_service, read_body, TestRequest};
+    use crate::{guard, App, HttpResponse};
+
+    #[derive(Serialize, Deserialize)]
+    struct Info {
+        name: String,
+    }
+
+    impl Schema for Info {
+        fn schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": { "name": String::schema() },
+                "required": ["name"],
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Filter {
+        #[allow(dead_code)]
+        limit: Option<u32>,
+    }
+
+    impl Schema for Filter {
+        fn schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": { "limit": u32::schema() },
+            })
+        }
+    }
+
+    fn get_user(_: Path<(u64,)>, _: Query<Filter>) -> Json<Info> {
+        Json(Info {
+            name: "test".to_string(),
+        })
+    }
+
+    fn create_user(user: Json<Info>) -> String {
+        user.name.clone()
+    }
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(path_template(""), ("/".to_string(), vec![]));
+        assert_eq!(
+            path_template(r"/users/{id:\d{1,3}}/{tail:.*}"),
+            (
+                "/users/{id}/{tail}".to_string(),
+                vec!["id".to_string(), "tail".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_openapi() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .service(web::resource("/users/{id}").route(
+                            web::get().api_to(get_user).describe(|op| {
+                                op.summary("Get user")
+                                    .tag("users")
+                                    .response(StatusCode::NOT_FOUND, "No user");
+                            }),
+                        ))
+                        .route("/users", web::post().api_to(create_user))
+                        .route("/status", web::get().to(|| HttpResponse::Ok())),
+                )
+                .service(web::resource("/any").to(|| HttpResponse::Ok()))
+                .service(
+                    web::service("/custom")
+                        .guard(guard::Method(Method::from_bytes(b"PURGE").unwrap()))
+                        .finish(|req: ServiceRequest| {
+                            req.into_response(HttpResponse::Ok())
+                        }),
+                )
+                .service(
+                    OpenApi::new("Test", "1.0")
+                        .path("/docs/openapi.json")
+                        .server("http://localhost:8080"),
+                ),
+        );
+
+        let req = TestRequest::with_uri("/docs/openapi.json").to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let doc: Value = serde_json::from_slice(&read_body(resp)).unwrap();
+
+        assert_eq!(doc["openapi"], "3.0.2");
+        assert_eq!(doc["info"], json!({ "title": "Test", "version": "1.0" }));
+        assert_eq!(doc["servers"], json!([{ "url": "http://localhost:8080" }]));
+        assert_eq!(doc["paths"].as_object().unwrap().len(), 3);
+
+        assert_eq!(
+            doc["paths"]["/api/users/{id}"]["get"],
+            json!({
+                "summary": "Get user",
+                "tags": ["users"],
+                "parameters": [
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "integer", "format": "int64" },
+                    },
+                    {
+                        "name": "limit",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer", "format": "int32" },
+                    },
+                ],
+                "responses": {
+                    "200": {
+                        "description": "OK",
+                        "content": {
+                            "application/json": { "schema": Info::schema() },
+                        },
+                    },
+                    "404": { "description": "No user" },
+                },
+            })
+        );
+        assert_eq!(
+            doc["paths"]["/api/users"]["post"]["requestBody"],
+            json!({
+                "required": true,
+                "content": { "application/json": { "schema": Info::schema() } },
+            })
+        );
+        assert_eq!(
+            doc["paths"]["/api/users"]["post"]["responses"]["200"]["content"],
+            json!({ "text/plain": { "schema": { "type": "string" } } })
+        );
+        assert_eq!(
+            doc["paths"]["/api/status"]["get"]["responses"],
+            json!({ "default": { "description": "Default response" } })
+        );
+    }
+}
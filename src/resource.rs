@@ -2,20 +2,25 @@ use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
+use actix_http::body::{Body, BodySize, MessageBody, ResponseBody};
+use actix_http::http::{header, HeaderValue, Method};
 use actix_http::{Error, Extensions, Response};
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{
     apply_transform, IntoNewService, IntoTransform, NewService, Service, Transform,
 };
+use bytes::Bytes;
 use futures::future::{ok, Either, FutureResult};
 use futures::{Async, Future, IntoFuture, Poll};
 
 use crate::data::Data;
 use crate::dev::{insert_slash, AppService, HttpServiceFactory, ResourceDef};
 use crate::extract::FromRequest;
-use crate::guard::Guard;
+use crate::guard::{self, Guard};
 use crate::handler::{AsyncFactory, Factory};
+use crate::openapi::Operation;
 use crate::responder::Responder;
+use crate::rmap::ResourceMethods;
 use crate::route::{CreateRouteService, Route, RouteService};
 use crate::service::{ServiceRequest, ServiceResponse};
 
@@ -43,7 +48,12 @@ pub type HttpNewService = BoxedNewService<(), ServiceRequest, ServiceResponse, E
 /// ```
 ///
 /// If no matching route could be found, *405* response code get returned.
+/// Response contains `Allow` header with methods of resource routes.
 /// Default behavior could be overriden with `default_resource()` method.
+///
+/// *OPTIONS* requests are answered automatically with `Allow` header,
+/// unless some route handles *OPTIONS* method. *HEAD* requests are handled
+/// by *GET* routes, response body is dropped.
 pub struct Resource<T = ResourceEndpoint> {
     endpoint: T,
     rdef: String,
@@ -392,6 +402,7 @@ where
         > + 'static,
 {
     fn register(mut self, config: &mut AppService) {
+        let methods = resource_methods(&self.routes, &self.guards);
         let guards = if self.guards.is_empty() {
             None
         } else {
@@ -409,7 +420,75 @@ where
         if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
         }
-        config.register_service(rdef, guards, self, None)
+        config.register_resource(rdef, guards, self, methods)
+    }
+}
+
+/// Http methods and api operations of resource routes, restricted by
+/// resource guards.
+pub(crate) fn resource_methods(
+    routes: &[Route],
+    guards: &[Box<dyn Guard>],
+) -> ResourceMethods {
+    let methods = allowed_methods(routes, guards);
+
+    let mut operations: Vec<(Method, Operation)> = Vec::new();
+    if let Some(ref allowed) = methods {
+        for route in routes {
+            // route guards could be moved to resource, i.e. `Scope::route()`
+            let route_methods = route.methods().unwrap_or_else(|| allowed.clone());
+            if let Some(op) = route.operation() {
+                for method in route_methods {
+                    // first matching route handles the request
+                    if allowed.contains(&method)
+                        && !operations.iter().any(|(m, _)| *m == method)
+                    {
+                        operations.push((method, op.clone()));
+                    }
+                }
+            }
+        }
+    }
+    ResourceMethods {
+        methods,
+        operations,
+    }
+}
+
+/// Http methods handled by resource routes, restricted by resource guards.
+///
+/// Returns `None` if resource is not restricted to specific methods.
+fn allowed_methods(routes: &[Route], guards: &[Box<dyn Guard>]) -> Option<Vec<Method>> {
+    let mut methods = if routes.is_empty() {
+        None
+    } else {
+        Some(Vec::new())
+    };
+    for route in routes {
+        match (route.methods(), methods.as_mut()) {
+            (Some(route_methods), Some(methods)) => {
+                for method in route_methods {
+                    if !methods.contains(&method) {
+                        methods.push(method);
+                    }
+                }
+            }
+            _ => {
+                methods = None;
+                break;
+            }
+        }
+    }
+
+    match (guard::all_methods(guards), methods) {
+        (Some(allowed), Some(methods)) => Some(
+            methods
+                .into_iter()
+                .filter(|m| allowed.contains(m))
+                .collect(),
+        ),
+        (allowed, None) => allowed,
+        (None, methods) => methods,
     }
 }
 
@@ -511,7 +590,7 @@ impl Future for CreateResourceService {
         }
 
         if done {
-            let routes = self
+            let routes: Vec<_> = self
                 .fut
                 .drain(..)
                 .map(|item| match item {
@@ -520,6 +599,7 @@ impl Future for CreateResourceService {
                 })
                 .collect();
             Ok(Async::Ready(ResourceService {
+                allow: allow_header(&routes),
                 routes,
                 data: self.data.clone(),
                 default: self.default.take(),
@@ -530,10 +610,35 @@ impl Future for CreateResourceService {
     }
 }
 
+/// Build `Allow` header value from methods of resource routes.
+///
+/// *HEAD* is allowed if *GET* is allowed, *OPTIONS* is always allowed.
+/// Returns `None` if none of the routes is restricted to specific methods.
+fn allow_header(routes: &[RouteService]) -> Option<HeaderValue> {
+    let mut methods = Vec::new();
+    for method in routes.iter().filter_map(|route| route.methods()).flatten() {
+        if !methods.contains(&method) {
+            methods.push(method);
+        }
+    }
+    if methods.is_empty() {
+        return None;
+    }
+    if methods.contains(&Method::GET) && !methods.contains(&Method::HEAD) {
+        methods.push(Method::HEAD);
+    }
+    if !methods.contains(&Method::OPTIONS) {
+        methods.push(Method::OPTIONS);
+    }
+    let methods: Vec<_> = methods.iter().map(|m| m.as_str()).collect();
+    HeaderValue::from_str(&methods.join(", ")).ok()
+}
+
 pub struct ResourceService {
     routes: Vec<RouteService>,
     data: Option<Rc<Extensions>>,
     default: Option<HttpService>,
+    allow: Option<HeaderValue>,
 }
 
 impl Service for ResourceService {
@@ -558,18 +663,59 @@ impl Service for ResourceService {
                 return route.call(req);
             }
         }
+
+        // handle *HEAD* request with *GET* route
+        if *req.method() == Method::HEAD {
+            req.head_mut().method = Method::GET;
+            let idx = self.routes.iter().position(|route| route.check(&mut req));
+            req.head_mut().method = Method::HEAD;
+
+            if let Some(idx) = idx {
+                if let Some(ref data) = self.data {
+                    req.set_data_container(data.clone());
+                }
+                return Either::B(Box::new(self.routes[idx].call(req).map(|res| {
+                    res.map_body(|_, body| {
+                        ResponseBody::Other(Body::Message(Box::new(HeadBody(
+                            body.size(),
+                        ))))
+                    })
+                })));
+            }
+        }
+
+        if *req.method() == Method::OPTIONS {
+            if let Some(ref allow) = self.allow {
+                let res = Response::Ok().header(header::ALLOW, allow.clone()).finish();
+                return Either::A(ok(ServiceResponse::new(req.into_parts().0, res)));
+            }
+        }
+
         if let Some(ref mut default) = self.default {
             default.call(req)
         } else {
-            let req = req.into_parts().0;
-            Either::A(ok(ServiceResponse::new(
-                req,
-                Response::MethodNotAllowed().finish(),
-            )))
+            let mut res = Response::MethodNotAllowed();
+            if let Some(ref allow) = self.allow {
+                res.header(header::ALLOW, allow.clone());
+            }
+            Either::A(ok(ServiceResponse::new(req.into_parts().0, res.finish())))
         }
     }
 }
 
+/// Body of *HEAD* response, keeps size of dropped body
+struct HeadBody(BodySize);
+
+impl MessageBody for HeadBody {
+    fn size(&self) -> BodySize {
+        self.0
+    }
+
+    fn poll_next(&mut self) -> Poll<Option<Bytes>, Error> {
+        Ok(Async::Ready(None))
+    }
+}
+
 #[doc(hidden)]
 pub struct ResourceEndpoint {
     factory: Rc<RefCell<Option<ResourceFactory>>>,
@@ -603,9 +749,10 @@ mod tests {
     use futures::{Future, IntoFuture};
     use tokio_timer::sleep;
 
+    use crate::dev::{BodySize, MessageBody};
     use crate::http::{header, HeaderValue, Method, StatusCode};
     use crate::service::{ServiceRequest, ServiceResponse};
-    use crate::test::{call_service, init_service, TestRequest};
+    use crate::test::{call_service, init_service, read_body, TestRequest};
     use crate::{guard, web, App, Error, HttpResponse};
 
     fn md<S, B>(
@@ -764,4 +911,56 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NO_CONTENT);
     }
 
+    #[test]
+    fn test_allowed_methods() {
+        let mut srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/test")
+                        .route(web::get().to(|| HttpResponse::Ok().body("test")))
+                        .route(web::post().to(|| HttpResponse::Created())),
+                )
+                .service(
+                    web::resource("/options")
+                        .route(web::get().to(|| HttpResponse::Ok()))
+                        .route(
+                            web::method(Method::OPTIONS).to(|| HttpResponse::Accepted()),
+                        ),
+                ),
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::PUT)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            "GET, POST, HEAD, OPTIONS"
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            "GET, POST, HEAD, OPTIONS"
+        );
+
+        let req = TestRequest::with_uri("/options")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::HEAD)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.response().body().size(), BodySize::Sized(4));
+        assert!(read_body(resp).is_empty());
+    }
 }
@@ -1,19 +1,91 @@
 use std::cell::RefCell;
+use std::fmt::Write;
 use std::rc::Rc;
 
+use actix_http::http::Method;
 use actix_router::ResourceDef;
 use hashbrown::HashMap;
 use url::Url;
 
 use crate::error::UrlGenerationError;
+use crate::openapi::Operation;
 use crate::request::HttpRequest;
 
+/// Kind of registered resource
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceKind {
+    /// Resource or service
+    Resource,
+    /// Scope, contains nested resources
+    Scope,
+    /// Default resource of application or scope
+    Default,
+    /// External resource, used for url generation only
+    External,
+}
+
+/// Http methods and api operations of registered resource
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResourceMethods {
+    /// `None` if resource is not restricted to specific methods
+    pub(crate) methods: Option<Vec<Method>>,
+    pub(crate) operations: Vec<(Method, Operation)>,
+}
+
+/// Description of registered resource
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceEntry {
+    pattern: String,
+    name: Option<String>,
+    methods: Option<Vec<Method>>,
+    operations: Vec<(Method, Operation)>,
+    kind: ResourceKind,
+}
+
+impl ResourceEntry {
+    /// Full resource pattern, including scope prefixes.
+    ///
+    /// Pattern of default resource is a prefix of its scope.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Resource name
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|s| s.as_str())
+    }
+
+    /// Http methods derived from resource and route guards.
+    ///
+    /// Returns `None` if resource is not restricted to specific methods.
+    pub fn methods(&self) -> Option<&[Method]> {
+        self.methods.as_ref().map(|m| m.as_slice())
+    }
+
+    /// Api operations of resource routes registered with
+    /// `Route::api_to()` or described with `Route::describe()`
+    pub fn operations(&self) -> &[(Method, Operation)] {
+        &self.operations
+    }
+
+    /// Kind of resource
+    pub fn kind(&self) -> ResourceKind {
+        self.kind
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ResourceMap {
     root: ResourceDef,
     parent: RefCell<Option<Rc<ResourceMap>>>,
     named: HashMap<String, ResourceDef>,
-    patterns: Vec<(ResourceDef, Option<Rc<ResourceMap>>)>,
+    patterns: Vec<(
+        ResourceDef,
+        Option<Rc<ResourceMap>>,
+        ResourceKind,
+        ResourceMethods,
+    )>,
+    default: bool,
 }
 
 impl ResourceMap {
@@ -23,20 +95,50 @@ impl ResourceMap {
             parent: RefCell::new(None),
             named: HashMap::new(),
             patterns: Vec::new(),
+            default: false,
         }
     }
 
     pub fn add(&mut self, pattern: &mut ResourceDef, nested: Option<Rc<ResourceMap>>) {
+        self.add_service(pattern, nested, ResourceMethods::default())
+    }
+
+    pub(crate) fn add_service(
+        &mut self,
+        pattern: &mut ResourceDef,
+        nested: Option<Rc<ResourceMap>>,
+        methods: ResourceMethods,
+    ) {
+        let kind = if nested.is_some() {
+            ResourceKind::Scope
+        } else {
+            ResourceKind::Resource
+        };
+        self.add_entry(pattern, nested, kind, methods)
+    }
+
+    pub(crate) fn add_entry(
+        &mut self,
+        pattern: &mut ResourceDef,
+        nested: Option<Rc<ResourceMap>>,
+        kind: ResourceKind,
+        methods: ResourceMethods,
+    ) {
         pattern.set_id(self.patterns.len() as u16);
-        self.patterns.push((pattern.clone(), nested));
+        self.patterns.push((pattern.clone(), nested, kind, methods));
         if !pattern.name().is_empty() {
             self.named
                 .insert(pattern.name().to_string(), pattern.clone());
         }
     }
 
+    /// Mark map as having custom default resource
+    pub(crate) fn set_default(&mut self) {
+        self.default = true;
+    }
+
     pub(crate) fn finish(&self, current: Rc<ResourceMap>) {
-        for (_, nested) in &self.patterns {
+        for (_, nested, _, _) in &self.patterns {
             if let Some(ref nested) = nested {
                 *nested.parent.borrow_mut() = Some(current.clone());
                 nested.finish(nested.clone());
@@ -46,6 +148,95 @@ impl ResourceMap {
 }
 
 impl ResourceMap {
+    /// Registered resources, including resources of nested scopes.
+    ///
+    /// Resources are listed in registration order, nested resources follow
+    /// their scope.
+    pub fn resources(&self) -> Vec<ResourceEntry> {
+        let mut entries = Vec::new();
+        self.collect_entries("", &mut entries);
+        entries
+    }
+
+    fn collect_entries(&self, prefix: &str, entries: &mut Vec<ResourceEntry>) {
+        for (rdef, nested, kind, methods) in &self.patterns {
+            let pattern = if *kind == ResourceKind::External {
+                rdef.pattern().to_string()
+            } else {
+                format!("{}{}", prefix, rdef.pattern())
+            };
+            let name = if rdef.name().is_empty() {
+                None
+            } else {
+                Some(rdef.name().to_string())
+            };
+            entries.push(ResourceEntry {
+                pattern: pattern.clone(),
+                name,
+                methods: methods.methods.clone(),
+                operations: methods.operations.clone(),
+                kind: *kind,
+            });
+            if let Some(ref nested) = nested {
+                nested.collect_entries(&pattern, entries);
+            }
+        }
+        if self.default {
+            entries.push(ResourceEntry {
+                pattern: prefix.to_string(),
+                name: None,
+                methods: None,
+                operations: Vec::new(),
+                kind: ResourceKind::Default,
+            });
+        }
+    }
+
+    /// Printable route table, one resource per line.
+    ///
+    /// Every line contains methods, pattern, name and kind of a resource.
+    /// Methods column contains `*` if resource is not restricted to
+    /// specific methods.
+    pub fn route_table(&self) -> String {
+        let rows: Vec<_> = self
+            .resources()
+            .into_iter()
+            .map(|entry| {
+                let methods = match entry.methods {
+                    Some(ref methods) => methods
+                        .iter()
+                        .map(|m| m.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    None => "*".to_string(),
+                };
+                (methods, entry)
+            })
+            .collect();
+        let width = rows.iter().map(|(m, _)| m.len()).max().unwrap_or(0);
+
+        let mut table = String::new();
+        for (methods, entry) in &rows {
+            let pattern = if entry.pattern.is_empty() {
+                "/"
+            } else {
+                entry.pattern.as_str()
+            };
+            let _ = write!(table, "{:<width$} {}", methods, pattern, width = width);
+            if let Some(ref name) = entry.name {
+                let _ = write!(table, " name={}", name);
+            }
+            match entry.kind {
+                ResourceKind::Resource => (),
+                ResourceKind::Scope => table.push_str(" (scope)"),
+                ResourceKind::Default => table.push_str(" (default)"),
+                ResourceKind::External => table.push_str(" (external)"),
+            }
+            table.push('\n');
+        }
+        table
+    }
+
     /// Generate url for named resource
     ///
     /// Check [`HttpRequest::url_for()`](../struct.HttpRequest.html#method.
@@ -83,7 +274,7 @@ impl ResourceMap {
     pub fn has_resource(&self, path: &str) -> bool {
         let path = if path.is_empty() { "/" } else { path };
 
-        for (pattern, rmap) in &self.patterns {
+        for (pattern, rmap, _, _) in &self.patterns {
             if let Some(ref rmap) = rmap {
                 if let Some(plen) = pattern.is_prefix_match(path) {
                     return rmap.has_resource(&path[plen..]);
@@ -132,7 +323,7 @@ impl ResourceMap {
                 Err(UrlGenerationError::NotEnoughElements)
             }
         } else {
-            for (_, rmap) in &self.patterns {
+            for (_, rmap, _, _) in &self.patterns {
                 if let Some(ref rmap) = rmap {
                     if rmap.pattern_for(name, path, elements)?.is_some() {
                         return Ok(Some(()));
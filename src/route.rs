@@ -1,13 +1,14 @@
 use std::rc::Rc;
 
 use actix_http::{http::Method, Error};
-use actix_service::{NewService, Service};
-use futures::future::{ok, Either, FutureResult};
+use actix_service::{IntoTransform, NewService, Service, Transform};
+use futures::future::{err, ok, Either, FutureResult};
 use futures::{Async, Future, IntoFuture, Poll};
 
 use crate::extract::FromRequest;
 use crate::guard::{self, Guard};
 use crate::handler::{AsyncFactory, AsyncHandler, Extract, Factory, Handler};
+use crate::openapi::{ApiComponent, Operation};
 use crate::responder::Responder;
 use crate::service::{ServiceRequest, ServiceResponse};
 use crate::HttpResponse;
@@ -36,6 +37,12 @@ type BoxedRouteNewService<Req, Res> = Box<
     >,
 >;
 
+type BoxedRouteTransform<Req, Res> = Box<
+    dyn Fn(
+        BoxedRouteService<Req, Res>,
+    ) -> Box<dyn Future<Item = BoxedRouteService<Req, Res>, Error = ()>>,
+>;
+
 /// Resource route definition
 ///
 /// Route uses builder-like pattern for configuration.
@@ -43,6 +50,8 @@ type BoxedRouteNewService<Req, Res> = Box<
 pub struct Route {
     service: BoxedRouteNewService<ServiceRequest, ServiceResponse>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    wraps: Rc<Vec<BoxedRouteTransform<ServiceRequest, ServiceResponse>>>,
+    operation: Option<Operation>,
 }
 
 impl Route {
@@ -53,12 +62,25 @@ impl Route {
                 HttpResponse::NotFound()
             })))),
             guards: Rc::new(Vec::new()),
+            wraps: Rc::new(Vec::new()),
+            operation: None,
         }
     }
 
     pub(crate) fn take_guards(&mut self) -> Vec<Box<dyn Guard>> {
         std::mem::replace(Rc::get_mut(&mut self.guards).unwrap(), Vec::new())
     }
+
+    /// Http methods handled by the route, `None` if route is not
+    /// restricted to specific methods
+    pub(crate) fn methods(&self) -> Option<Vec<Method>> {
+        guard::all_methods(&self.guards)
+    }
+
+    /// Api operation of the route, if route is described
+    pub(crate) fn operation(&self) -> Option<&Operation> {
+        self.operation.as_ref()
+    }
 }
 
 impl NewService for Route {
@@ -74,6 +96,8 @@ impl NewService for Route {
         CreateRouteService {
             fut: self.service.new_service(&()),
             guards: self.guards.clone(),
+            wraps: self.wraps.clone(),
+            wrapped: 0,
         }
     }
 }
@@ -85,6 +109,8 @@ type RouteFuture = Box<
 pub struct CreateRouteService {
     fut: RouteFuture,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    wraps: Rc<Vec<BoxedRouteTransform<ServiceRequest, ServiceResponse>>>,
+    wrapped: usize,
 }
 
 impl Future for CreateRouteService {
@@ -92,12 +118,22 @@ impl Future for CreateRouteService {
     type Error = ();
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self.fut.poll()? {
-            Async::Ready(service) => Ok(Async::Ready(RouteService {
-                service,
-                guards: self.guards.clone(),
-            })),
-            Async::NotReady => Ok(Async::NotReady),
+        loop {
+            match self.fut.poll()? {
+                Async::Ready(service) => {
+                    // apply route middlewares, one at a time
+                    if self.wrapped < self.wraps.len() {
+                        self.fut = (self.wraps[self.wrapped])(service);
+                        self.wrapped += 1;
+                    } else {
+                        return Ok(Async::Ready(RouteService {
+                            service,
+                            guards: self.guards.clone(),
+                        }));
+                    }
+                }
+                Async::NotReady => return Ok(Async::NotReady),
+            }
         }
     }
 }
@@ -116,6 +152,12 @@ impl RouteService {
         }
         true
     }
+
+    /// Http methods handled by the route, `None` if route is not
+    /// restricted to specific methods
+    pub(crate) fn methods(&self) -> Option<Vec<Method>> {
+        guard::all_methods(&self.guards)
+    }
 }
 
 impl Service for RouteService {
@@ -175,6 +217,100 @@ impl Route {
         self
     }
 
+    /// Register a route middleware.
+    ///
+    /// This is similar to `Resource's` middlewares, but middleware get invoked
+    /// only if route's guards match the request. Route level middlewares are
+    /// not allowed to change response type (i.e modify response's body).
+    /// Middlewares get applied to route's handler regardless of the order
+    /// of `wrap()` and `to()` calls.
+    ///
+    /// ```rust
+    /// use actix_web::{middleware, web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/index.html")
+    ///             .route(web::get().to(|| HttpResponse::Ok()))
+    ///             .route(
+    ///                 web::post()
+    ///                     .wrap(middleware::DefaultHeaders::new().header("X-Posted", "1"))
+    ///                     .to(|| HttpResponse::Created()),
+    ///             ),
+    ///     );
+    /// }
+    /// ```
+    pub fn wrap<M, F>(mut self, mw: F) -> Self
+    where
+        M: Transform<
+                BoxedRouteService<ServiceRequest, ServiceResponse>,
+                Request = ServiceRequest,
+                Response = ServiceResponse,
+                Error = Error,
+                InitError = (),
+            > + 'static,
+        M::Transform: 'static,
+        M::Future: 'static,
+        <M::Transform as Service>::Future: 'static,
+        F: IntoTransform<M, BoxedRouteService<ServiceRequest, ServiceResponse>>,
+    {
+        let mw = mw.into_transform();
+        let wrap: BoxedRouteTransform<ServiceRequest, ServiceResponse> =
+            Box::new(move |service| {
+                let fut: RouteFuture =
+                    Box::new(mw.new_transform(service).map(|service| {
+                        let service: BoxedRouteService<_, _> =
+                            Box::new(RouteTransformWrapper { service });
+                        service
+                    }));
+                fut
+            });
+        Rc::get_mut(&mut self.wraps).unwrap().push(wrap);
+        self
+    }
+
+    /// Register a route middleware function.
+    ///
+    /// This function accepts instance of `ServiceRequest` type and
+    /// mutable reference to the next middleware in chain.
+    ///
+    /// ```rust
+    /// use actix_service::Service;
+    /// # use futures::Future;
+    /// use actix_web::{web, App, HttpResponse};
+    /// use actix_web::http::{header::CONTENT_TYPE, HeaderValue};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/index.html").route(
+    ///             web::post()
+    ///                 .wrap_fn(|req, srv| {
+    ///                     srv.call(req).map(|mut res| {
+    ///                         res.headers_mut().insert(
+    ///                             CONTENT_TYPE, HeaderValue::from_static("text/plain"),
+    ///                         );
+    ///                         res
+    ///                     })
+    ///                 })
+    ///                 .to(|| HttpResponse::Created()),
+    ///         ),
+    ///     );
+    /// }
+    /// ```
+    pub fn wrap_fn<F, R>(self, mw: F) -> Self
+    where
+        F: FnMut(
+                ServiceRequest,
+                &mut BoxedRouteService<ServiceRequest, ServiceResponse>,
+            ) -> R
+            + Clone
+            + 'static,
+        R: IntoFuture<Item = ServiceResponse, Error = Error> + 'static,
+        R::Future: 'static,
+    {
+        self.wrap(mw)
+    }
+
     /// Set handler function, use request extractors for parameters.
     ///
     /// ```rust
@@ -274,6 +410,66 @@ impl Route {
         ))));
         self
     }
+
+    /// Set handler function and describe route's api operation.
+    ///
+    /// Works like `Route::to()`, additionally extractors and responder of
+    /// the handler describe parameters, request body and responses of the
+    /// operation in OpenAPI document. See `openapi` module.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App};
+    ///
+    /// fn index(path: web::Path<(String,)>) -> String {
+    ///     format!("Welcome {}!", path.0)
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/{username}/index.html")
+    ///             .route(web::get().api_to(index))
+    ///     );
+    /// }
+    /// ```
+    pub fn api_to<F, T, R>(mut self, handler: F) -> Route
+    where
+        F: Factory<T, R> + 'static,
+        T: FromRequest + ApiComponent + 'static,
+        R: Responder + ApiComponent + 'static,
+    {
+        let op = self.operation.get_or_insert_with(Operation::default);
+        T::describe_request(op);
+        R::describe_response(op);
+        self.to(handler)
+    }
+
+    /// Describe route's api operation.
+    ///
+    /// Routes described with this method are included in OpenAPI document
+    /// even if handler is registered with `Route::to()`.
+    ///
+    /// ```rust
+    /// use actix_web::{web, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/index.html").route(
+    ///             web::get()
+    ///                 .describe(|op| {
+    ///                     op.summary("Index page").tag("pages");
+    ///                 })
+    ///                 .to(|| HttpResponse::Ok()),
+    ///         ),
+    ///     );
+    /// }
+    /// ```
+    pub fn describe<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Operation),
+    {
+        f(self.operation.get_or_insert_with(Operation::default));
+        self
+    }
 }
 
 struct RouteNewService<T>
@@ -372,16 +568,50 @@ where
     }
 }
 
+struct RouteTransformWrapper<T: Service> {
+    service: T,
+}
+
+impl<T> Service for RouteTransformWrapper<T>
+where
+    T::Future: 'static,
+    T: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error>,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = Either<
+        FutureResult<Self::Response, Self::Error>,
+        Box<dyn Future<Item = Self::Response, Error = Self::Error>>,
+    >;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let mut fut = self.service.call(req);
+        match fut.poll() {
+            Ok(Async::Ready(res)) => Either::A(ok(res)),
+            Err(e) => Either::A(err(e)),
+            Ok(Async::NotReady) => Either::B(Box::new(fut)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
+    use actix_service::Service;
     use bytes::Bytes;
     use futures::Future;
     use serde_derive::Serialize;
     use tokio_timer::sleep;
 
+    use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
     use crate::http::{Method, StatusCode};
+    use crate::middleware::DefaultHeaders;
     use crate::test::{call_service, init_service, read_body, TestRequest};
     use crate::{error, web, App, HttpResponse};
 
@@ -447,6 +677,12 @@ mod tests {
             .method(Method::HEAD)
             .to_request();
         let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::PATCH)
+            .to_request();
+        let resp = call_service(&mut srv, req);
         assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
 
         let req = TestRequest::with_uri("/json").to_request();
@@ -456,4 +692,48 @@ mod tests {
         let body = read_body(resp);
         assert_eq!(body, Bytes::from_static(b"{\"name\":\"test\"}"));
     }
+
+    #[test]
+    fn test_route_wrap() {
+        let mut srv = init_service(
+            App::new().service(
+                web::resource("/test")
+                    .route(web::get().to(|| HttpResponse::Ok()))
+                    .route(
+                        web::post()
+                            .wrap(DefaultHeaders::new().header(CONTENT_TYPE, "0001"))
+                            .to(|| HttpResponse::Created())
+                            .wrap_fn(|req, srv| {
+                                srv.call(req).map(|mut res| {
+                                    res.headers_mut().insert(
+                                        HeaderName::from_static("x-route"),
+                                        HeaderValue::from_static("post"),
+                                    );
+                                    res
+                                })
+                            }),
+                    ),
+            ),
+        );
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(CONTENT_TYPE).is_none());
+        assert!(resp.headers().get("x-route").is_none());
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&mut srv, req);
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("0001")
+        );
+        assert_eq!(
+            resp.headers().get("x-route").unwrap(),
+            HeaderValue::from_static("post")
+        );
+    }
 }
@@ -0,0 +1,381 @@
+//! Prefix tree resource router
+//!
+//! Router compiles static segments and dynamic `{param}` segments of
+//! resource patterns into a prefix tree. For a request path, tree yields
+//! resources whose patterns could match the path, candidates are checked
+//! in registration order with `ResourceDef`, so precedence of resources
+//! and guards evaluation are the same as for linear router.
+use actix_router::{Resource, ResourceDef, ResourcePath};
+use hashbrown::HashMap;
+
+/// Resource router.
+pub struct Router<T, U = ()> {
+    resources: Vec<(ResourceDef, T, Option<U>)>,
+    tree: Node,
+    candidates: Vec<usize>,
+}
+
+impl<T, U> Router<T, U> {
+    pub fn build() -> RouterBuilder<T, U> {
+        RouterBuilder {
+            resources: Vec::new(),
+        }
+    }
+
+    /// Find first registered resource that matches the path and
+    /// passes the check, returns resource and id of its definition.
+    pub fn recognize_mut_checked<R, P, F>(
+        &mut self,
+        resource: &mut R,
+        check: F,
+    ) -> Option<(&mut T, u16)>
+    where
+        F: Fn(&R, &Option<U>) -> bool,
+        R: Resource<P>,
+        P: ResourcePath,
+    {
+        let mut candidates = std::mem::replace(&mut self.candidates, Vec::new());
+        candidates.clear();
+        self.tree
+            .collect(Some(resource.resource_path().path()), &mut candidates);
+        candidates.sort_unstable();
+
+        let resources = &self.resources;
+        let found = candidates.iter().cloned().find(|idx| {
+            let item = &resources[*idx];
+            item.0.match_path_checked(resource, &check, &item.2)
+        });
+        self.candidates = candidates;
+
+        found.map(move |idx| {
+            let item = &mut self.resources[idx];
+            (&mut item.1, item.0.id())
+        })
+    }
+}
+
+pub struct RouterBuilder<T, U = ()> {
+    resources: Vec<(ResourceDef, T, Option<U>)>,
+}
+
+impl<T, U> RouterBuilder<T, U> {
+    /// Register resource for specified path.
+    pub fn path(&mut self, path: &str, resource: T) -> &mut (ResourceDef, T, Option<U>) {
+        self.rdef(ResourceDef::new(path), resource)
+    }
+
+    /// Register resource for specified path prefix.
+    pub fn prefix(
+        &mut self,
+        prefix: &str,
+        resource: T,
+    ) -> &mut (ResourceDef, T, Option<U>) {
+        self.rdef(ResourceDef::prefix(prefix), resource)
+    }
+
+    /// Register resource for ResourceDef
+    pub fn rdef(
+        &mut self,
+        rdef: ResourceDef,
+        resource: T,
+    ) -> &mut (ResourceDef, T, Option<U>) {
+        self.resources.push((rdef, resource, None));
+        self.resources.last_mut().unwrap()
+    }
+
+    /// Finish configuration and create router instance.
+    pub fn finish(self) -> Router<T, U> {
+        let mut tree = Node::default();
+        for (idx, item) in self.resources.iter().enumerate() {
+            tree.insert(item.0.pattern(), idx);
+        }
+        Router {
+            tree,
+            resources: self.resources,
+            candidates: Vec::new(),
+        }
+    }
+}
+
+/// Node of prefix tree, corresponds to path segment
+#[derive(Default)]
+struct Node {
+    /// Resources that could match any path with segments of this node
+    resources: Vec<usize>,
+    statics: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+}
+
+enum Segment<'a> {
+    /// Literal segment, path segment must be equal
+    Static(&'a str),
+    /// `{name}` segment, matches any non-empty path segment
+    Param,
+    /// Segment can not be compiled, rest of the pattern is matched by regex
+    Other,
+}
+
+impl Node {
+    fn insert(&mut self, pattern: &str, idx: usize) {
+        let dynamic = pattern.contains('{');
+        let regex = !dynamic && pattern.ends_with('*');
+        let mut node = self;
+
+        let mut segments = pattern.split('/').peekable();
+        while let Some(segment) = segments.next() {
+            let last = segments.peek().is_none();
+
+            node = match classify(segment, last, dynamic, regex) {
+                Segment::Static(s) => node.statics.entry(s.to_string()).or_default(),
+                Segment::Param => node.param.get_or_insert_with(Default::default),
+                Segment::Other => break,
+            };
+        }
+        node.resources.push(idx);
+    }
+
+    /// Collect resources that could match path, `path` is a remainder of
+    /// the path after segments of this node, `None` if path is consumed
+    fn collect(&self, path: Option<&str>, candidates: &mut Vec<usize>) {
+        candidates.extend_from_slice(&self.resources);
+
+        if let Some(path) = path {
+            let (segment, rest) = match path.find('/') {
+                Some(pos) => (&path[..pos], Some(&path[pos + 1..])),
+                None => (path, None),
+            };
+            if let Some(node) = self.statics.get(segment) {
+                node.collect(rest, candidates);
+            }
+            if !segment.is_empty() {
+                if let Some(ref node) = self.param {
+                    node.collect(rest, candidates);
+                }
+            }
+        }
+    }
+}
+
+/// Classify pattern segment.
+///
+/// Last literal segment of static pattern must be equal to path segment,
+/// except empty segment, prefix pattern with trailing slash matches any
+/// segment. Dynamic pattern could be a prefix pattern without segment
+/// boundary check, so its last literal segment is not compiled. Patterns
+/// with trailing `*` are unescaped regexes.
+fn classify(segment: &str, last: bool, dynamic: bool, regex: bool) -> Segment {
+    if dynamic {
+        if segment.starts_with('{')
+            && segment.ends_with('}')
+            && !segment[1..segment.len() - 1].contains(|c| c == '{' || c == '}' || c == ':')
+        {
+            Segment::Param
+        } else if last || segment.contains(|c| c == '{' || c == '}') {
+            Segment::Other
+        } else {
+            Segment::Static(segment)
+        }
+    } else if (last && segment.is_empty())
+        || (regex && segment.contains(|c| "\\.+*?()|[]{}^$".contains(c)))
+    {
+        Segment::Other
+    } else {
+        Segment::Static(segment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_router::{Path, Router as LinearRouter};
+
+    use super::*;
+
+    #[test]
+    fn test_recognizer() {
+        let mut router = Router::<usize>::build();
+        router.path("/name", 10).0.set_id(0);
+        router.path("/name/{val}", 11).0.set_id(1);
+        router.path("/name/{val}/index.html", 12).0.set_id(2);
+        router.path("/file/{file}.{ext}", 13).0.set_id(3);
+        router.path("/v{val}/{val2}/index.html", 14).0.set_id(4);
+        router.path("/v/{tail:.*}", 15).0.set_id(5);
+        router.path("/test2/{test}.html", 16).0.set_id(6);
+        router.path("/{test}/index.html", 17).0.set_id(7);
+        let mut router = router.finish();
+        let check = |_: &Path<&str>, _: &Option<()>| true;
+
+        let mut path = Path::new("/unknown");
+        assert!(router.recognize_mut_checked(&mut path, check).is_none());
+
+        let mut path = Path::new("/name");
+        let (h, info) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 10);
+        assert_eq!(info, 0);
+        assert!(path.is_empty());
+
+        let mut path = Path::new("/name/value");
+        let (h, info) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 11);
+        assert_eq!(info, 1);
+        assert_eq!(&path["val"], "value");
+
+        let mut path = Path::new("/name/value2/index.html");
+        let (h, _) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 12);
+        assert_eq!(&path["val"], "value2");
+
+        let mut path = Path::new("/file/file.gz");
+        let (h, _) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 13);
+        assert_eq!(&path["file"], "file");
+        assert_eq!(&path["ext"], "gz");
+
+        let mut path = Path::new("/vtest/ttt/index.html");
+        let (h, _) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 14);
+        assert_eq!(&path["val"], "test");
+        assert_eq!(&path["val2"], "ttt");
+
+        let mut path = Path::new("/v/blah-blah/index.html");
+        let (h, _) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 15);
+        assert_eq!(&path["tail"], "blah-blah/index.html");
+
+        let mut path = Path::new("/test2/index.html");
+        let (h, _) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 16);
+        assert_eq!(&path["test"], "index");
+
+        let mut path = Path::new("/bbb/index.html");
+        let (h, _) = router.recognize_mut_checked(&mut path, check).unwrap();
+        assert_eq!(*h, 17);
+        assert_eq!(&path["test"], "bbb");
+    }
+
+    #[test]
+    fn test_precedence_and_check() {
+        let mut router = Router::<usize, usize>::build();
+        router.path("/users/{id}", 1).2 = Some(1);
+        router.path("/users/{id}", 2).2 = Some(2);
+        router.path("/users/me", 3);
+        let mut router = router.finish();
+
+        // first registered resource wins
+        let mut path = Path::new("/users/me");
+        let (h, _) = router
+            .recognize_mut_checked(&mut path, |_, _| true)
+            .unwrap();
+        assert_eq!(*h, 1);
+
+        // check is evaluated for matched resources in registration order
+        let mut path = Path::new("/users/me");
+        let (h, _) = router
+            .recognize_mut_checked(&mut path, |_, data| *data == Some(2))
+            .unwrap();
+        assert_eq!(*h, 2);
+
+        let mut path = Path::new("/users/me");
+        let (h, _) = router
+            .recognize_mut_checked(&mut path, |_, data| data.is_none())
+            .unwrap();
+        assert_eq!(*h, 3);
+    }
+
+    #[test]
+    fn test_same_as_linear() {
+        let patterns = [
+            "",
+            "/",
+            "/name",
+            "/name/",
+            "/name/{val}",
+            "/name/{val}/index.html",
+            "/name/{val:\\d+}",
+            "/file/{file}.{ext}",
+            "/v{val}/{val2}/index.html",
+            "/v/{tail:.*}",
+            "/{test}/index.html",
+            "/static*",
+            "/static/*",
+            "/a.b/*",
+            "/users/{id}/posts",
+            "/users/{id}/posts/{post}",
+            "/{tail}*",
+        ];
+        let prefixes = [
+            "/api", "/api/", "/", "", "/{project}", "/{project}/api", "/name/",
+        ];
+        let paths = [
+            "",
+            "/",
+            "//",
+            "/name",
+            "/name/",
+            "/name1",
+            "/name/value",
+            "/name/123",
+            "/name/value/index.html",
+            "/name//index.html",
+            "/file/file.gz",
+            "/vtest/ttt/index.html",
+            "/v/blah-blah/index.html",
+            "/test/index.html",
+            "/static",
+            "/staticfile",
+            "/static/file",
+            "/a.b/c",
+            "/axb/c",
+            "/api",
+            "/api/",
+            "/apix",
+            "/api/users",
+            "/project",
+            "/project/api",
+            "/project/apix",
+            "/project/api/users",
+            "/users/1/posts",
+            "/users/1/posts/2",
+            "/users//posts",
+            "name",
+            "name/value",
+        ];
+
+        // each resource alone, and all resources in different orders
+        let mut sets: Vec<Vec<ResourceDef>> = Vec::new();
+        for p in &patterns {
+            sets.push(vec![ResourceDef::new(p)]);
+        }
+        for p in &prefixes {
+            sets.push(vec![ResourceDef::prefix(p)]);
+        }
+        let mut all: Vec<_> = patterns.iter().map(|p| ResourceDef::new(p)).collect();
+        all.extend(prefixes.iter().map(|p| ResourceDef::prefix(p)));
+        sets.push(all.clone());
+        all.reverse();
+        sets.push(all);
+
+        for set in sets {
+            let mut tree = Router::<usize>::build();
+            let mut linear = LinearRouter::<usize>::build();
+            for (idx, rdef) in set.into_iter().enumerate() {
+                linear.rdef(rdef.clone(), idx);
+                tree.rdef(rdef, idx);
+            }
+            let mut tree = tree.finish();
+            let mut linear = linear.finish();
+
+            for p in &paths {
+                let mut path1 = Path::new(*p);
+                let mut path2 = Path::new(*p);
+                let res1 = tree
+                    .recognize_mut_checked(&mut path1, |_, _| true)
+                    .map(|(h, _)| *h);
+                let res2 = linear
+                    .recognize_mut_checked(&mut path2, |_, _| true)
+                    .map(|(h, _)| *h);
+                assert_eq!(res1, res2, "path: {:?}", p);
+                assert_eq!(path1.unprocessed(), path2.unprocessed());
+            }
+        }
+    }
+}
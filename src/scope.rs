@@ -2,8 +2,9 @@ use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
+use actix_http::http::Method;
 use actix_http::{Extensions, Response};
-use actix_router::{ResourceDef, ResourceInfo, Router};
+use actix_router::{ResourceDef, ResourceInfo};
 use actix_service::boxed::{self, BoxedNewService, BoxedService};
 use actix_service::{
     apply_transform, IntoNewService, IntoTransform, NewService, Service, Transform,
@@ -13,13 +14,14 @@ use futures::{Async, IntoFuture, Poll};
 
 use crate::config::ServiceConfig;
 use crate::data::Data;
-use crate::dev::{AppService, HttpServiceFactory};
+use crate::dev::{insert_slash, AppService, HttpServiceFactory};
 use crate::error::Error;
 use crate::guard::Guard;
 use crate::resource::Resource;
 use crate::normalized_resource::NormalizedResource;
-use crate::rmap::ResourceMap;
+use crate::rmap::{ResourceKind, ResourceMap};
 use crate::route::Route;
+use crate::router::Router;
 use crate::service::{
     ServiceFactory, ServiceFactoryWrapper, ServiceRequest, ServiceResponse,
 };
@@ -249,6 +251,9 @@ where
     /// This method can be called multiple times, in that case
     /// multiple resources with one route would be registered for same resource path.
     ///
+    /// Route guards are moved to the registered resource, see `App::route()`
+    /// for limitations of method handling.
+    ///
     /// ```rust
     /// use actix_web::{web, App, HttpResponse};
     ///
@@ -431,7 +436,8 @@ where
 {
     fn register(mut self, config: &mut AppService) {
         // update default resource if needed
-        if self.default.borrow().is_none() {
+        let has_default = self.default.borrow().is_some();
+        if !has_default {
             *self.default.borrow_mut() = Some(config.default_service());
         }
 
@@ -441,11 +447,19 @@ where
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
 
+        let prefix = insert_slash(&self.rdef);
+        for conflict in cfg.route_conflicts() {
+            config.add_route_conflict(conflict.with_prefix(&prefix));
+        }
+
         let mut rmap = ResourceMap::new(ResourceDef::root_prefix(&self.rdef));
 
         // external resources
         for mut rdef in std::mem::replace(&mut self.external, Vec::new()) {
-            rmap.add(&mut rdef, None);
+            rmap.add_entry(&mut rdef, None, ResourceKind::External, Default::default());
+        }
+        if has_default {
+            rmap.set_default();
         }
 
         // custom app data storage
@@ -461,8 +475,8 @@ where
                 cfg.into_services()
                     .1
                     .into_iter()
-                    .map(|(mut rdef, srv, guards, nested)| {
-                        rmap.add(&mut rdef, nested);
+                    .map(|(mut rdef, srv, guards, nested, methods)| {
+                        rmap.add_service(&mut rdef, nested, methods);
                         (rdef, srv, RefCell::new(guards))
                     })
                     .collect(),
@@ -624,7 +638,7 @@ impl Service for ScopeService {
     }
 
     fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
-        let res = self.router.recognize_mut_checked(&mut req, |req, guards| {
+        let check = |req: &ServiceRequest, guards: &Option<Guards>| {
             if let Some(ref guards) = guards {
                 for f in guards {
                     if !f.check(req.head()) {
@@ -633,7 +647,16 @@ impl Service for ScopeService {
                 }
             }
             true
-        });
+        };
+        let mut res = self.router.recognize_mut_checked(&mut req, &check);
+
+        // *HEAD* request could be handled by resource restricted to *GET*,
+        // i.e. registered with `route()`
+        if res.is_none() && *req.method() == Method::HEAD {
+            req.head_mut().method = Method::GET;
+            res = self.router.recognize_mut_checked(&mut req, &check);
+            req.head_mut().method = Method::HEAD;
+        }
 
         if let Some((srv, _info)) = res {
             if let Some(ref data) = self.data {
@@ -1,8 +1,13 @@
+use std::any::Any;
 use std::marker::PhantomData;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::{fmt, io, net};
 
-use actix_http::{body::MessageBody, Error, HttpService, KeepAlive, Request, Response};
+use actix_http::{
+    body::MessageBody, Error, HttpServiceBuilder, KeepAlive, Request,
+    Response,
+};
 use actix_rt::System;
 use actix_server::{Server, ServerBuilder};
 use actix_server_config::ServerConfig;
@@ -11,6 +16,8 @@ use parking_lot::Mutex;
 
 use net2::TcpBuilder;
 
+use crate::data::ConnectionDataAny;
+
 #[cfg(feature = "ssl")]
 use openssl::ssl::{SslAcceptor, SslAcceptorBuilder};
 #[cfg(feature = "rust-tls")]
@@ -25,8 +32,101 @@ struct Config {
     keep_alive: KeepAlive,
     client_timeout: u64,
     client_shutdown: u64,
+    h1: H1Config,
+    h2: H2Config,
+    max_requests_per_connection: usize,
+    max_connection_age: u64,
+    min_request_data_rate: Option<(u64, u64)>,
+    min_response_data_rate: Option<(u64, u64)>,
+    on_connect: Option<OnConnectFn>,
+}
+
+#[derive(Default)]
+struct H1Config {
+    max_headers: Option<usize>,
+    max_header_block_size: Option<usize>,
+    max_uri_length: Option<usize>,
+    max_header_value_size: Option<usize>,
+}
+
+#[derive(Default)]
+struct H2Config {
+    max_concurrent_streams: Option<u32>,
+    initial_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    max_frame_size: Option<u32>,
+    max_header_list_size: Option<u32>,
+    keep_alive: Option<KeepAlive>,
+    ping_interval: Option<u64>,
+    ping_timeout: Option<u64>,
+}
+
+impl Config {
+    /// Create http service builder with server settings
+    fn service<T, S>(&self) -> HttpServiceBuilder<T, S>
+    where
+        S: NewService<Config = ServerConfig, Request = Request>,
+        S::Error: Into<Error>,
+        S::InitError: fmt::Debug,
+    {
+        let mut srv = HttpServiceBuilder::new()
+            .keep_alive(self.keep_alive)
+            .client_timeout(self.client_timeout)
+            .max_requests_per_connection(self.max_requests_per_connection)
+            .max_connection_age(self.max_connection_age);
+
+        if let Some((rate, grace)) = self.min_request_data_rate {
+            srv = srv.min_request_data_rate(rate, grace);
+        }
+        if let Some((rate, grace)) = self.min_response_data_rate {
+            srv = srv.min_response_data_rate(rate, grace);
+        }
+
+        let h1 = &self.h1;
+        if let Some(val) = h1.max_headers {
+            srv = srv.h1_max_headers(val);
+        }
+        if let Some(val) = h1.max_header_block_size {
+            srv = srv.h1_max_header_block_size(val);
+        }
+        if let Some(val) = h1.max_uri_length {
+            srv = srv.h1_max_uri_length(val);
+        }
+        if let Some(val) = h1.max_header_value_size {
+            srv = srv.h1_max_header_value_size(val);
+        }
+
+        let h2 = &self.h2;
+        if let Some(val) = h2.max_concurrent_streams {
+            srv = srv.h2_max_concurrent_streams(val);
+        }
+        if let Some(val) = h2.initial_window_size {
+            srv = srv.h2_initial_window_size(val);
+        }
+        if let Some(val) = h2.initial_connection_window_size {
+            srv = srv.h2_initial_connection_window_size(val);
+        }
+        if let Some(val) = h2.max_frame_size {
+            srv = srv.h2_max_frame_size(val);
+        }
+        if let Some(val) = h2.max_header_list_size {
+            srv = srv.h2_max_header_list_size(val);
+        }
+        if let Some(val) = h2.keep_alive {
+            srv = srv.h2_keep_alive(val);
+        }
+        if let Some(val) = h2.ping_interval {
+            srv = srv.h2_ping_interval(val);
+        }
+        if let Some(val) = h2.ping_timeout {
+            srv = srv.h2_ping_timeout(val);
+        }
+        srv
+    }
 }
 
+type OnConnectFn = Arc<dyn Fn(&dyn Any) -> Rc<dyn Any> + Send + Sync>;
+
 /// An HTTP Server.
 ///
 /// Create new http server with application factory.
@@ -88,6 +188,13 @@ where
                 keep_alive: KeepAlive::Timeout(5),
                 client_timeout: 5000,
                 client_shutdown: 5000,
+                h1: H1Config::default(),
+                h2: H2Config::default(),
+                max_requests_per_connection: 0,
+                max_connection_age: 0,
+                min_request_data_rate: None,
+                min_response_data_rate: None,
+                on_connect: None,
             })),
             backlog: 1024,
             sockets: Vec::new(),
@@ -178,6 +285,220 @@ where
         self
     }
 
+    /// Set maximum number of requests served by single connection.
+    ///
+    /// Http/1 response to the last request gets `Connection: close` header,
+    /// http/2 connection is closed gracefully. Limiting number of requests
+    /// lets clients rebalance connections across server instances.
+    ///
+    /// To disable limit set value to 0. By default there is no limit.
+    pub fn max_requests_per_connection(self, val: usize) -> Self {
+        self.config.lock().max_requests_per_connection = val;
+        self
+    }
+
+    /// Set maximum connection lifetime in milliseconds.
+    ///
+    /// Http/1 response sent after connection reaches this age gets
+    /// `Connection: close` header, idle http/1 connection is closed,
+    /// http/2 connection is closed gracefully.
+    ///
+    /// To disable limit set value to 0. By default there is no limit.
+    pub fn max_connection_age(self, val: u64) -> Self {
+        self.config.lock().max_connection_age = val;
+        self
+    }
+
+    /// Set minimum request body data rate.
+    ///
+    /// Connection is dropped if client sends request body slower than
+    /// `bytes_per_sec` bytes per second. Rate is not checked during
+    /// `grace_period` (in milliseconds) and while application does not
+    /// read request payload.
+    ///
+    /// By default minimum rate is not enforced.
+    pub fn min_request_data_rate(self, bytes_per_sec: u64, grace_period: u64) -> Self {
+        self.config.lock().min_request_data_rate = Some((bytes_per_sec, grace_period));
+        self
+    }
+
+    /// Set minimum response data rate.
+    ///
+    /// Connection is dropped if client reads response slower than
+    /// `bytes_per_sec` bytes per second. Rate is not checked during
+    /// `grace_period` (in milliseconds) and while response body is not ready.
+    ///
+    /// By default minimum rate is not enforced.
+    pub fn min_response_data_rate(self, bytes_per_sec: u64, grace_period: u64) -> Self {
+        self.config.lock().min_response_data_rate = Some((bytes_per_sec, grace_period));
+        self
+    }
+
+    /// Set maximum number of headers in http/1 request.
+    ///
+    /// Request with more headers is rejected with
+    /// `431 Request Header Fields Too Large` response.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn h1_max_headers(self, val: usize) -> Self {
+        self.config.lock().h1.max_headers = Some(val);
+        self
+    }
+
+    /// Set maximum size of http/1 request head, request line and headers.
+    ///
+    /// Request with larger head is rejected with
+    /// `431 Request Header Fields Too Large` response.
+    ///
+    /// By default max size is set to 131,072 bytes.
+    pub fn h1_max_header_block_size(self, val: usize) -> Self {
+        self.config.lock().h1.max_header_block_size = Some(val);
+        self
+    }
+
+    /// Set maximum length of http/1 request uri.
+    ///
+    /// Request with longer uri is rejected with `414 URI Too Long` response.
+    ///
+    /// By default max length is set to 65,536 bytes.
+    pub fn h1_max_uri_length(self, val: usize) -> Self {
+        self.config.lock().h1.max_uri_length = Some(val);
+        self
+    }
+
+    /// Set maximum size of single http/1 request header value.
+    ///
+    /// Request with larger header value is rejected with
+    /// `431 Request Header Fields Too Large` response.
+    ///
+    /// By default max size is set to 65,536 bytes.
+    pub fn h1_max_header_value_size(self, val: usize) -> Self {
+        self.config.lock().h1.max_header_value_size = Some(val);
+        self
+    }
+
+    /// Set maximum number of concurrent streams of http/2 connection.
+    ///
+    /// By default there is no limit.
+    pub fn h2_max_concurrent_streams(self, val: u32) -> Self {
+        self.config.lock().h2.max_concurrent_streams = Some(val);
+        self
+    }
+
+    /// Set initial http/2 stream-level flow control window size.
+    ///
+    /// By default window size is set to 65,535 bytes.
+    pub fn h2_initial_window_size(self, val: u32) -> Self {
+        self.config.lock().h2.initial_window_size = Some(val);
+        self
+    }
+
+    /// Set initial http/2 connection-level flow control window size.
+    ///
+    /// By default window size is set to 65,535 bytes.
+    pub fn h2_initial_connection_window_size(self, val: u32) -> Self {
+        self.config.lock().h2.initial_connection_window_size = Some(val);
+        self
+    }
+
+    /// Set maximum http/2 frame size the server is willing to receive.
+    ///
+    /// Value must be between 16,384 and 16,777,215 bytes.
+    /// By default max frame size is set to 16,384 bytes.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if value is out of range.
+    pub fn h2_max_frame_size(self, val: u32) -> Self {
+        assert!(
+            val >= 16_384 && val <= 16_777_215,
+            "http/2 max frame size must be between 16384 and 16777215, got {}",
+            val
+        );
+        self.config.lock().h2.max_frame_size = Some(val);
+        self
+    }
+
+    /// Set maximum size of header list the server is willing to accept
+    /// on http/2 connection.
+    ///
+    /// By default there is no limit.
+    pub fn h2_max_header_list_size(self, val: u32) -> Self {
+        self.config.lock().h2.max_header_list_size = Some(val);
+        self
+    }
+
+    /// Set http/2 connection keep-alive setting.
+    ///
+    /// Http/2 connection without active streams is closed gracefully
+    /// after keep-alive timeout.
+    ///
+    /// By default server keep-alive setting is used.
+    pub fn h2_keep_alive<T: Into<KeepAlive>>(self, val: T) -> Self {
+        self.config.lock().h2.keep_alive = Some(val.into());
+        self
+    }
+
+    /// Set http/2 keep-alive PING interval in milliseconds.
+    ///
+    /// Server sends PING frame with this interval and closes connection
+    /// if client does not acknowledge it within PING timeout.
+    ///
+    /// To disable PING frames set value to 0. By default PING frames
+    /// are disabled.
+    pub fn h2_ping_interval(self, val: u64) -> Self {
+        self.config.lock().h2.ping_interval = Some(val);
+        self
+    }
+
+    /// Set http/2 keep-alive PING timeout in milliseconds.
+    ///
+    /// By default PING timeout is set to 20000 milliseconds.
+    pub fn h2_ping_timeout(self, val: u64) -> Self {
+        self.config.lock().h2.ping_timeout = Some(val);
+        self
+    }
+
+    /// Set per-connection callback.
+    ///
+    /// Callback get called once for each accepted connection with reference
+    /// to the underlying stream, i.e. `tokio_tcp::TcpStream`, tls stream or
+    /// `tokio_uds::UnixStream`. Concrete stream type could be obtained with
+    /// `Any::downcast_ref()`. Result of the call is available to all requests
+    /// received on this connection via `web::ConnectionData<T>` extractor.
+    ///
+    /// ```rust
+    /// use std::any::Any;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use actix_web::{web, App, HttpResponse, HttpServer};
+    ///
+    /// static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct ConnectionId(usize);
+    ///
+    /// fn index(id: web::ConnectionData<ConnectionId>) -> HttpResponse {
+    ///     HttpResponse::Ok().body(format!("connection #{}", id.0))
+    /// }
+    ///
+    /// fn main() {
+    ///     HttpServer::new(|| App::new().route("/", web::get().to(index)))
+    ///         .on_connect(|_: &dyn Any| {
+    ///             ConnectionId(CONNECTIONS.fetch_add(1, Ordering::Relaxed))
+    ///         });
+    /// }
+    /// ```
+    pub fn on_connect<CB, T>(self, f: CB) -> Self
+    where
+        CB: Fn(&dyn Any) -> T + Send + Sync + 'static,
+        T: 'static,
+    {
+        self.config.lock().on_connect =
+            Some(Arc::new(move |io: &dyn Any| -> Rc<dyn Any> {
+                Rc::new(f(io))
+            }));
+        self
+    }
+
     /// Set server host name.
     ///
     /// Host name is used by application router as a hostname for url
@@ -245,10 +566,12 @@ where
             lst,
             move || {
                 let c = cfg.lock();
-                HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout)
-                    .finish(factory())
+                let mut srv = c.service();
+                if let Some(ref f) = c.on_connect {
+                    let f = f.clone();
+                    srv = srv.on_connect(move |io| connection_data(&f, io));
+                }
+                srv.finish(factory())
             },
         )?;
         Ok(self)
@@ -257,7 +580,10 @@ where
     #[cfg(feature = "ssl")]
     /// Use listener for accepting incoming tls connection requests
     ///
-    /// This method sets alpn protocols to "h2" and "http/1.1"
+    /// This method sets alpn protocols to "h2" and "http/1.1".
+    /// If client authentication is enabled, verified client certificate
+    /// is available via [`PeerCertificate`](tls/struct.PeerCertificate.html)
+    /// extractor.
     pub fn listen_ssl(
         self,
         lst: net::TcpListener,
@@ -288,12 +614,16 @@ where
             lst,
             move || {
                 let c = cfg.lock();
+                let mut srv = c
+                    .service()
+                    .client_disconnect(c.client_shutdown)
+                    .on_connect(crate::tls::openssl_info);
+                if let Some(ref f) = c.on_connect {
+                    let f = f.clone();
+                    srv = srv.on_connect(move |io| connection_data(&f, io));
+                }
                 acceptor.clone().map_err(SslError::Ssl).and_then(
-                    HttpService::build()
-                        .keep_alive(c.keep_alive)
-                        .client_timeout(c.client_timeout)
-                        .client_disconnect(c.client_shutdown)
-                        .finish(factory())
+                    srv.finish(factory())
                         .map_err(SslError::Service)
                         .map_init_err(|_| ()),
                 )
@@ -305,7 +635,10 @@ where
     #[cfg(feature = "rust-tls")]
     /// Use listener for accepting incoming tls connection requests
     ///
-    /// This method sets alpn protocols to "h2" and "http/1.1"
+    /// This method sets alpn protocols to "h2" and "http/1.1".
+    /// If client authentication is enabled, verified client certificate
+    /// is available via [`PeerCertificate`](tls/struct.PeerCertificate.html)
+    /// extractor.
     pub fn listen_rustls(
         self,
         lst: net::TcpListener,
@@ -339,12 +672,16 @@ where
             lst,
             move || {
                 let c = cfg.lock();
+                let mut srv = c
+                    .service()
+                    .client_disconnect(c.client_shutdown)
+                    .on_connect(crate::tls::rustls_info);
+                if let Some(ref f) = c.on_connect {
+                    let f = f.clone();
+                    srv = srv.on_connect(move |io| connection_data(&f, io));
+                }
                 acceptor.clone().map_err(SslError::Ssl).and_then(
-                    HttpService::build()
-                        .keep_alive(c.keep_alive)
-                        .client_timeout(c.client_timeout)
-                        .client_disconnect(c.client_shutdown)
-                        .finish(factory())
+                    srv.finish(factory())
                         .map_err(SslError::Service)
                         .map_init_err(|_| ()),
                 )
@@ -422,7 +759,9 @@ where
     #[cfg(feature = "rust-tls")]
     /// Start listening for incoming tls connections.
     ///
-    /// This method sets alpn protocols to "h2" and "http/1.1"
+    /// This method sets alpn protocols to "h2" and "http/1.1".
+    /// Use [`CertResolver`](tls/struct.CertResolver.html) to serve multiple
+    /// SNI hostnames or to reload certificates without server restart.
     pub fn bind_rustls<A: net::ToSocketAddrs>(
         mut self,
         addr: A,
@@ -458,10 +797,12 @@ where
             addr,
             move || {
                 let c = cfg.lock();
-                HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_timeout(c.client_timeout)
-                    .finish(factory())
+                let mut srv = c.service();
+                if let Some(ref f) = c.on_connect {
+                    let f = f.clone();
+                    srv = srv.on_connect(move |io| connection_data(&f, io));
+                }
+                srv.finish(factory())
             },
         )?;
         Ok(self)
@@ -533,6 +874,11 @@ where
     }
 }
 
+/// Erase stream type for `HttpServer::on_connect()` callback.
+fn connection_data<T: 'static>(f: &OnConnectFn, io: &T) -> ConnectionDataAny {
+    ConnectionDataAny(f(io))
+}
+
 fn create_tcp_listener(
     addr: net::SocketAddr,
     backlog: i32,
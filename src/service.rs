@@ -15,10 +15,10 @@ use futures::future::{ok, FutureResult, IntoFuture};
 use crate::config::{AppConfig, AppService};
 use crate::data::Data;
 use crate::dev::insert_slash;
-use crate::guard::Guard;
+use crate::guard::{self, Guard};
 use crate::info::ConnectionInfo;
 use crate::request::HttpRequest;
-use crate::rmap::ResourceMap;
+use crate::rmap::{ResourceMap, ResourceMethods};
 
 pub trait HttpServiceFactory {
     fn register(self, config: &mut AppService);
@@ -490,6 +490,10 @@ where
         > + 'static,
 {
     fn register(mut self, config: &mut AppService) {
+        let methods = ResourceMethods {
+            methods: guard::all_methods(&self.guards),
+            operations: Vec::new(),
+        };
         let guards = if self.guards.is_empty() {
             None
         } else {
@@ -504,7 +508,7 @@ where
         if let Some(ref name) = self.name {
             *rdef.name_mut() = name.clone();
         }
-        config.register_service(rdef, guards, self.srv, None)
+        config.register_resource(rdef, guards, self.srv, methods)
     }
 }
 
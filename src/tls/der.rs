@@ -0,0 +1,274 @@
+//! Minimal DER parser for certificate subject and alternative names,
+//! used when openssl is not available.
+use std::net::IpAddr;
+
+use super::peer::SubjectAltName;
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_NUMERIC_STRING: u8 = 0x12;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_TELETEX_STRING: u8 = 0x14;
+const TAG_IA5_STRING: u8 = 0x16;
+const TAG_VISIBLE_STRING: u8 = 0x1a;
+const TAG_UNIVERSAL_STRING: u8 = 0x1c;
+const TAG_BMP_STRING: u8 = 0x1e;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_VERSION: u8 = 0xa0;
+const TAG_EXTENSIONS: u8 = 0xa3;
+
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+const SUBJECT_ATTRIBUTES: &[(&[u8], &str)] = &[
+    (&[0x55, 0x04, 0x03], "CN"),
+    (&[0x55, 0x04, 0x06], "C"),
+    (&[0x55, 0x04, 0x07], "L"),
+    (&[0x55, 0x04, 0x08], "ST"),
+    (&[0x55, 0x04, 0x0a], "O"),
+    (&[0x55, 0x04, 0x0b], "OU"),
+    (&[0x55, 0x04, 0x05], "serialNumber"),
+    (
+        &[0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19],
+        "DC",
+    ),
+    (
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01],
+        "emailAddress",
+    ),
+];
+
+/// Read single DER element, returns tag, content and remaining data.
+fn read_der(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.get(0)?;
+    let first = *data.get(1)? as usize;
+
+    let (len, offset) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let n = first & 0x7f;
+        if n == 0 || n > 4 || data.len() < 2 + n {
+            return None;
+        }
+        let len = data[2..2 + n]
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | *b as usize);
+        (len, 2 + n)
+    };
+
+    if data.len() - offset < len {
+        return None;
+    }
+    Some((tag, &data[offset..offset + len], &data[offset + len..]))
+}
+
+/// Decode ASN.1 string of the given type, `None` if type is not a string
+/// type or content is not valid for it.
+fn decode_string(tag: u8, value: &[u8]) -> Option<String> {
+    match tag {
+        TAG_UTF8_STRING => String::from_utf8(value.to_vec()).ok(),
+        TAG_NUMERIC_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING
+        | TAG_VISIBLE_STRING => decode_ascii(value),
+        // T.61 is treated as latin-1, same as openssl does
+        TAG_TELETEX_STRING => Some(value.iter().map(|b| *b as char).collect()),
+        TAG_BMP_STRING => {
+            if value.len() % 2 != 0 {
+                return None;
+            }
+            let units = value
+                .chunks(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16(&units).ok()
+        }
+        TAG_UNIVERSAL_STRING => {
+            if value.len() % 4 != 0 {
+                return None;
+            }
+            value
+                .chunks(4)
+                .map(|c| {
+                    std::char::from_u32(u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+                })
+                .collect()
+        }
+        _ => None,
+    }
+}
+
+fn decode_ascii(value: &[u8]) -> Option<String> {
+    if value.is_ascii() {
+        Some(value.iter().map(|b| *b as char).collect())
+    } else {
+        None
+    }
+}
+
+/// Read DER element with expected tag.
+fn expect_der(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match read_der(data)? {
+        (t, content, rest) if t == tag => Some((content, rest)),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_certificate(
+    der: &[u8],
+) -> Option<(Vec<(&'static str, String)>, Vec<SubjectAltName>)> {
+    let (cert, _) = expect_der(der, TAG_SEQUENCE)?;
+    let (tbs, _) = expect_der(cert, TAG_SEQUENCE)?;
+
+    // version is optional
+    let mut data = tbs;
+    if let (TAG_VERSION, _, rest) = read_der(data)? {
+        data = rest;
+    }
+    // serial number, signature algorithm, issuer, validity
+    for _ in 0..4 {
+        data = read_der(data)?.2;
+    }
+    let (subject, mut data) = expect_der(data, TAG_SEQUENCE)?;
+    let subject = parse_name(subject)?;
+
+    // public key info, optional issuer and subject unique ids
+    let mut sans = Vec::new();
+    while !data.is_empty() {
+        let (tag, content, rest) = read_der(data)?;
+        if tag == TAG_EXTENSIONS {
+            sans = parse_extensions(content)?;
+        }
+        data = rest;
+    }
+
+    Some((subject, sans))
+}
+
+fn parse_name(mut data: &[u8]) -> Option<Vec<(&'static str, String)>> {
+    let mut attrs = Vec::new();
+
+    while !data.is_empty() {
+        let (mut rdn, rest) = expect_der(data, TAG_SET)?;
+        data = rest;
+
+        while !rdn.is_empty() {
+            let (atv, rest) = expect_der(rdn, TAG_SEQUENCE)?;
+            rdn = rest;
+
+            let (oid, value) = expect_der(atv, TAG_OID)?;
+            let (tag, value, _) = read_der(value)?;
+            // attributes of unknown type or with undecodable value are skipped
+            let name = SUBJECT_ATTRIBUTES.iter().find(|(o, _)| *o == oid);
+            if let (Some((_, name)), Some(value)) = (name, decode_string(tag, value)) {
+                attrs.push((*name, value));
+            }
+        }
+    }
+    Some(attrs)
+}
+
+fn parse_extensions(data: &[u8]) -> Option<Vec<SubjectAltName>> {
+    let (mut data, _) = expect_der(data, TAG_SEQUENCE)?;
+
+    while !data.is_empty() {
+        let (ext, rest) = expect_der(data, TAG_SEQUENCE)?;
+        data = rest;
+
+        let (oid, mut ext) = expect_der(ext, TAG_OID)?;
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        // critical flag is optional
+        if let (TAG_BOOLEAN, _, rest) = read_der(ext)? {
+            ext = rest;
+        }
+        let (value, _) = expect_der(ext, TAG_OCTET_STRING)?;
+        return parse_alt_names(value);
+    }
+    Some(Vec::new())
+}
+
+fn parse_alt_names(data: &[u8]) -> Option<Vec<SubjectAltName>> {
+    let (mut data, _) = expect_der(data, TAG_SEQUENCE)?;
+    let mut names = Vec::new();
+
+    while !data.is_empty() {
+        let (tag, value, rest) = read_der(data)?;
+        data = rest;
+
+        // rfc822Name, dNSName and uniformResourceIdentifier are IA5String
+        match tag {
+            0x81 => names.extend(decode_ascii(value).map(SubjectAltName::Email)),
+            0x82 => names.extend(decode_ascii(value).map(SubjectAltName::Dns)),
+            0x86 => names.extend(decode_ascii(value).map(SubjectAltName::Uri)),
+            0x87 => match value.len() {
+                4 => {
+                    let mut addr = [0u8; 4];
+                    addr.copy_from_slice(value);
+                    names.push(SubjectAltName::Ip(IpAddr::from(addr)));
+                }
+                16 => {
+                    let mut addr = [0u8; 16];
+                    addr.copy_from_slice(value);
+                    names.push(SubjectAltName::Ip(IpAddr::from(addr)));
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    Some(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_string() {
+        assert_eq!(
+            decode_string(TAG_UTF8_STRING, "Zürich".as_bytes()),
+            Some("Zürich".to_owned())
+        );
+        assert_eq!(decode_string(TAG_UTF8_STRING, b"\xff"), None);
+        assert_eq!(
+            decode_string(TAG_PRINTABLE_STRING, b"localhost"),
+            Some("localhost".to_owned())
+        );
+        assert_eq!(decode_string(TAG_IA5_STRING, b"caf\xe9"), None);
+        assert_eq!(
+            decode_string(TAG_TELETEX_STRING, b"caf\xe9"),
+            Some("café".to_owned())
+        );
+        assert_eq!(
+            decode_string(TAG_BMP_STRING, b"\x00Z\x00\xfc\x04\x1f"),
+            Some("ZüП".to_owned())
+        );
+        assert_eq!(decode_string(TAG_BMP_STRING, b"\x00Z\x00"), None);
+        assert_eq!(
+            decode_string(TAG_UNIVERSAL_STRING, b"\x00\x00\x00Z\x00\x01\xf6\x00"),
+            Some("Z\u{1f600}".to_owned())
+        );
+        assert_eq!(
+            decode_string(TAG_UNIVERSAL_STRING, b"\x00\x11\x00\x00"),
+            None
+        );
+        assert_eq!(decode_string(TAG_OCTET_STRING, b"localhost"), None);
+    }
+
+    #[test]
+    fn test_parse_name() {
+        // CN as OCTET STRING, O as UTF8String
+        let name = b"\x31\x0c\x30\x0a\x06\x03\x55\x04\x03\x04\x03abc\
+                     \x31\x10\x30\x0e\x06\x03\x55\x04\x0a\x0c\x07Example";
+        assert_eq!(parse_name(name), Some(vec![("O", "Example".to_owned())]));
+
+        // IA5String with non-ascii content
+        let names = b"\x30\x0d\x82\x04caf\xe9\x82\x05local";
+        assert_eq!(
+            parse_alt_names(names),
+            Some(vec![SubjectAltName::Dns("local".to_owned())])
+        );
+    }
+}
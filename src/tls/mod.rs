@@ -0,0 +1,115 @@
+//! TLS support utilities for `HttpServer`
+//!
+//! [`CertResolver`](struct.CertResolver.html) selects server certificate
+//! by the SNI hostname presented by the client and allows to replace
+//! certificates without restarting the server. It is available with
+//! `rust-tls` feature.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "rust-tls")]
+//! # mod example {
+//! use std::time::Duration;
+//! use actix_web::{tls::CertResolver, web, App, HttpResponse, HttpServer};
+//!
+//! # pub
+//! fn main() -> std::io::Result<()> {
+//!     let resolver = CertResolver::new()
+//!         .cert("www.rust-lang.org", "rust-lang.pem", "rust-lang.key")?
+//!         .cert("crates.io", "crates.pem", "crates.key")?;
+//!
+//!     // re-read certificates whenever files change on disk
+//!     resolver.watch(Duration::from_secs(10));
+//!
+//!     HttpServer::new(|| App::new().route("/", web::get().to(|| HttpResponse::Ok())))
+//!         .bind_rustls("0.0.0.0:443", resolver.server_config())?
+//!         .run()
+//! }
+//! # }
+//! # #[cfg(feature = "rust-tls")]
+//! # fn main() -> std::io::Result<()> { example::main() }
+//! # #[cfg(not(feature = "rust-tls"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`PeerCertificate`](struct.PeerCertificate.html) extractor provides
+//! access to the verified client certificate of mutual tls connections.
+//!
+//! ```rust
+//! use actix_web::{tls::PeerCertificate, HttpResponse};
+//!
+//! fn index(cert: PeerCertificate) -> HttpResponse {
+//!     if cert.subject_alt_names().iter().any(|n| n.dns() == Some("billing.svc")) {
+//!         HttpResponse::Ok().finish()
+//!     } else {
+//!         HttpResponse::Forbidden().finish()
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+#[cfg(not(feature = "ssl"))]
+mod der;
+mod peer;
+#[cfg(feature = "rust-tls")]
+mod resolver;
+
+pub use self::peer::{PeerCertificate, SubjectAltName};
+#[cfg(feature = "rust-tls")]
+pub use self::resolver::CertResolver;
+
+/// Tls connection details captured during connection setup.
+#[derive(Clone)]
+pub(crate) struct TlsInfo {
+    pub(crate) sni: Option<String>,
+    pub(crate) peer: Option<PeerCertificate>,
+}
+
+#[cfg(feature = "ssl")]
+/// On-connect callback for openssl connections.
+pub(crate) fn openssl_info<T>(io: &tokio_openssl::SslStream<T>) -> TlsInfo {
+    use openssl::ssl::NameType;
+    use openssl::x509::X509VerifyResult;
+
+    let ssl = io.get_ref().ssl();
+
+    // openssl returns peer certificate even if verification has failed
+    let peer = if ssl.verify_result() == X509VerifyResult::OK {
+        ssl.peer_certificate().and_then(|cert| {
+            let chain = ssl
+                .peer_cert_chain()
+                .map(|chain| chain.iter().filter_map(|c| c.to_der().ok()).collect())
+                .unwrap_or_default();
+            PeerCertificate::from_der(cert.to_der().ok()?, chain)
+        })
+    } else {
+        None
+    };
+
+    TlsInfo {
+        peer,
+        sni: ssl.servername(NameType::HOST_NAME).map(|s| s.to_owned()),
+    }
+}
+
+#[cfg(feature = "rust-tls")]
+/// On-connect callback for rustls connections.
+pub(crate) fn rustls_info<T>(
+    io: &tokio_rustls::TlsStream<T, rustls::ServerSession>,
+) -> TlsInfo {
+    use rustls::Session;
+
+    let session = io.get_ref().1;
+
+    // rustls returns peer certificates only after successful verification
+    let peer = session.get_peer_certificates().and_then(|mut certs| {
+        if certs.is_empty() {
+            return None;
+        }
+        let cert = certs.remove(0);
+        PeerCertificate::from_der(cert.0, certs.into_iter().map(|c| c.0).collect())
+    });
+
+    TlsInfo {
+        peer,
+        sni: session.get_sni_hostname().map(|s| s.to_owned()),
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::rc::Rc;
+
+use actix_http::error::{Error, ErrorForbidden};
+use actix_http::{Payload, RequestHead};
+
+use crate::extract::FromRequest;
+use crate::request::HttpRequest;
+
+#[cfg(not(feature = "ssl"))]
+use super::der::parse_certificate;
+use super::TlsInfo;
+
+/// Verified certificate presented by the client during tls handshake.
+///
+/// Client certificate is available only if server's tls configuration
+/// requests client authentication. If client did not present certificate,
+/// extractor returns *403 Forbidden* error, use `Option<PeerCertificate>`
+/// if client certificate is optional.
+///
+/// Certificate could be used in guards as well.
+///
+/// ```rust
+/// use actix_web::{guard, tls::PeerCertificate, web, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/admin")
+///             .guard(guard::fn_guard(|head| {
+///                 PeerCertificate::from_head(head)
+///                     .map(|cert| cert.common_name() == Some("admin"))
+///                     .unwrap_or(false)
+///             }))
+///             .to(|| HttpResponse::Ok()),
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PeerCertificate(Rc<Inner>);
+
+struct Inner {
+    der: Vec<u8>,
+    chain: Vec<Vec<u8>>,
+    subject: Vec<(&'static str, String)>,
+    sans: Vec<SubjectAltName>,
+    fingerprint: Vec<u8>,
+}
+
+/// Subject alternative name of the certificate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubjectAltName {
+    /// DNS name
+    Dns(String),
+    /// Email address
+    Email(String),
+    /// URI
+    Uri(String),
+    /// IP address
+    Ip(IpAddr),
+}
+
+impl SubjectAltName {
+    /// DNS name, if this is a `Dns` entry.
+    pub fn dns(&self) -> Option<&str> {
+        match self {
+            SubjectAltName::Dns(ref name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl PeerCertificate {
+    /// Parse DER encoded certificate.
+    ///
+    /// `chain` contains DER encoded intermediate certificates presented
+    /// by the client. Returns `None` if certificate could not be parsed.
+    pub fn from_der(der: Vec<u8>, chain: Vec<Vec<u8>>) -> Option<PeerCertificate> {
+        let (subject, sans) = parse_certificate(&der)?;
+        let fingerprint = sha256(&der);
+
+        Some(PeerCertificate(Rc::new(Inner {
+            der,
+            chain,
+            subject,
+            sans,
+            fingerprint,
+        })))
+    }
+
+    /// Get client certificate of the connection the request was received on.
+    pub fn from_head(head: &RequestHead) -> Option<PeerCertificate> {
+        head.extensions()
+            .get::<TlsInfo>()
+            .and_then(|info| info.peer.clone())
+    }
+
+    /// Certificate subject, formatted as comma separated `TYPE=value`
+    /// attributes, i.e. `C=US, O=Example, CN=example.com`.
+    pub fn subject(&self) -> String {
+        self.0
+            .subject
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Common name (CN) attribute of the certificate subject.
+    pub fn common_name(&self) -> Option<&str> {
+        self.0
+            .subject
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == "CN")
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Subject alternative names of the certificate.
+    pub fn subject_alt_names(&self) -> &[SubjectAltName] {
+        &self.0.sans
+    }
+
+    /// SHA-256 fingerprint of the DER encoded certificate.
+    pub fn fingerprint(&self) -> &[u8] {
+        &self.0.fingerprint
+    }
+
+    /// SHA-256 fingerprint as lowercase hex string.
+    pub fn fingerprint_hex(&self) -> String {
+        self.0
+            .fingerprint
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// DER encoded certificate.
+    pub fn der(&self) -> &[u8] {
+        &self.0.der
+    }
+
+    /// DER encoded intermediate certificates presented by the client.
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.0.chain
+    }
+}
+
+impl fmt::Debug for PeerCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PeerCertificate")
+            .field("subject", &self.subject())
+            .field("subject_alt_names", &self.0.sans)
+            .field("fingerprint", &self.fingerprint_hex())
+            .finish()
+    }
+}
+
+impl FromRequest for PeerCertificate {
+    type Config = ();
+    type Error = Error;
+    type Future = Result<Self, Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(cert) = PeerCertificate::from_head(req.head()) {
+            Ok(cert)
+        } else {
+            log::debug!(
+                "Failed to construct PeerCertificate extractor. \
+                 Request path: {:?}",
+                req.path()
+            );
+            Err(ErrorForbidden("Client certificate is required"))
+        }
+    }
+}
+
+#[cfg(feature = "rust-tls")]
+fn sha256(data: &[u8]) -> Vec<u8> {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .to_vec()
+}
+
+#[cfg(not(feature = "rust-tls"))]
+fn sha256(data: &[u8]) -> Vec<u8> {
+    openssl::sha::sha256(data).to_vec()
+}
+
+#[cfg(feature = "ssl")]
+fn parse_certificate(
+    der: &[u8],
+) -> Option<(Vec<(&'static str, String)>, Vec<SubjectAltName>)> {
+    use openssl::x509::X509;
+
+    let cert = X509::from_der(der).ok()?;
+
+    // attributes of unknown type or with undecodable value are skipped
+    let subject = cert
+        .subject_name()
+        .entries()
+        .filter_map(|entry| {
+            let name = entry.object().nid().short_name().ok()?;
+            // `to_string()` is not available in older openssl 0.10 releases
+            #[allow(deprecated)]
+            let value = entry.data().as_utf8().ok()?;
+            Some((name, value.to_string()))
+        })
+        .collect();
+
+    let sans = cert
+        .subject_alt_names()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| {
+                    if let Some(dns) = name.dnsname() {
+                        Some(SubjectAltName::Dns(dns.to_owned()))
+                    } else if let Some(email) = name.email() {
+                        Some(SubjectAltName::Email(email.to_owned()))
+                    } else if let Some(uri) = name.uri() {
+                        Some(SubjectAltName::Uri(uri.to_owned()))
+                    } else {
+                        name.ipaddress().and_then(parse_ip)
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some((subject, sans))
+}
+
+#[cfg(feature = "ssl")]
+fn parse_ip(addr: &[u8]) -> Option<SubjectAltName> {
+    match addr.len() {
+        4 => {
+            let mut ip = [0u8; 4];
+            ip.copy_from_slice(addr);
+            Some(SubjectAltName::Ip(IpAddr::from(ip)))
+        }
+        16 => {
+            let mut ip = [0u8; 16];
+            ip.copy_from_slice(addr);
+            Some(SubjectAltName::Ip(IpAddr::from(ip)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    fn test_cert() -> Vec<u8> {
+        let pem = std::fs::read_to_string("tests/cert.pem").unwrap();
+        let b64: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        base64::decode(&b64).unwrap()
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let cert = PeerCertificate::from_der(test_cert(), Vec::new()).unwrap();
+        assert_eq!(
+            cert.subject(),
+            "C=RU, ST=Saint-Petersburg, L=Saint-Petersburg, O=Kupibilet, CN=localhost"
+        );
+        assert_eq!(cert.common_name(), Some("localhost"));
+        assert_eq!(
+            cert.subject_alt_names(),
+            &[
+                SubjectAltName::Dns("localhost".to_owned()),
+                SubjectAltName::Dns("127.0.0.1".to_owned())
+            ]
+        );
+        assert_eq!(cert.fingerprint().len(), 32);
+        assert_eq!(cert.fingerprint_hex().len(), 64);
+        assert!(cert.chain().is_empty());
+
+        assert!(PeerCertificate::from_der(b"invalid".to_vec(), Vec::new()).is_none());
+        assert!(PeerCertificate::from_der(Vec::new(), Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_extractor() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(PeerCertificate::from_request(&req, &mut pl).is_err());
+
+        let cert = PeerCertificate::from_der(test_cert(), Vec::new());
+        req.head().extensions_mut().insert(TlsInfo {
+            sni: None,
+            peer: cert,
+        });
+        let cert = PeerCertificate::from_request(&req, &mut pl).unwrap();
+        assert_eq!(cert.common_name(), Some("localhost"));
+    }
+}
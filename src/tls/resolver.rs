@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+use std::{fmt, thread};
+
+use parking_lot::{Mutex, RwLock};
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::sign::{self, CertifiedKey};
+use rustls::{NoClientAuth, ResolvesServerCert, ServerConfig, SignatureScheme};
+
+/// Certificate resolver with SNI based certificate selection.
+///
+/// Certificates and private keys are loaded from PEM files. Private key
+/// could be in PKCS8 or RSA format. Resolver could be cloned, all clones
+/// share same set of certificates.
+///
+/// If client does not send SNI extension or hostname is not registered,
+/// default certificate is used. If default certificate is not set,
+/// handshake fails.
+#[derive(Clone)]
+pub struct CertResolver(Arc<Inner>);
+
+struct Inner {
+    sources: Mutex<Vec<Source>>,
+    keys: RwLock<Keys>,
+}
+
+#[derive(Default)]
+struct Keys {
+    names: HashMap<String, CertifiedKey>,
+    default: Option<CertifiedKey>,
+}
+
+struct Source {
+    name: Option<String>,
+    cert: PathBuf,
+    key: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl Default for CertResolver {
+    fn default() -> Self {
+        CertResolver::new()
+    }
+}
+
+impl CertResolver {
+    /// Create new resolver without any certificates.
+    pub fn new() -> Self {
+        CertResolver(Arc::new(Inner {
+            sources: Mutex::new(Vec::new()),
+            keys: RwLock::new(Keys::default()),
+        }))
+    }
+
+    /// Register certificate for SNI hostname.
+    ///
+    /// Certificate chain and private key get loaded immediately.
+    pub fn cert<N, C, K>(self, name: N, cert: C, key: K) -> io::Result<Self>
+    where
+        N: AsRef<str>,
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        self.add_source(Some(name.as_ref().to_lowercase()), cert, key)?;
+        Ok(self)
+    }
+
+    /// Set default certificate.
+    ///
+    /// Default certificate is used if client does not send SNI hostname
+    /// or there is no certificate registered for requested hostname.
+    pub fn default_cert<C, K>(self, cert: C, key: K) -> io::Result<Self>
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        self.add_source(None, cert, key)?;
+        Ok(self)
+    }
+
+    fn add_source<C, K>(&self, name: Option<String>, cert: C, key: K) -> io::Result<()>
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        let mut src = Source {
+            name,
+            cert: cert.as_ref().to_owned(),
+            key: key.as_ref().to_owned(),
+            modified: None,
+        };
+        let ck = src.load()?;
+
+        let mut keys = self.0.keys.write();
+        if let Some(ref name) = src.name {
+            keys.names.insert(name.clone(), ck);
+        } else {
+            keys.default = Some(ck);
+        }
+
+        let mut sources = self.0.sources.lock();
+        sources.retain(|s| s.name != src.name);
+        sources.push(src);
+        Ok(())
+    }
+
+    /// Re-read all registered certificates and private keys.
+    ///
+    /// Certificates get replaced only if all files could be loaded,
+    /// otherwise currently active certificates stay in use.
+    pub fn reload(&self) -> io::Result<()> {
+        let mut sources = self.0.sources.lock();
+
+        let mut keys = Keys::default();
+        for src in sources.iter_mut() {
+            let ck = src.load()?;
+            if let Some(ref name) = src.name {
+                keys.names.insert(name.clone(), ck);
+            } else {
+                keys.default = Some(ck);
+            }
+        }
+        *self.0.keys.write() = keys;
+        Ok(())
+    }
+
+    /// Re-read certificates if any of the files has been modified
+    /// since last load.
+    fn reload_modified(&self) -> io::Result<bool> {
+        let modified = self
+            .0
+            .sources
+            .lock()
+            .iter()
+            .any(|src| src.modified != src.last_modified());
+        if modified {
+            self.reload()?;
+        }
+        Ok(modified)
+    }
+
+    /// Watch certificate files for changes.
+    ///
+    /// This method spawns a thread that checks modification time of
+    /// registered files every `interval` and reloads certificates when
+    /// any of them changes. Thread exits when all clones of the resolver
+    /// get dropped.
+    pub fn watch(&self, interval: Duration) {
+        let inner = Arc::downgrade(&self.0);
+
+        thread::Builder::new()
+            .name("actix-web-cert-watcher".to_owned())
+            .spawn(move || watch(inner, interval))
+            .expect("Can not spawn certificate watcher thread");
+    }
+
+    #[cfg(unix)]
+    /// Reload certificates when process receives `SIGHUP` signal.
+    ///
+    /// This method must be called from within running actix system,
+    /// for example from `HttpServer` factory or before `HttpServer::start()`.
+    pub fn reload_on_signal(&self) {
+        use futures::{Future, Stream};
+        use tokio_signal::unix::{Signal, SIGHUP};
+
+        let resolver = self.clone();
+        actix_rt::spawn(
+            Signal::new(SIGHUP)
+                .flatten_stream()
+                .for_each(move |_| {
+                    log::info!("SIGHUP received, reloading certificates");
+                    if let Err(e) = resolver.reload() {
+                        log::error!("Can not reload certificates: {}", e);
+                    }
+                    Ok(())
+                })
+                .map_err(|e| log::error!("Can not listen for SIGHUP: {}", e)),
+        );
+    }
+
+    /// Create rustls `ServerConfig` that uses this resolver.
+    ///
+    /// Client authentication is disabled.
+    pub fn server_config(&self) -> ServerConfig {
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        config.cert_resolver = Arc::new(self.clone());
+        config
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(
+        &self,
+        server_name: Option<webpki::DNSNameRef>,
+        _: &[SignatureScheme],
+    ) -> Option<CertifiedKey> {
+        let keys = self.0.keys.read();
+        if let Some(name) = server_name {
+            let name: &str = name.into();
+            if let Some(ck) = keys.names.get(&name.to_lowercase()) {
+                return Some(ck.clone());
+            }
+        }
+        keys.default.clone()
+    }
+}
+
+impl fmt::Debug for CertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys = self.0.keys.read();
+        f.debug_struct("CertResolver")
+            .field("names", &keys.names.keys().collect::<Vec<_>>())
+            .field("default", &keys.default.is_some())
+            .finish()
+    }
+}
+
+fn watch(inner: Weak<Inner>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+
+        let resolver = match inner.upgrade() {
+            Some(inner) => CertResolver(inner),
+            None => return,
+        };
+        match resolver.reload_modified() {
+            Ok(true) => log::info!("Certificates reloaded"),
+            Ok(false) => (),
+            Err(e) => log::error!("Can not reload certificates: {}", e),
+        }
+    }
+}
+
+impl Source {
+    fn last_modified(&self) -> Option<SystemTime> {
+        let cert = fs::metadata(&self.cert).and_then(|m| m.modified()).ok();
+        let key = fs::metadata(&self.key).and_then(|m| m.modified()).ok();
+        cert.max(key)
+    }
+
+    fn load(&mut self) -> io::Result<CertifiedKey> {
+        let modified = self.last_modified();
+        let ck = load_certified_key(&self.cert, &self.key)?;
+        self.modified = modified;
+        Ok(ck)
+    }
+}
+
+fn load_certified_key(cert: &Path, key: &Path) -> io::Result<CertifiedKey> {
+    let chain = certs(&mut BufReader::new(File::open(cert)?))
+        .map_err(|_| invalid_data("Can not parse certificate", cert))?;
+    if chain.is_empty() {
+        return Err(invalid_data("No certificates found", cert));
+    }
+
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(key)?))
+        .map_err(|_| invalid_data("Can not parse private key", key))?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut BufReader::new(File::open(key)?))
+            .map_err(|_| invalid_data("Can not parse private key", key))?;
+    }
+    let key = match keys.into_iter().next() {
+        Some(k) => sign::any_supported_type(&k)
+            .map_err(|_| invalid_data("Unsupported private key type", key))?,
+        None => return Err(invalid_data("No private keys found", key)),
+    };
+
+    Ok(CertifiedKey::new(chain, Arc::new(key)))
+}
+
+fn invalid_data(msg: &str, path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", msg, path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let resolver = CertResolver::new();
+        assert!(resolver.resolve(None, &[]).is_none());
+
+        let resolver = resolver
+            .cert("LocalHost", "tests/cert.pem", "tests/key.pem")
+            .unwrap();
+        let name = webpki::DNSNameRef::try_from_ascii_str("localhost").unwrap();
+        assert!(resolver.resolve(Some(name), &[]).is_some());
+        let name = webpki::DNSNameRef::try_from_ascii_str("rust-lang.org").unwrap();
+        assert!(resolver.resolve(Some(name), &[]).is_none());
+        assert!(resolver.resolve(None, &[]).is_none());
+
+        let resolver = resolver
+            .default_cert("tests/cert.pem", "tests/key.pem")
+            .unwrap();
+        assert!(resolver.resolve(Some(name), &[]).is_some());
+        assert!(resolver.resolve(None, &[]).is_some());
+        assert!(resolver.reload().is_ok());
+        assert_eq!(resolver.reload_modified().unwrap(), false);
+    }
+
+    #[test]
+    fn test_load_error() {
+        let res =
+            CertResolver::new().cert("localhost", "tests/test.png", "tests/key.pem");
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let res = CertResolver::new().default_cert("tests/cert.pem", "tests/cert.pem");
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let res = CertResolver::new().default_cert("tests/missing.pem", "tests/key.pem");
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
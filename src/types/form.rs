@@ -19,6 +19,7 @@ use crate::http::{
 };
 use crate::request::HttpRequest;
 use crate::responder::Responder;
+use crate::types::payload::DecompressConfig;
 
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
@@ -269,7 +270,7 @@ impl<U> UrlEncoded<U> {
             }
         };
 
-        let payload = Decompress::from_headers(payload.take(), req.headers());
+        let payload = DecompressConfig::decompress(req, payload.take());
         UrlEncoded {
             encoding,
             stream: Some(payload),
@@ -482,5 +483,4 @@ mod tests {
         use crate::responder::tests::BodyTest;
         assert_eq!(resp.body().bin_ref(), b"hello=world&counter=123");
     }
-
 }
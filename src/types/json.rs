@@ -17,6 +17,7 @@ use crate::error::{Error, JsonPayloadError};
 use crate::extract::FromRequest;
 use crate::request::HttpRequest;
 use crate::responder::Responder;
+use crate::types::payload::DecompressConfig;
 
 /// Json helper
 ///
@@ -327,7 +328,7 @@ where
             .get(&CONTENT_LENGTH)
             .and_then(|l| l.to_str().ok())
             .and_then(|s| s.parse::<usize>().ok());
-        let payload = Decompress::from_headers(payload.take(), req.headers());
+        let payload = DecompressConfig::decompress(req, payload.take());
 
         JsonBody {
             limit: 262_144,
@@ -526,6 +527,38 @@ mod tests {
         assert!(format!("{}", s.err().unwrap()).contains("Content type error"));
     }
 
+    #[test]
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    fn test_compressed_json_above_8mb() {
+        use std::io::Write;
+
+        use flate2::{write::GzEncoder, Compression};
+
+        // pseudo random name, compresses well below default max ratio
+        let mut seed = 1u32;
+        let name: String = (0..9_000_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (b'a' + (seed >> 16) as u8 % 26) as char
+            })
+            .collect();
+        let body = serde_json::to_vec(&MyObject { name: name.clone() }).unwrap();
+        let mut enc = GzEncoder::new(Vec::new(), Compression::fast());
+        enc.write_all(&body).unwrap();
+        let data = Bytes::from(enc.finish().unwrap());
+
+        let (req, mut pl) = TestRequest::default()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .header(header::CONTENT_LENGTH, data.len().to_string())
+            .set_payload(data)
+            .data(JsonConfig::default().limit(16_777_216))
+            .to_http_parts();
+
+        let s = block_on(Json::<MyObject>::from_request(&req, &mut pl)).unwrap();
+        assert_eq!(s.name, name);
+    }
+
     #[test]
     fn test_json_body() {
         let (req, mut pl) = TestRequest::default().to_http_parts();
@@ -10,5 +10,5 @@ pub(crate) mod readlines;
 pub use self::form::{Form, FormConfig};
 pub use self::json::{Json, JsonConfig};
 pub use self::path::{Path, PathConfig};
-pub use self::payload::{Payload, PayloadConfig};
+pub use self::payload::{DecompressConfig, Payload, PayloadConfig};
 pub use self::query::{Query, QueryConfig};
@@ -45,6 +45,16 @@ use crate::request::HttpRequest;
 /// ```
 pub struct Payload(crate::dev::Payload);
 
+impl Payload {
+    /// Trailing headers of request payload
+    ///
+    /// Trailers are available after payload stream is complete, i.e.
+    /// chunked http/1 trailer section or trailing http/2 headers frame.
+    pub fn trailers(&self) -> Option<header::HeaderMap> {
+        self.0.trailers()
+    }
+}
+
 impl Stream for Payload {
     type Item = Bytes;
     type Error = PayloadError;
@@ -281,6 +291,89 @@ impl Default for PayloadConfig {
     }
 }
 
+/// Decompression limits for request's payload.
+///
+/// Limits apply to compressed payloads decoded by `Bytes`, `String`,
+/// `Json` and `Form` extractors. If any limit is exceeded,
+/// `PayloadError::DecompressionLimit` get returned, it is converted to
+/// *413 Payload Too Large* response. Config could be registered
+/// per application or per resource with `.data()` method.
+///
+/// ```rust
+/// use actix_web::{web, App};
+///
+/// fn index(body: String) -> String {
+///     body
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .data(web::DecompressConfig::default().limit(1_048_576))
+///         .service(
+///             web::resource("/index.html")
+///                 .data(web::DecompressConfig::default().max_ratio(10))
+///                 .route(web::post().to(index)),
+///         );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct DecompressConfig {
+    limit: Option<usize>,
+    max_ratio: usize,
+}
+
+impl DecompressConfig {
+    /// Change max size of decompressed payload.
+    ///
+    /// By default decompressed payload size is limited only by the limit
+    /// of the extractor, i.e. `PayloadConfig::limit()` or
+    /// `JsonConfig::limit()`.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Change max ratio between decompressed and compressed payload sizes.
+    ///
+    /// Ratio is checked only after decompressed payload reaches 64Kb.
+    /// By default max ratio is 100.
+    pub fn max_ratio(mut self, ratio: usize) -> Self {
+        self.max_ratio = ratio;
+        self
+    }
+
+    /// Create payload decoder with limits registered for the request
+    pub(crate) fn decompress(
+        req: &HttpRequest,
+        payload: dev::Payload,
+    ) -> dev::Decompress<dev::Payload> {
+        let tmp;
+        let cfg = if let Some(cfg) = req.app_data::<DecompressConfig>() {
+            cfg
+        } else {
+            tmp = DecompressConfig::default();
+            &tmp
+        };
+
+        let decompress = dev::Decompress::from_headers(payload, req.headers())
+            .max_ratio(cfg.max_ratio);
+        if let Some(limit) = cfg.limit {
+            decompress.max_size(limit)
+        } else {
+            decompress
+        }
+    }
+}
+
+impl Default for DecompressConfig {
+    fn default() -> Self {
+        DecompressConfig {
+            limit: None,
+            max_ratio: 100,
+        }
+    }
+}
+
 /// Future that resolves to a complete http message body.
 ///
 /// Load http message body.
@@ -313,7 +406,7 @@ impl HttpMessageBody {
         }
 
         HttpMessageBody {
-            stream: Some(dev::Decompress::from_headers(payload.take(), req.headers())),
+            stream: Some(DecompressConfig::decompress(req, payload.take())),
             limit: 262_144,
             length: len,
             fut: None,
@@ -459,4 +552,44 @@ mod tests {
             _ => unreachable!("error"),
         }
     }
+
+    #[test]
+    #[cfg(any(feature = "flate2-zlib", feature = "flate2-rust"))]
+    fn test_decompress_limits() {
+        use std::io::Write;
+
+        use flate2::{write::GzEncoder, Compression};
+
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(&[0u8; 131_072]).unwrap();
+        let data = Bytes::from(enc.finish().unwrap());
+
+        // default max ratio
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_ENCODING, "gzip")
+            .set_payload(data.clone())
+            .to_http_parts();
+        let res = block_on(HttpMessageBody::new(&req, &mut pl).limit(1_048_576));
+        match res.err().unwrap() {
+            PayloadError::DecompressionLimit => (),
+            _ => unreachable!("error"),
+        }
+
+        // max size
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_ENCODING, "gzip")
+            .data(DecompressConfig::default().max_ratio(10_000).limit(65_536))
+            .set_payload(data.clone())
+            .to_http_parts();
+        let res = block_on(HttpMessageBody::new(&req, &mut pl).limit(1_048_576));
+        match res.err().unwrap() {
+            PayloadError::DecompressionLimit => (),
+            _ => unreachable!("error"),
+        }
+
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_ENCODING, "gzip")
+            .data(DecompressConfig::default().max_ratio(10_000))
+            .set_payload(data)
+            .to_http_parts();
+        let res = block_on(HttpMessageBody::new(&req, &mut pl).limit(1_048_576));
+        assert_eq!(res.unwrap().len(), 131_072);
+    }
 }
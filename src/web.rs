@@ -16,10 +16,13 @@ use crate::scope::Scope;
 use crate::service::WebService;
 
 pub use crate::config::ServiceConfig;
-pub use crate::data::Data;
+pub use crate::data::{ConnectionData, Data};
 pub use crate::request::HttpRequest;
 pub use crate::types::*;
 
+pub mod sse;
+pub mod ws;
+
 /// Create resource for a specific path.
 ///
 /// Resources may have variable path segments. For example, a
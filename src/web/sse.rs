@@ -0,0 +1,386 @@
+//! Server-Sent Events
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use actix_web::web::{self, sse};
+//! use actix_web::{App, Error};
+//! use futures::{stream, Stream};
+//!
+//! fn events(
+//!     last_id: Option<sse::LastEventId>,
+//! ) -> sse::Sse<impl Stream<Item = sse::Event, Error = Error>> {
+//!     let start = last_id
+//!         .and_then(|id| id.parse::<u32>().ok())
+//!         .map(|id| id + 1)
+//!         .unwrap_or(0);
+//!
+//!     let events = stream::iter_ok((start..start + 3).map(|id| {
+//!         sse::Event::new()
+//!             .event("counter")
+//!             .id(id.to_string())
+//!             .data(format!("value {}", id))
+//!     }));
+//!     sse::Sse::new(events).keep_alive(Duration::from_secs(10))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().service(web::resource("/events").to(events));
+//! }
+//! ```
+use std::fmt::Write;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use actix_http::http::header::{self, ContentEncoding, HeaderName};
+use actix_http::Response;
+use bytes::{Bytes, BytesMut};
+use futures::{Async, Future, Poll, Stream};
+use tokio_timer::Delay;
+
+use crate::dev::Payload;
+use crate::error::{Error, ErrorBadRequest};
+use crate::extract::FromRequest;
+use crate::middleware::BodyEncoding;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+/// Default keep-alive interval
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Keep-alive comment
+const KEEP_ALIVE_COMMENT: &[u8] = b":\n\n";
+
+/// Single server-sent event
+///
+/// Data could contain multiple lines, every line is sent as separate `data`
+/// field. Event name, id and comment must be single line, only the first
+/// line is sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Event {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+impl Event {
+    /// Create empty event
+    pub fn new() -> Self {
+        Event::default()
+    }
+
+    /// Set event data
+    pub fn data<T: Into<String>>(mut self, data: T) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Set event name, `message` is used by client if name is not set
+    pub fn event<T: Into<String>>(mut self, event: T) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set event id, client sends last received id in `Last-Event-ID`
+    /// header on reconnect
+    pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set client reconnection time
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Set comment, comments are ignored by client
+    pub fn comment<T: Into<String>>(mut self, comment: T) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        let mut s = String::new();
+        if let Some(ref comment) = self.comment {
+            let _ = writeln!(s, ":{}", first_line(comment));
+        }
+        if let Some(ref event) = self.event {
+            let _ = writeln!(s, "event:{}", first_line(event));
+        }
+        if let Some(ref id) = self.id {
+            let _ = writeln!(s, "id:{}", first_line(id));
+        }
+        if let Some(retry) = self.retry {
+            let ms = retry.as_secs() * 1000 + u64::from(retry.subsec_millis());
+            let _ = writeln!(s, "retry:{}", ms);
+        }
+        if let Some(ref data) = self.data {
+            // every line of data, including empty last line, is a data field
+            let mut rest = data.as_str();
+            while let Some(pos) = rest.find(is_line_break) {
+                let _ = writeln!(s, "data:{}", &rest[..pos]);
+                let len = if rest[pos..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+                rest = &rest[pos + len..];
+            }
+            let _ = writeln!(s, "data:{}", rest);
+        }
+        s.push('\n');
+        buf.extend_from_slice(s.as_bytes());
+    }
+}
+
+/// `\r\n`, `\r` and `\n` are line terminators of event stream
+fn is_line_break(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+fn first_line(s: &str) -> &str {
+    s.split(is_line_break).next().unwrap_or("")
+}
+
+/// Server-sent events responder
+///
+/// Response has `text/event-stream` content type and is never compressed
+/// by `Compress` middleware. If no event is sent during keep-alive interval
+/// comment line is sent to keep connection alive.
+pub struct Sse<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S, E> Sse<S>
+where
+    S: Stream<Item = Event, Error = E> + 'static,
+    E: Into<Error> + 'static,
+{
+    /// Create responder for stream of events
+    pub fn new(stream: S) -> Self {
+        Sse {
+            stream,
+            keep_alive: Some(KEEP_ALIVE),
+        }
+    }
+
+    /// Set keep-alive interval
+    ///
+    /// By default keep-alive interval is 15 seconds.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Disable keep-alive comments
+    pub fn disable_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+}
+
+impl<S, E> Responder for Sse<S>
+where
+    S: Stream<Item = Event, Error = E> + 'static,
+    E: Into<Error> + 'static,
+{
+    type Error = Error;
+    type Future = Result<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        let keep_alive = self
+            .keep_alive
+            .map(|interval| (interval, Delay::new(Instant::now() + interval)));
+
+        Ok(Response::Ok()
+            .content_type("text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .encoding(ContentEncoding::Identity)
+            .streaming(SseStream {
+                stream: self.stream,
+                keep_alive,
+            }))
+    }
+}
+
+struct SseStream<S> {
+    stream: S,
+    keep_alive: Option<(Duration, Delay)>,
+}
+
+impl<S, E> Stream for SseStream<S>
+where
+    S: Stream<Item = Event, Error = E>,
+    E: Into<Error>,
+{
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, Error> {
+        match self.stream.poll().map_err(|e| e.into())? {
+            Async::Ready(Some(event)) => {
+                if let Some((interval, ref mut delay)) = self.keep_alive {
+                    delay.reset(Instant::now() + interval);
+                }
+                let mut buf = BytesMut::new();
+                event.encode(&mut buf);
+                return Ok(Async::Ready(Some(buf.freeze())));
+            }
+            Async::Ready(None) => return Ok(Async::Ready(None)),
+            Async::NotReady => (),
+        }
+
+        if let Some((interval, ref mut delay)) = self.keep_alive {
+            match delay.poll() {
+                Ok(Async::Ready(_)) => {
+                    delay.reset(Instant::now() + interval);
+                    return Ok(Async::Ready(Some(Bytes::from_static(
+                        KEEP_ALIVE_COMMENT,
+                    ))));
+                }
+                Ok(Async::NotReady) => (),
+                Err(e) => {
+                    log::error!("Sse keep-alive timer error: {}", e);
+                    self.keep_alive = None;
+                }
+            }
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+/// Extract `Last-Event-ID` header sent by reconnecting client
+///
+/// Use `Option<LastEventId>` if header is optional.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LastEventId(String);
+
+impl LastEventId {
+    /// Unwrap into inner `String` value
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for LastEventId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequest for LastEventId {
+    type Config = ();
+    type Error = Error;
+    type Future = Result<Self, Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let name = HeaderName::from_static("last-event-id");
+        match req.headers().get(&name).map(|val| val.to_str()) {
+            Some(Ok(id)) => Ok(LastEventId(id.to_owned())),
+            Some(Err(_)) => Err(ErrorBadRequest("Invalid Last-Event-ID header")),
+            None => Err(ErrorBadRequest("Missing Last-Event-ID header")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::test::{block_on, TestRequest};
+
+    #[test]
+    fn test_event() {
+        let mut buf = BytesMut::new();
+        Event::new()
+            .comment("comment")
+            .event("update")
+            .id("1")
+            .retry(Duration::from_secs(2))
+            .data("line1\nline2")
+            .encode(&mut buf);
+        assert_eq!(
+            &buf[..],
+            &b":comment\nevent:update\nid:1\nretry:2000\ndata:line1\ndata:line2\n\n"[..]
+        );
+
+        let mut buf = BytesMut::new();
+        Event::new().data("text").id("2\n3").encode(&mut buf);
+        assert_eq!(&buf[..], &b"id:2\ndata:text\n\n"[..]);
+    }
+
+    #[test]
+    fn test_event_data() {
+        let encode = |data: &str| {
+            let mut buf = BytesMut::new();
+            Event::new().data(data).encode(&mut buf);
+            buf
+        };
+        assert_eq!(&encode("")[..], &b"data:\n\n"[..]);
+        assert_eq!(&encode("a\n")[..], &b"data:a\ndata:\n\n"[..]);
+        assert_eq!(&encode("a\r\nb")[..], &b"data:a\ndata:b\n\n"[..]);
+        assert_eq!(&encode("a\n\nb")[..], &b"data:a\ndata:\ndata:b\n\n"[..]);
+        assert_eq!(&encode("a\r\r\nb")[..], &b"data:a\ndata:\ndata:b\n\n"[..]);
+        assert_eq!(
+            &encode("x\revent:evil")[..],
+            &b"data:x\ndata:event:evil\n\n"[..]
+        );
+    }
+
+    #[test]
+    fn test_event_single_line_fields() {
+        let mut buf = BytesMut::new();
+        Event::new()
+            .comment("c\r:evil")
+            .event("e\revent:evil")
+            .id("1\rid:evil")
+            .encode(&mut buf);
+        assert_eq!(&buf[..], &b":c\nevent:e\nid:1\n\n"[..]);
+    }
+
+    #[test]
+    fn test_sse() {
+        let req = TestRequest::default().to_http_request();
+        let events = stream::iter_ok::<_, Error>(vec![
+            Event::new().data("1"),
+            Event::new().data("2"),
+        ]);
+        let mut resp = Sse::new(events).respond_to(&req).unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+
+        let body = block_on(resp.take_body().concat2()).unwrap();
+        assert_eq!(&body[..], &b"data:1\n\ndata:2\n\n"[..]);
+    }
+
+    #[test]
+    fn test_last_event_id() {
+        let (req, mut pl) =
+            TestRequest::with_header("last-event-id", "10").to_http_parts();
+        let id = LastEventId::from_request(&req, &mut pl).unwrap();
+        assert_eq!(&*id, "10");
+
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        assert!(LastEventId::from_request(&req, &mut pl).is_err());
+        let id = block_on(Option::<LastEventId>::from_request(&req, &mut pl)).unwrap();
+        assert!(id.is_none());
+    }
+}
@@ -0,0 +1,457 @@
+//! Websockets support without actors
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix_web::{web, App, Error, HttpRequest, HttpResponse};
+//! use actix_web::web::ws;
+//! use futures::{Future, Sink, Stream};
+//!
+//! fn index(req: HttpRequest, payload: web::Payload) -> Result<HttpResponse, Error> {
+//!     let (res, sink, stream) = ws::upgrade(&req, payload)?;
+//!
+//!     // echo text messages back to the client
+//!     let echo = stream
+//!         .take_while(|frame| match frame {
+//!             ws::Frame::Close(_) => Ok(false),
+//!             _ => Ok(true),
+//!         })
+//!         .filter_map(|frame| match frame {
+//!             ws::Frame::Text(Some(text)) => {
+//!                 String::from_utf8(text.to_vec()).ok().map(ws::Message::Text)
+//!             }
+//!             _ => None,
+//!         })
+//!         .forward(sink)
+//!         .map(|_| ())
+//!         .map_err(|_| ());
+//!     actix_rt::spawn(echo);
+//!
+//!     Ok(res)
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().service(web::resource("/ws/").to(index));
+//! }
+//! ```
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{handshake, Codec};
+use bytes::{Bytes, BytesMut};
+use futures::sync::mpsc;
+use futures::task::AtomicTask;
+use futures::{Async, Poll, Sink, StartSend, Stream};
+
+pub use actix_http::ws::{
+    CloseCode, CloseReason, Frame, HandshakeError, Item, Message, ProtocolError,
+};
+
+use crate::request::HttpRequest;
+use crate::types::Payload;
+use crate::HttpResponse;
+
+/// Max number of messages buffered by `WsSink`
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Do websocket handshake.
+///
+/// Returns handshake response, sink for outgoing messages and stream
+/// of incoming frames. Response must be returned from the handler,
+/// sink and stream could be driven by spawned tasks.
+///
+/// Close frame received from the peer is answered automatically, with the
+/// same close code. Once every copy of the sink is dropped, close frame
+/// without reason is sent. Connection gets closed after both sides sent
+/// close frames, or after close frame is sent and the stream is dropped
+/// or ended.
+pub fn upgrade(
+    req: &HttpRequest,
+    payload: Payload,
+) -> Result<(HttpResponse, WsSink, WsStream), HandshakeError> {
+    let mut res = handshake(req.head())?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let state = Rc::new(RefCell::new(CloseState::default()));
+
+    let res = res.streaming(WsBody {
+        rx,
+        codec: Codec::new(),
+        buf: BytesMut::new(),
+        state: state.clone(),
+    });
+    let sink = WsSink { tx };
+    let stream = WsStream {
+        payload,
+        codec: Codec::new(),
+        buf: BytesMut::new(),
+        eof: false,
+        state,
+    };
+    Ok((res, sink, stream))
+}
+
+/// State of close handshake, shared by the stream and the response body
+#[derive(Default)]
+struct CloseState {
+    /// Close frame is received, or no more frames could be received
+    received: bool,
+    /// Reason of received close frame, echoed in the reply
+    reason: Option<CloseReason>,
+    /// Stream is dropped, close frame of the peer is not awaited
+    dropped: bool,
+    /// Close frame is sent
+    sent: bool,
+    /// Response body task, woken once close frame is received
+    task: AtomicTask,
+}
+
+impl CloseState {
+    fn received(&mut self, reason: Option<CloseReason>) {
+        if !self.received {
+            self.received = true;
+            self.reason = reason;
+            self.task.notify();
+        }
+    }
+
+    fn dropped(&mut self) {
+        self.dropped = true;
+        self.task.notify();
+    }
+}
+
+/// Sink for outgoing websocket messages
+///
+/// Sink is bounded, `start_send()` returns `AsyncSink::NotReady`
+/// if peer does not read sent messages fast enough.
+#[derive(Clone)]
+pub struct WsSink {
+    tx: mpsc::Sender<Message>,
+}
+
+impl Sink for WsSink {
+    type SinkItem = Message;
+    type SinkError = ProtocolError;
+
+    fn start_send(&mut self, msg: Message) -> StartSend<Message, ProtocolError> {
+        self.tx.start_send(msg).map_err(|_| closed())
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), ProtocolError> {
+        self.tx.poll_complete().map_err(|_| closed())
+    }
+
+    fn close(&mut self) -> Poll<(), ProtocolError> {
+        self.tx.close().map_err(|_| closed())
+    }
+}
+
+fn closed() -> ProtocolError {
+    ProtocolError::Io(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "Websocket connection is closed",
+    ))
+}
+
+/// Stream of incoming websocket frames
+///
+/// Payload is read only if buffered data does not contain complete frame,
+/// so a peer that sends faster than frames are consumed gets throttled.
+/// Stream ends after close frame.
+pub struct WsStream {
+    payload: Payload,
+    codec: Codec,
+    buf: BytesMut,
+    eof: bool,
+    state: Rc<RefCell<CloseState>>,
+}
+
+impl Stream for WsStream {
+    type Item = Frame;
+    type Error = ProtocolError;
+
+    fn poll(&mut self) -> Poll<Option<Frame>, ProtocolError> {
+        if self.state.borrow().received {
+            return Ok(Async::Ready(None));
+        }
+
+        loop {
+            match self.codec.decode(&mut self.buf) {
+                Ok(Some(frame)) => {
+                    if let Frame::Close(ref reason) = frame {
+                        self.state.borrow_mut().received(reason.clone());
+                    }
+                    return Ok(Async::Ready(Some(frame)));
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    self.state.borrow_mut().received(None);
+                    return Err(e);
+                }
+            }
+            if self.eof {
+                self.state.borrow_mut().received(None);
+                return Ok(Async::Ready(None));
+            }
+
+            match self.payload.poll() {
+                Ok(Async::Ready(Some(chunk))) => self.buf.extend_from_slice(&chunk[..]),
+                Ok(Async::Ready(None)) => self.eof = true,
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    self.state.borrow_mut().received(None);
+                    return Err(ProtocolError::Io(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("{}", e),
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for WsStream {
+    fn drop(&mut self) {
+        // nobody reads frames, do not wait for close frame of the peer
+        // once close frame is sent
+        self.state.borrow_mut().dropped();
+    }
+}
+
+/// Response body, encodes messages sent to `WsSink`
+struct WsBody {
+    rx: mpsc::Receiver<Message>,
+    codec: Codec,
+    buf: BytesMut,
+    state: Rc<RefCell<CloseState>>,
+}
+
+impl Stream for WsBody {
+    type Item = Bytes;
+    type Error = ProtocolError;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, ProtocolError> {
+        let mut state = self.state.borrow_mut();
+        state.task.register();
+
+        // nothing could be sent after close frame
+        while !state.sent {
+            if state.received {
+                // reply to close frame of the peer
+                let reason = state.reason.take();
+                self.codec.encode(Message::Close(reason), &mut self.buf)?;
+                state.sent = true;
+                break;
+            }
+            match self.rx.poll() {
+                Ok(Async::Ready(Some(msg))) => {
+                    state.sent = match msg {
+                        Message::Close(_) => true,
+                        _ => false,
+                    };
+                    self.codec.encode(msg, &mut self.buf)?;
+                }
+                Ok(Async::Ready(None)) | Err(_) => {
+                    self.codec.encode(Message::Close(None), &mut self.buf)?;
+                    state.sent = true;
+                }
+                Ok(Async::NotReady) => break,
+            }
+        }
+
+        if !self.buf.is_empty() {
+            Ok(Async::Ready(Some(self.buf.take().freeze())))
+        } else if state.sent && (state.received || state.dropped) {
+            Ok(Async::Ready(None))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_http::error::PayloadError;
+    use futures::stream;
+
+    use super::*;
+    use crate::dev::{self, MessageBody};
+    use crate::http::header;
+    use crate::test::TestRequest;
+    use crate::FromRequest;
+
+    fn encode(msgs: Vec<Message>) -> Bytes {
+        let mut codec = Codec::new().client_mode();
+        let mut buf = BytesMut::new();
+        for msg in msgs {
+            codec.encode(msg, &mut buf).unwrap();
+        }
+        buf.freeze()
+    }
+
+    fn ws_stream(chunks: Vec<Bytes>, reads: Rc<Cell<usize>>) -> WsStream {
+        let chunks = stream::iter_ok::<_, PayloadError>(chunks)
+            .inspect(move |_| reads.set(reads.get() + 1));
+        let (req, _) = TestRequest::default().to_http_parts();
+        let mut payload: dev::Payload = dev::Payload::Stream(Box::new(chunks));
+        WsStream {
+            payload: Payload::from_request(&req, &mut payload).unwrap(),
+            codec: Codec::new(),
+            buf: BytesMut::new(),
+            eof: false,
+            state: Rc::new(RefCell::new(CloseState::default())),
+        }
+    }
+
+    #[test]
+    fn test_stream_reads_one_frame() {
+        let reads = Rc::new(Cell::new(0));
+        let mut stream = ws_stream(
+            vec![
+                encode(vec![
+                    Message::Text("a".to_string()),
+                    Message::Text("b".to_string()),
+                ]),
+                encode(vec![Message::Close(Some(CloseCode::Normal.into()))]),
+                encode(vec![Message::Text("c".to_string())]),
+            ],
+            reads.clone(),
+        );
+
+        assert_eq!(
+            stream.poll().unwrap(),
+            Async::Ready(Some(Frame::Text(Some(BytesMut::from("a")))))
+        );
+        assert_eq!(reads.get(), 1);
+
+        // buffered frame is returned without reading more payload
+        assert_eq!(
+            stream.poll().unwrap(),
+            Async::Ready(Some(Frame::Text(Some(BytesMut::from("b")))))
+        );
+        assert_eq!(reads.get(), 1);
+
+        assert_eq!(
+            stream.poll().unwrap(),
+            Async::Ready(Some(Frame::Close(Some(CloseCode::Normal.into()))))
+        );
+        assert_eq!(reads.get(), 2);
+        assert!(stream.state.borrow().received);
+
+        // nothing is read after close frame
+        assert_eq!(stream.poll().unwrap(), Async::Ready(None));
+        assert_eq!(reads.get(), 2);
+    }
+
+    #[test]
+    fn test_body_close_handshake() {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let state = Rc::new(RefCell::new(CloseState::default()));
+        let mut body = WsBody {
+            rx,
+            codec: Codec::new(),
+            buf: BytesMut::new(),
+            state: state.clone(),
+        };
+        let mut sink = WsSink { tx };
+        let mut client = Codec::new().client_mode();
+
+        crate::test::block_on(futures::lazy(move || {
+            // close frame sent by the application
+            sink.start_send(Message::Close(Some(CloseCode::Away.into())))
+                .unwrap();
+            let chunk = match body.poll().unwrap() {
+                Async::Ready(Some(chunk)) => chunk,
+                _ => panic!(),
+            };
+            let frame = client.decode(&mut BytesMut::from(chunk)).unwrap();
+            assert_eq!(frame, Some(Frame::Close(Some(CloseCode::Away.into()))));
+
+            // body waits for close frame of the peer
+            assert_eq!(body.poll().unwrap(), Async::NotReady);
+            state.borrow_mut().received(Some(CloseCode::Away.into()));
+            assert_eq!(body.poll().unwrap(), Async::Ready(None));
+            Ok::<_, ()>(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_body_close_reply() {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let state = Rc::new(RefCell::new(CloseState::default()));
+        let mut body = WsBody {
+            rx,
+            codec: Codec::new(),
+            buf: BytesMut::new(),
+            state: state.clone(),
+        };
+        let mut client = Codec::new().client_mode();
+
+        crate::test::block_on(futures::lazy(move || {
+            assert_eq!(body.poll().unwrap(), Async::NotReady);
+
+            // close frame of the peer is echoed
+            state.borrow_mut().received(Some(CloseCode::Normal.into()));
+            let chunk = match body.poll().unwrap() {
+                Async::Ready(Some(chunk)) => chunk,
+                _ => panic!(),
+            };
+            let frame = client.decode(&mut BytesMut::from(chunk)).unwrap();
+            assert_eq!(frame, Some(Frame::Close(Some(CloseCode::Normal.into()))));
+            assert_eq!(body.poll().unwrap(), Async::Ready(None));
+
+            // messages sent after close frame are dropped
+            let mut tx = tx;
+            tx.try_send(Message::Text("text".to_string())).unwrap();
+            assert_eq!(body.poll().unwrap(), Async::Ready(None));
+            Ok::<_, ()>(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_stream_dropped() {
+        let (req, mut pl) = TestRequest::default()
+            .header(header::UPGRADE, "websocket")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "x3JJHMbDL1EzLkh9GBhXDw==")
+            .to_http_parts();
+        let payload = Payload::from_request(&req, &mut pl).unwrap();
+        let (mut res, mut sink, stream) = upgrade(&req, payload).unwrap();
+        let mut body = res.take_body();
+        let mut client = Codec::new().client_mode();
+
+        crate::test::block_on(futures::lazy(move || {
+            // send-only handler, connection is open while sink is alive
+            drop(stream);
+            assert_eq!(body.poll_next().unwrap(), Async::NotReady);
+
+            sink.start_send(Message::Text("text".to_string())).unwrap();
+            let chunk = match body.poll_next().unwrap() {
+                Async::Ready(Some(chunk)) => chunk,
+                _ => panic!(),
+            };
+            let frame = client.decode(&mut BytesMut::from(chunk)).unwrap();
+            assert_eq!(frame, Some(Frame::Text(Some(BytesMut::from("text")))));
+            assert_eq!(body.poll_next().unwrap(), Async::NotReady);
+
+            // close frame of the peer is not awaited
+            drop(sink);
+            let chunk = match body.poll_next().unwrap() {
+                Async::Ready(Some(chunk)) => chunk,
+                _ => panic!(),
+            };
+            let frame = client.decode(&mut BytesMut::from(chunk)).unwrap();
+            assert_eq!(frame, Some(Frame::Close(None)));
+            assert_eq!(body.poll_next().unwrap(), Async::Ready(None));
+            Ok::<_, ()>(())
+        }))
+        .unwrap();
+    }
+}
@@ -80,6 +80,72 @@ fn test_start() {
     let _ = sys.stop();
 }
 
+#[cfg(all(unix, feature = "client"))]
+fn check_on_connect(after_bind: bool) {
+    use std::any::Any;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+
+        let srv = HttpServer::new(|| {
+            App::new().service(web::resource("/").route(web::to(
+                |data: web::ConnectionData<&'static str>| Response::Ok().body(*data),
+            )))
+        })
+        .workers(1)
+        .system_exit()
+        .disable_signals();
+        let srv = if after_bind {
+            srv.bind(format!("{}", addr))
+                .unwrap()
+                .on_connect(|_: &dyn Any| "connection")
+        } else {
+            srv.on_connect(|_: &dyn Any| "connection")
+                .bind(format!("{}", addr))
+                .unwrap()
+        };
+        let srv = srv.start();
+
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let client = test::run_on(|| Ok::<_, ()>(awc::Client::default())).unwrap();
+    let mut response =
+        test::block_on(client.get(format!("http://{}", addr)).send()).unwrap();
+    assert!(response.status().is_success());
+    let bytes = test::block_on(response.body()).unwrap();
+    assert_eq!(bytes, actix_web::web::Bytes::from_static(b"connection"));
+
+    // stop
+    let _ = srv.stop(false);
+
+    thread::sleep(Duration::from_millis(100));
+    let _ = sys.stop();
+}
+
+#[test]
+#[cfg(all(unix, feature = "client"))]
+fn test_on_connect() {
+    check_on_connect(false);
+}
+
+#[test]
+#[cfg(all(unix, feature = "client"))]
+fn test_on_connect_after_bind() {
+    check_on_connect(true);
+}
+
+#[test]
+#[should_panic(expected = "http/2 max frame size must be between")]
+fn test_h2_max_frame_size_range() {
+    let _ = HttpServer::new(|| App::new()).h2_max_frame_size(1024);
+}
+
 #[cfg(feature = "ssl")]
 fn ssl_acceptor() -> std::io::Result<SslAcceptorBuilder> {
     use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
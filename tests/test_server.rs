@@ -719,6 +719,55 @@ fn test_brotli_encoding_large() {
     assert_eq!(bytes, Bytes::from(data));
 }
 
+#[test]
+#[cfg(feature = "zstd")]
+fn test_zstd_encoding() {
+    let mut srv = TestServer::new(move || {
+        h1::H1Service::new(
+            App::new()
+                .wrap(Compress::new(ContentEncoding::Zstd))
+                .service(
+                    web::resource("/").route(web::to(move || Response::Ok().body(STR))),
+                )
+                .service(
+                    web::resource("/echo")
+                        .route(web::to(move |body: Bytes| Response::Ok().body(body))),
+                ),
+        )
+    });
+
+    // compressed response
+    let mut response = srv
+        .block_on(
+            srv.get("/")
+                .header(ACCEPT_ENCODING, "zstd")
+                .no_decompress()
+                .send(),
+        )
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+    let enc = srv.block_on(response.body()).unwrap();
+    assert_ne!(enc, Bytes::from_static(STR.as_ref()));
+
+    // automatic response decompression
+    let mut response = srv
+        .block_on(srv.get("/").header(ACCEPT_ENCODING, "zstd").send())
+        .unwrap();
+    let bytes = srv.block_on(response.body()).unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // request payload decompression
+    let request = srv
+        .post("/echo")
+        .header(CONTENT_ENCODING, "zstd")
+        .send_body(enc);
+    let mut response = srv.block_on(request).unwrap();
+    assert!(response.status().is_success());
+    let bytes = srv.block_on(response.body()).unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
 // #[cfg(all(feature = "brotli", feature = "ssl"))]
 // #[test]
 // fn test_brotli_encoding_large_ssl() {
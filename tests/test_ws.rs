@@ -0,0 +1,111 @@
+use actix_http::HttpService;
+use actix_http_test::TestServer;
+use actix_web::web::ws;
+use actix_web::{web, App, Error, HttpRequest};
+use bytes::{Bytes, BytesMut};
+use futures::{Future, Sink, Stream};
+
+fn echo(req: HttpRequest, payload: web::Payload) -> Result<web::HttpResponse, Error> {
+    let (res, sink, stream) = ws::upgrade(&req, payload)?;
+
+    actix_rt::spawn(
+        stream
+            .map(|frame| match frame {
+                ws::Frame::Text(text) => {
+                    ws::Message::Text(String::from_utf8(text.unwrap().to_vec()).unwrap())
+                }
+                ws::Frame::Binary(bin) => ws::Message::Binary(bin.unwrap().freeze()),
+                ws::Frame::Ping(msg) => ws::Message::Pong(msg),
+                ws::Frame::Close(reason) => ws::Message::Close(reason),
+                _ => ws::Message::Nop,
+            })
+            .forward(sink)
+            .map(|_| ())
+            .map_err(|_| ()),
+    );
+    Ok(res)
+}
+
+#[test]
+fn test_upgrade() {
+    let mut srv = TestServer::new(|| {
+        HttpService::new(App::new().service(web::resource("/").to(echo)))
+    });
+
+    let framed = srv.ws().unwrap();
+    let framed = srv
+        .block_on(framed.send(ws::Message::Text("text".to_string())))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(item, Some(ws::Frame::Text(Some(BytesMut::from("text")))));
+
+    let framed = srv
+        .block_on(framed.send(ws::Message::Binary("text".into())))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(
+        item,
+        Some(ws::Frame::Binary(Some(Bytes::from_static(b"text").into())))
+    );
+
+    let framed = srv
+        .block_on(framed.send(ws::Message::Ping("text".into())))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(item, Some(ws::Frame::Pong("text".to_string())));
+
+    let framed = srv
+        .block_on(framed.send(ws::Message::Close(Some(ws::CloseCode::Normal.into()))))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(
+        item,
+        Some(ws::Frame::Close(Some(ws::CloseCode::Normal.into())))
+    );
+
+    // server closes connection after close frame
+    let (item, _) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert!(item.is_none());
+}
+
+#[test]
+fn test_close_reply() {
+    let mut srv = TestServer::new(|| {
+        HttpService::new(App::new().service(web::resource("/").to(
+            |req: HttpRequest, payload: web::Payload| {
+                // frames are consumed, nothing is sent back
+                let (res, sink, stream) = ws::upgrade(&req, payload)?;
+                actix_rt::spawn(stream.for_each(|_| Ok(())).map_err(|_| ()).then(
+                    move |res| {
+                        drop(sink);
+                        res
+                    },
+                ));
+                Ok::<_, Error>(res)
+            },
+        )))
+    });
+
+    let framed = srv.ws().unwrap();
+    let framed = srv
+        .block_on(framed.send(ws::Message::Close(Some(ws::CloseCode::Away.into()))))
+        .unwrap();
+    let (item, framed) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert_eq!(
+        item,
+        Some(ws::Frame::Close(Some(ws::CloseCode::Away.into())))
+    );
+
+    let (item, _) = srv.block_on(framed.into_future()).map_err(|_| ()).unwrap();
+    assert!(item.is_none());
+}
+
+#[test]
+fn test_handshake_error() {
+    let mut srv = TestServer::new(|| {
+        HttpService::new(App::new().service(web::resource("/").to(echo)))
+    });
+
+    let response = srv.block_on(srv.get("/").send()).unwrap();
+    assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}